@@ -0,0 +1,90 @@
+//! The L1 bridge of the `reth` binary, anchoring the state roots of the committed blocks in the
+//! state root contract on L1.
+//!
+//! Only compiled with the `l1-bridge` feature. Other binaries can install the bridge on their
+//! node with the [`StateRootBridge`].
+//!
+//! The bridge logs under the `t1::bridge` target, so its verbosity can be set apart from the
+//! node's, e.g. with `RUST_LOG=t1::bridge=debug` or `--log.stdout.filter t1::bridge=debug`.
+//!
+//! The ExEx is generic over the node it's installed on. It only uses the node's provider, task
+//! executor and chain spec, and the [`Chain`](reth_execution_types::Chain)s of its notifications,
+//! so any node built on the reth primitives can run it, e.g. an OP-stack node. Only the `reth`
+//! binary and the `t1` commands are tied to the [`EthereumNode`](reth_node_ethereum::EthereumNode).
+
+use alloy_sol_types::sol;
+
+mod batch;
+mod client;
+mod commands;
+mod config;
+mod contract;
+mod decode;
+mod exex;
+mod handler;
+mod history;
+mod rpc;
+mod signer;
+#[cfg(test)]
+mod test_utils;
+mod watermark;
+
+pub use batch::RootBatch;
+#[cfg(any(test, feature = "test-utils"))]
+pub use client::MockStateRootSink;
+pub use client::{
+    estimate_eip1559_fees, scale_gas_estimate, BalanceStatus, BreakerState, CircuitBreaker,
+    Eip1559Fees, GasEstimateCache, L1Client, L1Metrics, L1RpcTimeout, L1SubmitError, NonceManager,
+    RateLimiter, StateRootSink,
+};
+pub use commands::{
+    BackfillCommand, HistoryCommand, ReanchorCommand, SelftestCheck, SelftestCommand, T1Cli,
+    T1Commands,
+};
+pub use config::{
+    AnchoredRoot, ExExArgs, ExExConfig, ExExFileConfig, HeaderReceiptsRoot, HeaderStateRoot,
+    HeaderWithdrawalsRoot, L1Destination, L1Endpoint, PrintConfig, StateRootProvider, EXEX_NAME,
+};
+pub use contract::{blob_sidecar, StateDiff, StateRootCall, StateRootContract, TxParams};
+pub use decode::{decode_rollup_events, RollupEvent};
+pub use exex::{exex_init, reorg_depth, Discrepancy, StateRootBridge, StateRootBridgeBuilder};
+pub use handler::{RollupEventHandler, StateRootAnchor};
+pub use history::{AnchorEvents, SubmissionHistory, SubmissionRecord, L1_HISTORY_FILE};
+pub use rpc::{
+    AnchorState, BridgeStatus, BridgeStatusHandle, RootStatus, T1Api, T1ApiServer, T1Rpc,
+};
+pub use signer::{L1Signer, LocalSigner, RemoteSigner};
+pub use watermark::Watermark;
+
+sol!(
+    #[allow(missing_docs)]
+    CounterContract,
+    "counter_abi.json"
+);
+sol!(
+    #[allow(missing_docs)]
+    StateRootAbi,
+    "state_root_abi.json"
+);
+sol! {
+    /// New value of a storage slot of an [`AccountDiff`].
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(missing_docs)]
+    struct StorageDiff {
+        bytes32 slot;
+        bytes32 value;
+    }
+
+    /// Change of an account in a [`StateDiff`]. A destroyed account has no nonce, balance or
+    /// code.
+    #[derive(Debug, PartialEq, Eq)]
+    #[allow(missing_docs)]
+    struct AccountDiff {
+        address account;
+        bool destroyed;
+        uint64 nonce;
+        uint256 balance;
+        bytes32 codeHash;
+        StorageDiff[] storage;
+    }
+}
@@ -0,0 +1,360 @@
+//! Batching of the state roots queued for L1.
+
+use super::contract::StateDiff;
+use alloy_primitives::{BlockNumber, B256};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::time::Instant;
+use tracing::debug;
+
+/// State roots of committed blocks queued to be anchored on L1 together.
+///
+/// The queued state roots are anchored once the batch is full or the oldest of them has been
+/// queued for longer than the flush interval, in the order of their block numbers. A state root
+/// queued below the last queued one, e.g. when a failed submission is queued again, is inserted in
+/// order instead of being anchored after the later ones, since contracts may require the heights
+/// to increase monotonically.
+///
+/// With a non-zero submit interval, only the latest queued state root is kept and it isn't due
+/// before the interval has passed since the previous submission, coalescing bursts of commits.
+///
+/// The batch is bounded by applying backpressure: once it holds the maximum number of queued
+/// state roots it's saturated, and the ExEx stops consuming notifications until some of them are
+/// anchored. No state root is dropped, since contracts that hold a state root per height need all
+/// of them. A single committed chain is queued in full, so it may overshoot the bound.
+///
+/// A batch [`Self::with_finalized_only`] holds back the state roots above the finalized block,
+/// see [`Self::set_finalized`]. They count towards the bound, but aren't ready to be anchored.
+/// Likewise, a batch [`Self::with_min_depth`] holds back the state roots of the blocks less than
+/// the minimum depth below the committed tip, see [`Self::set_tip`].
+///
+/// A state root queued with a [`StateDiff`], see [`Self::push_diff`], is anchored on its own and
+/// never coalesced, as its diff would be lost.
+#[derive(Debug)]
+pub struct RootBatch {
+    /// Queued block numbers and state roots, with the time they were queued at.
+    roots: Vec<(BlockNumber, B256, Instant)>,
+    max_size: usize,
+    flush_interval: Duration,
+    submit_interval: Duration,
+    /// Number of queued state roots the batch is saturated at.
+    max_queued: usize,
+    /// Time the queued state roots were last taken to be submitted at.
+    submitted_at: Option<Instant>,
+    /// Time the queued state roots aren't due before, see [`Self::pause_until`].
+    paused_until: Option<Instant>,
+    /// Whether only the state roots of finalized blocks are ready to be anchored.
+    finalized_only: bool,
+    /// Number of the finalized block, see [`Self::set_finalized`].
+    finalized: Option<BlockNumber>,
+    /// Number of blocks a queued block has to be below the tip to be ready to be anchored.
+    min_depth: u64,
+    /// Number of the committed tip, see [`Self::set_tip`].
+    tip: Option<BlockNumber>,
+    /// State diffs of the queued blocks that have one, see [`Self::push_diff`].
+    diffs: BTreeMap<BlockNumber, StateDiff>,
+}
+
+impl RootBatch {
+    /// Creates an empty batch of at most `max_size` state roots.
+    pub fn new(max_size: usize, flush_interval: Duration) -> Self {
+        Self {
+            roots: Vec::new(),
+            max_size: max_size.max(1),
+            flush_interval,
+            submit_interval: Duration::ZERO,
+            max_queued: usize::MAX,
+            submitted_at: None,
+            paused_until: None,
+            finalized_only: false,
+            finalized: None,
+            min_depth: 0,
+            tip: None,
+            diffs: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the minimum time between two submissions, coalescing the state roots queued in
+    /// between into the latest one.
+    pub const fn with_submit_interval(mut self, submit_interval: Duration) -> Self {
+        self.submit_interval = submit_interval;
+        self
+    }
+
+    /// Bounds the batch to `max_queued` state roots, see [`Self::is_saturated`].
+    pub fn with_max_queued(mut self, max_queued: usize) -> Self {
+        self.max_queued = max_queued.max(1);
+        self
+    }
+
+    /// Only considers the state roots of blocks at or below the finalized block ready to be
+    /// anchored, if `finalized_only` is set.
+    pub const fn with_finalized_only(mut self, finalized_only: bool) -> Self {
+        self.finalized_only = finalized_only;
+        self
+    }
+
+    /// Sets the number of the finalized block, if any block is finalized yet.
+    pub fn set_finalized(&mut self, finalized: Option<BlockNumber>) {
+        self.finalized = finalized;
+    }
+
+    /// Only considers the state roots of blocks at least `min_depth` below the committed tip ready
+    /// to be anchored, see [`ExExConfig::min_depth`](super::config::ExExConfig::min_depth).
+    pub const fn with_min_depth(mut self, min_depth: u64) -> Self {
+        self.min_depth = min_depth;
+        self
+    }
+
+    /// Sets the number of the committed tip, which the minimum depth is counted from.
+    pub fn set_tip(&mut self, tip: BlockNumber) {
+        self.tip = Some(tip);
+    }
+
+    /// Queues the state root of the given block.
+    ///
+    /// With a submit interval, the state root replaces the queued one, which it supersedes, but
+    /// keeps the time it was queued at. A state root of a block at or below the last queued one
+    /// is inserted in order, see [`Self::insert`].
+    pub fn push(&mut self, number: BlockNumber, state_root: B256) {
+        if self.roots.last().is_some_and(|(last, _, _)| *last >= number) {
+            self.insert(number, state_root);
+            return
+        }
+        let mut queued_at = Instant::now();
+        let replaceable =
+            self.roots.last().is_some_and(|(last, _, _)| !self.diffs.contains_key(last));
+        if !self.submit_interval.is_zero() && replaceable {
+            if let Some((_, _, replaced_at)) = self.roots.pop() {
+                queued_at = replaced_at;
+            }
+        }
+        self.roots.push((number, state_root, queued_at));
+    }
+
+    /// Queues the state root of the given block with the given [`StateDiff`] leading to it.
+    ///
+    /// If the state root of the block is already queued, the diff is attached to it instead.
+    pub fn push_diff(&mut self, number: BlockNumber, state_root: B256, diff: StateDiff) {
+        if self.roots.last().is_some_and(|(last, _, _)| *last >= number) {
+            self.insert(number, state_root);
+        } else {
+            self.roots.push((number, state_root, Instant::now()));
+        }
+        self.diffs.insert(number, diff);
+    }
+
+    /// Queues the state root of a block at or below the last queued one in the order of the block
+    /// numbers, replacing the queued state root of the same block, if any.
+    fn insert(&mut self, number: BlockNumber, state_root: B256) {
+        let index = self.roots.partition_point(|(queued, _, _)| *queued < number);
+        match self.roots.get_mut(index) {
+            Some((queued, root, _)) if *queued == number => *root = state_root,
+            _ => {
+                debug!(target: "t1::bridge", number, "Queued state root out of order");
+                self.roots.insert(index, (number, state_root, Instant::now()));
+            }
+        }
+    }
+
+    /// Returns the [`StateDiff`] the state root of the given block was queued with, if any.
+    pub fn diff(&self, number: BlockNumber) -> Option<&StateDiff> {
+        self.diffs.get(&number)
+    }
+
+    /// Returns the number of queued state roots.
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// Returns `true` if no state roots are queued.
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Returns the number of the oldest queued state roots that are ready to be anchored, which
+    /// is all of them unless the batch is [`Self::with_finalized_only`] or
+    /// [`Self::with_min_depth`].
+    pub fn ready(&self) -> usize {
+        let finalized = match self.finalized {
+            _ if !self.finalized_only => BlockNumber::MAX,
+            Some(finalized) => finalized,
+            None => return 0,
+        };
+        let deep = match self.tip {
+            _ if self.min_depth == 0 => BlockNumber::MAX,
+            Some(tip) if tip >= self.min_depth => tip - self.min_depth,
+            _ => return 0,
+        };
+        let ready = finalized.min(deep);
+        self.roots.iter().take_while(|(number, _, _)| *number <= ready).count()
+    }
+
+    /// Returns `true` if at least a full batch of state roots is ready to be anchored.
+    pub fn is_full(&self) -> bool {
+        self.ready() >= self.max_size
+    }
+
+    /// Returns `true` if the maximum number of state roots is queued, and no more should be
+    /// queued before some of them are anchored.
+    pub fn is_saturated(&self) -> bool {
+        self.roots.len() >= self.max_queued
+    }
+
+    /// Returns the time the queued state roots are due to be anchored at, if any are ready.
+    pub fn deadline(&self) -> Option<Instant> {
+        if self.ready() == 0 {
+            return None
+        }
+        let (_, _, queued_at) = self.roots.first()?;
+        let mut deadline = *queued_at + self.flush_interval;
+        if let Some(submitted_at) = self.submitted_at {
+            deadline = deadline.max(submitted_at + self.submit_interval);
+        }
+        if let Some(paused_until) = self.paused_until {
+            deadline = deadline.max(paused_until);
+        }
+        Some(deadline)
+    }
+
+    /// Returns `true` if the queued state roots are due to be anchored.
+    pub fn is_due(&self) -> bool {
+        self.deadline().is_some_and(|deadline| deadline <= Instant::now())
+    }
+
+    /// Returns the oldest full batch of ready state roots, or all of them if there are fewer,
+    /// with the time they were queued at.
+    ///
+    /// The state roots stay queued until they are [`Self::remove`]d once anchored, so a
+    /// submission that fails or is cancelled leaves them queued. The submit interval restarts
+    /// from now.
+    pub fn next_batch(&mut self) -> Vec<(BlockNumber, B256, Instant)> {
+        self.submitted_at = Some(Instant::now());
+        let mut len = self.ready().min(self.max_size);
+        // A state root with a diff ends the batch before it, or makes up a batch of its own
+        if let Some(index) =
+            self.roots[..len].iter().position(|(number, _, _)| self.diffs.contains_key(number))
+        {
+            len = index.max(1);
+        }
+        self.roots[..len].to_vec()
+    }
+
+    /// Removes the oldest `count` queued state roots, after the batch returned by
+    /// [`Self::next_batch`] was anchored.
+    pub fn remove(&mut self, count: usize) {
+        for (number, _, _) in self.roots.drain(..count.min(self.roots.len())) {
+            self.diffs.remove(&number);
+        }
+    }
+
+    /// Removes the queued state roots of the blocks at or below `height`, e.g. after a correction
+    /// anchored a later block, returning the number of removed state roots.
+    pub fn remove_through(&mut self, height: BlockNumber) -> usize {
+        let count = self.roots.partition_point(|(number, _, _)| *number <= height);
+        self.remove(count);
+        count
+    }
+
+    /// Removes the queued state roots of the blocks at or above `first`, e.g. after they were
+    /// reorged out, returning the number of removed state roots.
+    pub fn invalidate(&mut self, first: BlockNumber) -> usize {
+        let queued = self.roots.len();
+        self.roots.retain(|(number, _, _)| *number < first);
+        self.diffs.split_off(&first);
+        queued - self.roots.len()
+    }
+
+    /// Defers the deadline of the queued state roots to at least `until`, e.g. while the
+    /// [`CircuitBreaker`](super::client::CircuitBreaker) is open.
+    pub fn pause_until(&mut self, until: Instant) {
+        self.paused_until = Some(until);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_batch_invalidate() {
+        let mut batch = RootBatch::new(10, Duration::ZERO);
+        for number in 1..=5 {
+            batch.push(number, B256::with_last_byte(number as u8));
+        }
+        assert_eq!(batch.invalidate(6), 0);
+        assert_eq!(batch.invalidate(3), 3);
+        let numbers = batch.next_batch().iter().map(|(number, _, _)| *number).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_root_batch_out_of_order() {
+        let mut batch = RootBatch::new(10, Duration::ZERO);
+        for number in [1, 3, 5] {
+            batch.push(number, B256::with_last_byte(number as u8));
+        }
+
+        // A lower block is queued in order, a queued one replaces its state root
+        batch.push(2, B256::with_last_byte(2));
+        batch.push(3, B256::with_last_byte(0x33));
+        let queued = batch.next_batch();
+        let roots = queued.iter().map(|(number, root, _)| (*number, *root)).collect::<Vec<_>>();
+        assert_eq!(
+            roots,
+            vec![
+                (1, B256::with_last_byte(1)),
+                (2, B256::with_last_byte(2)),
+                (3, B256::with_last_byte(0x33)),
+                (5, B256::with_last_byte(5)),
+            ]
+        );
+
+        // So is a diff of a queued block, which is attached to it
+        batch.push_diff(3, B256::with_last_byte(0x33), StateDiff::default());
+        assert_eq!(batch.len(), 4);
+        assert!(batch.diff(3).is_some());
+
+        assert_eq!(batch.remove_through(3), 3);
+        assert!(batch.diff(3).is_none());
+        let numbers = batch.next_batch().iter().map(|(number, _, _)| *number).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![5]);
+    }
+
+    #[test]
+    fn test_root_batch_finalized_only() {
+        let mut batch = RootBatch::new(10, Duration::ZERO).with_finalized_only(true);
+        for number in 1..=5 {
+            batch.push(number, B256::with_last_byte(number as u8));
+        }
+        assert_eq!(batch.ready(), 0);
+        assert!(batch.deadline().is_none());
+        assert!(batch.next_batch().is_empty());
+
+        batch.set_finalized(Some(3));
+        assert_eq!(batch.ready(), 3);
+        assert!(batch.is_due());
+        let numbers = batch.next_batch().iter().map(|(number, _, _)| *number).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_root_batch_min_depth() {
+        let mut batch = RootBatch::new(10, Duration::ZERO).with_min_depth(2);
+        for number in 1..=5 {
+            batch.push(number, B256::with_last_byte(number as u8));
+        }
+        assert_eq!(batch.ready(), 0);
+        batch.set_tip(1);
+        assert_eq!(batch.ready(), 0);
+        assert!(batch.deadline().is_none());
+
+        // Only the blocks at least 2 below the tip are ready, the others stay queued
+        batch.set_tip(5);
+        assert_eq!(batch.ready(), 3);
+        assert!(batch.is_due());
+        let numbers = batch.next_batch().iter().map(|(number, _, _)| *number).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        batch.remove(numbers.len());
+        assert_eq!((batch.len(), batch.ready()), (2, 0));
+    }
+}
@@ -0,0 +1,2466 @@
+//! The [`L1Client`] submitting state roots to L1, with its fee, nonce and gas management.
+
+use super::{
+    config::{load_abi, root_for, ExExConfig, L1Endpoint, StateRootProvider, STATE_ROOT_ABI},
+    contract::{StateDiff, StateRootCall, StateRootContract, TxParams},
+    history::{AnchorEvents, SubmissionHistory, SubmissionRecord},
+    rpc::BridgeStatusHandle,
+    signer::L1Signer,
+};
+use alloy_primitives::{Address, BlockNumber, Bytes, Selector, TxHash, B256, U256};
+use alloy_provider::RootProvider;
+use alloy_rpc_client::RpcClient;
+use alloy_rpc_types::{AccessList, FeeHistory};
+use alloy_transport::{
+    BoxTransport, Transport, TransportError, TransportErrorKind, TransportResult,
+};
+use async_trait::async_trait;
+use backon::{ExponentialBuilder, Retryable};
+use parking_lot::Mutex;
+use reth_metrics::{
+    metrics::{Counter, Gauge, Histogram},
+    Metrics,
+};
+use reth_primitives::Header;
+use std::{
+    collections::VecDeque,
+    fmt,
+    mem::{self, Discriminant},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::Instant;
+use tracing::{debug, error, info, warn};
+
+/// Factor the delay between two L1 submission attempts grows by.
+const L1_SUBMIT_RETRY_FACTOR: f32 = 2.0;
+
+/// Metrics of the L1 bridge ExEx.
+#[derive(Metrics)]
+#[metrics(scope = "exex.l1")]
+pub struct L1Metrics {
+    /// The total number of state roots anchored on L1
+    pub state_roots_submitted_total: Counter,
+    /// The total number of failed attempts to submit state roots to L1
+    pub submission_failures_total: Counter,
+    /// Time from receiving a committed block to its state root being confirmed on L1
+    pub submission_latency_seconds: Histogram,
+    /// The number of blocks between the local tip and the last L1-anchored block
+    pub anchor_gap: Gauge,
+    /// The total number of health checks that found the L1 anchor too far behind the local tip
+    pub anchor_lag_warnings_total: Counter,
+    /// The balance of the L1 account signing the submissions, in wei
+    pub account_balance_wei: Gauge,
+    /// The total number of balance checks that found the L1 account balance low
+    pub low_balance_warnings_total: Counter,
+    /// Time spent decoding the Counter events of a committed chain
+    pub decode_duration_seconds: Histogram,
+    /// The total number of committed blocks whose logs were decoded
+    pub decoded_blocks_total: Counter,
+    /// The total number of receipt logs scanned for Counter events
+    pub scanned_logs_total: Counter,
+    /// The total number of Counter events decoded
+    pub decoded_events_total: Counter,
+    /// The total number of confirmed submissions whose state root the contract doesn't hold
+    pub anchored_root_mismatches_total: Counter,
+    /// The number of blocks rolled back by the reorgs of the local chain
+    pub reorg_depth: Histogram,
+    /// The total number of reorgs deeper than [`ExExConfig::reorg_warn_depth`]
+    pub deep_reorgs_total: Counter,
+}
+
+/// Destination the state roots are anchored in.
+///
+/// Implemented by the [`L1Client`], anchoring them in the [`StateRootContract`] on L1. Set with
+/// [`L1Client::with_sink`], another sink, e.g. the `MockStateRootSink` of the `test-utils`
+/// feature, takes the place of the contract, without a live L1.
+///
+/// The submissions to a sink are verified and rechecked after L1 reorgs like those to the
+/// contract, by reading the anchored state roots and transactions back from the sink.
+#[async_trait]
+pub trait StateRootSink: fmt::Debug + Send + Sync {
+    /// Anchors the given state root of the block `number`, returning the hash of the confirmed
+    /// transaction.
+    async fn submit(&self, number: BlockNumber, root: B256) -> Result<TxHash, L1SubmitError>;
+
+    /// Returns the state root the sink holds for the block `number`, or `None` if it holds
+    /// none, see [`L1Client::anchored_state_root`].
+    async fn anchored_state_root(&self, number: BlockNumber) -> TransportResult<Option<B256>>;
+
+    /// Returns `true` if the confirmed transaction of the given hash is still included, i.e. it
+    /// wasn't reorged out, see [`L1Client::recheck_anchored`].
+    async fn is_included(&self, tx_hash: TxHash) -> TransportResult<bool>;
+}
+
+/// [`StateRootSink`] recording the submitted state roots, which can be programmed to fail.
+///
+/// The transaction hash of a submission is its index among all submissions, starting at 1.
+///
+/// Available to other crates as `reth::bridge::MockStateRootSink` with the `test-utils` feature,
+/// e.g. to launch the [`StateRootBridge`](super::exex::StateRootBridge) with
+/// [`StateRootBridgeBuilder::sink`](super::exex::StateRootBridgeBuilder::sink) in their tests.
+#[cfg(any(test, feature = "test-utils"))]
+#[derive(Debug, Default)]
+pub struct MockStateRootSink {
+    submissions: Mutex<Vec<(BlockNumber, B256)>>,
+    failures: Mutex<std::collections::VecDeque<L1SubmitError>>,
+    /// Number of submissions, from the first one, that were reorged out, see [`Self::reorg`].
+    reorged: AtomicU64,
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+impl MockStateRootSink {
+    /// Creates a sink failing the next submissions with the given errors, in order.
+    pub fn failing_with(failures: impl IntoIterator<Item = L1SubmitError>) -> Self {
+        Self { failures: Mutex::new(failures.into_iter().collect()), ..Default::default() }
+    }
+
+    /// Fails the next submission that isn't already programmed to fail with the given error.
+    pub fn fail_next(&self, err: L1SubmitError) {
+        self.failures.lock().push_back(err);
+    }
+
+    /// Returns the block numbers and state roots of the successful submissions, in order,
+    /// including those that were reorged out.
+    pub fn submissions(&self) -> Vec<(BlockNumber, B256)> {
+        self.submissions.lock().clone()
+    }
+
+    /// Reorgs the submissions so far out, so they are neither included nor anchored anymore.
+    pub fn reorg(&self) {
+        self.reorged.store(self.submissions.lock().len() as u64, Ordering::SeqCst);
+    }
+}
+
+#[cfg(any(test, feature = "test-utils"))]
+#[async_trait]
+impl StateRootSink for MockStateRootSink {
+    async fn submit(&self, number: BlockNumber, root: B256) -> Result<TxHash, L1SubmitError> {
+        if let Some(err) = self.failures.lock().pop_front() {
+            return Err(err)
+        }
+        let mut submissions = self.submissions.lock();
+        submissions.push((number, root));
+        Ok(TxHash::from(U256::from(submissions.len())))
+    }
+
+    async fn anchored_state_root(&self, number: BlockNumber) -> TransportResult<Option<B256>> {
+        let reorged = self.reorged.load(Ordering::SeqCst) as usize;
+        let submissions = self.submissions.lock();
+        let anchored = submissions[reorged..].iter().rev().find(|(n, _)| *n == number);
+        Ok(anchored.map(|(_, root)| *root))
+    }
+
+    async fn is_included(&self, tx_hash: TxHash) -> TransportResult<bool> {
+        let index = U256::from_be_bytes(tx_hash.0).saturating_to::<u64>();
+        let submitted = self.submissions.lock().len() as u64;
+        Ok(index > self.reorged.load(Ordering::SeqCst) && index <= submitted)
+    }
+}
+
+/// Client submitting state roots to the L1 [`StateRootContract`].
+///
+/// The transport and the contract are constructed once when the ExEx is initialized and reused
+/// for every submission.
+#[derive(Debug)]
+pub struct L1Client<T: Transport + Clone = BoxTransport> {
+    config: ExExConfig,
+    pub(super) contract: StateRootContract<T>,
+    signer: Arc<dyn L1Signer>,
+    nonces: NonceManager,
+    gas_estimates: GasEstimateCache,
+    pub(super) metrics: L1Metrics,
+    status: BridgeStatusHandle,
+    /// State root of the last confirmed submission.
+    last_state_root: Mutex<Option<B256>>,
+    /// Latest confirmed submissions, at most [`ExExConfig::l1_reorg_depth`], oldest first.
+    pub(super) recent: Mutex<VecDeque<ConfirmedSubmission>>,
+    /// Time of the last re-check of the [`Self::recent`] submissions, see
+    /// [`Self::recheck_anchored_if_due`].
+    last_recheck: Mutex<Option<Instant>>,
+    breaker: Mutex<CircuitBreaker>,
+    rate_limiter: Mutex<RateLimiter>,
+    history: Option<SubmissionHistory>,
+    anchor_events: AnchorEvents,
+    pub(super) state_roots: Arc<dyn StateRootProvider>,
+    /// Sink the state roots are anchored in instead of the [`StateRootContract`], if any.
+    sink: Option<Arc<dyn StateRootSink>>,
+}
+
+impl L1Client {
+    /// Connects to the L1 RPC endpoint of the given configuration over HTTP, WebSocket or IPC,
+    /// see [`L1Endpoint`].
+    pub async fn new(config: ExExConfig) -> eyre::Result<Self> {
+        let endpoint = L1Endpoint::parse(&config.l1_rpc)?;
+        let transport = endpoint.connect().await.map_err(|err| {
+            eyre::eyre!("L1 RPC endpoint {} is unreachable: {err}", config.l1_rpc)
+        })?;
+        Self::with_transport(config, transport).await
+    }
+}
+
+impl<T: Transport + Clone> L1Client<T> {
+    /// Creates a client submitting state roots over the given transport.
+    ///
+    /// Fails if the L1 is unreachable or on another chain than [`ExExConfig::l1_chain_id`]. The
+    /// [`NonceManager`] is initialized from the pending nonce of the prefunded account, and the
+    /// [`GasEstimateCache`] is warmed up with the estimated gas of a single state root. In
+    /// [`ExExConfig::dry_run`] mode the L1 isn't accessed at all.
+    pub async fn with_transport(config: ExExConfig, transport: T) -> eyre::Result<Self> {
+        let provider = RootProvider::new(RpcClient::new(transport, false));
+        // The ABI isn't read with the selector override
+        let (abi, source) = match config.selector_override {
+            Some(_) => load_abi(None, STATE_ROOT_ABI, "state root contract")?,
+            None => config.load_state_root_abi().map_err(|err| {
+                eyre::eyre!("state root contract {}: {err}", config.state_root_contract)
+            })?,
+        };
+        let contract = StateRootContract::new(provider, &config, &abi, &source)?
+            .with_rpc_timeout(config.l1_rpc_timeout)
+            .with_value(U256::from(config.submit_value));
+        let nonce = if config.dry_run {
+            0
+        } else {
+            check_l1_chain(&contract, &config).await?;
+            if config.state_root_proxy && config.selector_override.is_none() {
+                check_proxy_implementation(&contract, &config).await?;
+            }
+            contract.pending_nonce(config.account()).await?
+        };
+        let breaker = CircuitBreaker::new(config.breaker_threshold, config.breaker_cooldown);
+        let rate_limiter = RateLimiter::new(config.submit_rate, config.submit_burst);
+        let state_roots = config.anchored_root.provider();
+        let gas_estimates = GasEstimateCache::new(
+            config.gas_estimate_refresh,
+            config.gas_estimate_deviation_percent,
+        );
+        let client = Self {
+            signer: config.signer()?,
+            config,
+            contract,
+            nonces: NonceManager::new(nonce),
+            gas_estimates,
+            metrics: L1Metrics::default(),
+            status: BridgeStatusHandle::default(),
+            last_state_root: Mutex::new(None),
+            recent: Mutex::default(),
+            last_recheck: Mutex::default(),
+            breaker: Mutex::new(breaker),
+            rate_limiter: Mutex::new(rate_limiter),
+            history: None,
+            anchor_events: AnchorEvents::default(),
+            state_roots,
+            sink: None,
+        };
+        if !client.config.dry_run {
+            client.warm_up_gas_estimate().await;
+        }
+        Ok(client)
+    }
+
+    /// Sets the [`L1Signer`] of the submissions, which has to sign for [`ExExConfig::account`].
+    pub fn with_signer(mut self, signer: Arc<dyn L1Signer>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Sets the [`L1Metrics`] the client records its submissions in, e.g. labeled by destination.
+    pub fn with_metrics(mut self, metrics: L1Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Sets the [`BridgeStatusHandle`] the client reports its submissions to.
+    pub fn with_status(mut self, status: BridgeStatusHandle) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Sets the [`SubmissionHistory`] the state roots anchored by
+    /// [`Self::submit_state_roots`] are recorded in.
+    pub fn with_history(mut self, history: SubmissionHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Sets the [`AnchorEvents`] the state roots anchored by [`Self::submit_state_roots`] are
+    /// published to.
+    pub fn with_anchor_events(mut self, anchor_events: AnchorEvents) -> Self {
+        self.anchor_events = anchor_events;
+        self
+    }
+
+    /// Sets the [`StateRootProvider`] of the roots anchored for blocks, instead of the one of the
+    /// [`ExExConfig::anchored_root`].
+    pub fn with_state_root_provider(mut self, state_roots: Arc<dyn StateRootProvider>) -> Self {
+        self.state_roots = state_roots;
+        self
+    }
+
+    /// Sets the [`StateRootSink`] the state roots are anchored in by
+    /// [`Self::submit_state_roots`], instead of the [`StateRootContract`].
+    ///
+    /// The sink anchors the state roots one by one, in order. The anchored state roots are read
+    /// back from the sink too, see [`Self::anchored_state_root`].
+    pub fn with_sink(mut self, sink: Arc<dyn StateRootSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Returns the [`StateRootSink`] the state roots are anchored in, the one set with
+    /// [`Self::with_sink`], or the client itself, anchoring them in the [`StateRootContract`].
+    pub fn sink(&self) -> &dyn StateRootSink {
+        self.sink.as_deref().unwrap_or(self)
+    }
+
+    /// Returns the root anchored for the block of the given header, see [`StateRootProvider`].
+    ///
+    /// Fails if the block has no such root, e.g. a pre-Shanghai block without a withdrawals root.
+    pub fn state_root(&self, header: &Header) -> eyre::Result<B256> {
+        root_for(self.state_roots.as_ref(), header)
+    }
+
+    /// Returns the root anchored for the committed block of the given header, or `None` if the
+    /// block has no such root and is skipped with [`ExExConfig::skip_missing_roots`], see
+    /// [`Self::state_root`].
+    pub fn root_to_anchor(&self, header: &Header) -> eyre::Result<Option<B256>> {
+        if self.config.skip_missing_roots && self.state_roots.root_for(header).is_none() {
+            let number = header.number;
+            debug!(target: "t1::bridge", number, "Block has no root to anchor, skipping");
+            return Ok(None)
+        }
+        self.state_root(header).map(Some)
+    }
+
+    /// Returns the configuration of the client.
+    pub const fn config(&self) -> &ExExConfig {
+        &self.config
+    }
+
+    /// Returns the maximum number of state roots anchored in a single transaction, at most the
+    /// [`ExExConfig::batch_size`] and at least one.
+    ///
+    /// With [`ExExConfig::max_batch_calldata_bytes`], the calldata of the transaction has to fit
+    /// into it as well. It only depends on the number of state roots, so it's encoded for
+    /// placeholder state roots with the selector, dispatch prefix and ABI of the contract. A
+    /// single state root is anchored even if it doesn't fit.
+    pub fn max_batch_len(&self) -> usize {
+        let batch_size = self.config.batch_size.max(1);
+        let Some(max_bytes) = self.config.max_batch_calldata_bytes else { return batch_size };
+        let roots = vec![(0, B256::ZERO); batch_size];
+        let fits = |len: usize| {
+            StateRootCall::new(&roots[..len], self.config.legacy_state_root)
+                .is_ok_and(|call| self.contract.calldata(&call).len() <= max_bytes)
+        };
+        if !fits(1) {
+            warn!(
+                target: "t1::bridge",
+                max_bytes,
+                "Calldata of a single state root exceeds the maximum batch calldata size"
+            );
+        }
+        (2..=batch_size).take_while(|&len| fits(len)).last().unwrap_or(1)
+    }
+
+    /// Returns the [`L1Metrics`] of the client.
+    pub const fn metrics(&self) -> &L1Metrics {
+        &self.metrics
+    }
+
+    /// Returns the state root of the last submission confirmed on L1, if any.
+    pub fn last_state_root(&self) -> Option<B256> {
+        *self.last_state_root.lock()
+    }
+
+    /// Returns the [`BridgeStatusHandle`] of the client.
+    pub const fn status(&self) -> &BridgeStatusHandle {
+        &self.status
+    }
+
+    /// Returns the [`CircuitBreaker`] guarding the submissions of
+    /// [`flush_l1`](super::handler::flush_l1).
+    pub const fn breaker(&self) -> &Mutex<CircuitBreaker> {
+        &self.breaker
+    }
+
+    /// Sends a `changeStateRoot` transaction with the given state root and waits for it to be
+    /// confirmed, returning the transaction hash.
+    ///
+    /// See [`Self::submit`].
+    pub async fn submit_state_root(&self, state_root: B256) -> Result<TxHash, L1SubmitError> {
+        self.submit(StateRootCall::Single(state_root)).await
+    }
+
+    /// Anchors the state roots of the given blocks, in order, and waits for the transaction to be
+    /// confirmed, returning the transaction hash.
+    ///
+    /// See [`StateRootCall::new`], with [`ExExConfig::legacy_state_root`], and [`Self::submit`].
+    /// In [`ExExConfig::dry_run`] mode the call is only logged and `None` is returned. With a
+    /// [`StateRootSink`], the state roots are anchored in it instead, see [`Self::with_sink`]. Once
+    /// confirmed, the state root held by the contract, or the sink, is read back, see
+    /// [`Self::verify_anchored`], and the submission is kept for [`Self::recheck_anchored`]. The
+    /// anchored state roots are durably recorded in the [`SubmissionHistory`], if any, and then
+    /// published to the [`AnchorEvents`] before returning.
+    pub async fn submit_state_roots(
+        &self,
+        roots: &[(BlockNumber, B256)],
+    ) -> Result<Option<TxHash>, L1SubmitError> {
+        self.submit_state_roots_to(self.sink(), roots).await
+    }
+
+    /// Anchors the state roots of the given blocks in the given [`StateRootSink`], see
+    /// [`Self::submit_state_roots`]. The client itself sends them to the [`StateRootContract`] in
+    /// a single transaction, other sinks anchor them one by one.
+    pub async fn submit_state_roots_to(
+        &self,
+        sink: &dyn StateRootSink,
+        roots: &[(BlockNumber, B256)],
+    ) -> Result<Option<TxHash>, L1SubmitError> {
+        let call = StateRootCall::new(roots, self.config.legacy_state_root)?;
+        self.submit_call(sink, call, roots).await
+    }
+
+    /// Anchors the state root of the given block along with the given [`StateDiff`], see
+    /// [`StateRootCall::WithDiff`] and [`Self::submit_state_roots`]. With
+    /// [`ExExConfig::state_diff_blobs`], the diff is submitted in blobs, see
+    /// [`StateRootCall::BlobDiff`].
+    ///
+    /// A [`StateRootSink`] only anchors the state root, without the diff.
+    pub async fn submit_state_diff(
+        &self,
+        number: BlockNumber,
+        state_root: B256,
+        diff: &StateDiff,
+    ) -> Result<Option<TxHash>, L1SubmitError> {
+        self.submit_state_diff_to(self.sink(), number, state_root, diff).await
+    }
+
+    /// Anchors the state root of the given block along with the given [`StateDiff`] in the given
+    /// [`StateRootSink`], see [`Self::submit_state_diff`].
+    pub async fn submit_state_diff_to(
+        &self,
+        sink: &dyn StateRootSink,
+        number: BlockNumber,
+        state_root: B256,
+        diff: &StateDiff,
+    ) -> Result<Option<TxHash>, L1SubmitError> {
+        let diff = diff.encode();
+        let call = if self.config.state_diff_blobs {
+            StateRootCall::BlobDiff(number, state_root, diff)
+        } else {
+            StateRootCall::WithDiff(number, state_root, diff)
+        };
+        self.submit_call(sink, call, &[(number, state_root)]).await
+    }
+
+    /// Anchors the state roots of the given blocks with the given call in the given
+    /// [`StateRootSink`], see [`Self::submit_state_roots`].
+    async fn submit_call(
+        &self,
+        sink: &dyn StateRootSink,
+        call: StateRootCall,
+        roots: &[(BlockNumber, B256)],
+    ) -> Result<Option<TxHash>, L1SubmitError> {
+        if self.config.dry_run {
+            let calldata = self.contract.calldata(&call);
+            info!(
+                target: "t1::bridge",
+                contract = %self.config.state_root_contract,
+                ?roots,
+                calldata = %alloy_primitives::hex::encode_prefixed(calldata),
+                "Dry run, not submitting state roots to L1"
+            );
+            return Ok(None)
+        }
+
+        // The client sends the whole call to the contract, batched and with its diff
+        let tx_hash = if std::ptr::addr_eq(std::ptr::from_ref(sink), std::ptr::from_ref(self)) {
+            self.submit(call.clone()).await?
+        } else {
+            self.submit_to_sink(sink, roots).await?
+        };
+        if let Some(&(number, state_root)) = roots.last() {
+            self.verify_anchored(number, state_root, tx_hash).await;
+        }
+        self.track_confirmed(ConfirmedSubmission { call, roots: roots.to_vec(), tx_hash });
+        let timestamp =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_err(eyre::Report::from)?.as_secs();
+        let records = roots
+            .iter()
+            .map(|&(number, state_root)| SubmissionRecord {
+                number,
+                state_root,
+                l1_tx_hash: tx_hash,
+                timestamp,
+            })
+            .collect::<Vec<_>>();
+        if let Some(history) = &self.history {
+            history.append(&records)?;
+        }
+        for record in records {
+            self.anchor_events.publish(record);
+        }
+        Ok(Some(tx_hash))
+    }
+
+    /// Returns `true` if the [`StateRootContract`], or the [`StateRootSink`], already holds the
+    /// given state root of the given block, e.g. because another node anchored it.
+    ///
+    /// The state root is compared to the one of [`Self::anchored_state_root`]. If the anchored
+    /// state root can't be read, or in [`ExExConfig::dry_run`] mode, the state root is considered
+    /// not anchored.
+    pub async fn is_anchored_on_chain(&self, number: BlockNumber, state_root: B256) -> bool {
+        if self.config.dry_run {
+            return false
+        }
+
+        let anchored =
+            self.anchored_state_root(number).await.map(|anchored| anchored == Some(state_root));
+        anchored.unwrap_or_else(|err| {
+            warn!(
+                target: "t1::bridge",
+                %err,
+                number,
+                "Failed to read the state root anchored on L1"
+            );
+            false
+        })
+    }
+
+    /// Keeps the given confirmed submission among the latest [`ExExConfig::l1_reorg_depth`]
+    /// ones, see [`Self::recheck_anchored`].
+    fn track_confirmed(&self, submission: ConfirmedSubmission) {
+        let depth = self.config.l1_reorg_depth;
+        if depth == 0 {
+            return
+        }
+        let mut recent = self.recent.lock();
+        recent.push_back(submission);
+        while recent.len() > depth {
+            recent.pop_front();
+        }
+    }
+
+    /// Checks that the latest [`ExExConfig::l1_reorg_depth`] confirmed submissions are still
+    /// included in the L1, returning the number of submissions that were sent again.
+    ///
+    /// A submission whose transaction has no receipt anymore was reorged out of the L1, e.g.
+    /// because the L1 block it was confirmed in was orphaned. Unless the contract holds its state
+    /// root anyway, e.g. because the transaction was included again, it's submitted again, oldest
+    /// first, with the same call. This is independent of the reorgs of the local chain. With a
+    /// [`StateRootSink`], the sink is asked whether the transaction is still included, see
+    /// [`StateRootSink::is_included`], and whether it holds the state root.
+    ///
+    /// A submission whose receipt can't be read is skipped with a warning, and checked again by
+    /// the next re-check.
+    pub async fn recheck_anchored(&self) -> Result<usize, L1SubmitError> {
+        if self.config.dry_run {
+            return Ok(0)
+        }
+
+        let recent = self.recent.lock().iter().cloned().collect::<Vec<_>>();
+        let mut resubmitted = 0;
+        for submission in recent {
+            let tx_hash = submission.tx_hash;
+            match self.tx_included(tx_hash).await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(err) => {
+                    warn!(
+                        target: "t1::bridge",
+                        %err,
+                        %tx_hash,
+                        "Failed to read the receipt of a confirmed L1 transaction, skipping it"
+                    );
+                    continue
+                }
+            }
+
+            self.recent.lock().retain(|recent| recent.tx_hash != tx_hash);
+            let (number, state_root) = submission.roots[submission.roots.len() - 1];
+            if self.is_anchored_on_chain(number, state_root).await {
+                debug!(
+                    target: "t1::bridge",
+                    %tx_hash,
+                    number,
+                    "Reorged out L1 transaction was included again"
+                );
+                continue
+            }
+            warn!(
+                target: "t1::bridge",
+                %tx_hash,
+                roots = ?submission.roots,
+                "Confirmed L1 transaction was reorged out, submitting its state roots again"
+            );
+            self.submit_call(self.sink(), submission.call, &submission.roots).await?;
+            resubmitted += 1;
+        }
+        Ok(resubmitted)
+    }
+
+    /// Runs [`Self::recheck_anchored`] if the [`ExExConfig::l1_recheck_interval`] has passed
+    /// since the last re-check, returning the number of submissions that were sent again.
+    pub async fn recheck_anchored_if_due(&self) -> Result<usize, L1SubmitError> {
+        if self.config.l1_reorg_depth == 0 {
+            return Ok(0)
+        }
+        {
+            let mut last_recheck = self.last_recheck.lock();
+            let now = Instant::now();
+            if last_recheck.is_some_and(|last| now < last + self.config.l1_recheck_interval) {
+                return Ok(0)
+            }
+            *last_recheck = Some(now);
+        }
+        self.recheck_anchored().await
+    }
+
+    /// Returns `true` if the confirmed transaction of the given hash still has a receipt, or is
+    /// still included in the [`StateRootSink`].
+    async fn tx_included(&self, tx_hash: TxHash) -> TransportResult<bool> {
+        match &self.sink {
+            Some(sink) => sink.is_included(tx_hash).await,
+            None => Ok(self.contract.transaction_receipt(tx_hash).await?.is_some()),
+        }
+    }
+
+    /// Checks that the [`StateRootContract`], or the [`StateRootSink`], holds the given state root
+    /// of the given block after the transaction anchoring it was confirmed, as the contract may
+    /// accept the transaction without storing the state root.
+    ///
+    /// A mismatch is logged as an error and counted in
+    /// [`L1Metrics::anchored_root_mismatches_total`], but doesn't fail the submission, as another
+    /// node may have anchored a later state root in the meantime. A failed read only warns.
+    async fn verify_anchored(&self, number: BlockNumber, state_root: B256, tx_hash: TxHash) {
+        match self.anchored_state_root(number).await {
+            Ok(anchored) if anchored == Some(state_root) => {
+                debug!(target: "t1::bridge", number, %state_root, "Verified anchored state root")
+            }
+            Ok(anchored) => {
+                error!(
+                    target: "t1::bridge",
+                    number,
+                    %state_root,
+                    ?anchored,
+                    %tx_hash,
+                    "Contract doesn't hold the state root of the confirmed submission"
+                );
+                self.metrics.anchored_root_mismatches_total.increment(1);
+            }
+            Err(err) => {
+                warn!(
+                    target: "t1::bridge",
+                    %err,
+                    number,
+                    "Failed to read the anchored state root back from L1"
+                )
+            }
+        }
+    }
+
+    /// Returns the state root the [`StateRootContract`] holds for the given block, or `None` if
+    /// it holds none.
+    ///
+    /// Without [`ExExConfig::state_roots_per_height`] this is the latest anchored state root,
+    /// regardless of the block number. With a [`StateRootSink`], it's the state root the sink
+    /// holds for the block.
+    pub async fn anchored_state_root(&self, number: BlockNumber) -> TransportResult<Option<B256>> {
+        if let Some(sink) = &self.sink {
+            return sink.anchored_state_root(number).await
+        }
+        if self.config.state_roots_per_height {
+            let anchored = self.contract.state_root_at(number).await?;
+            return Ok(Some(anchored).filter(|anchored| !anchored.is_zero()))
+        }
+        self.contract.current_state_root().await
+    }
+
+    /// Checks the balance of the L1 account signing the submissions, recording it in the
+    /// [`L1Metrics::account_balance_wei`].
+    ///
+    /// Logs an error if the balance can't cover the estimated cost of the next submission of a
+    /// single state root, at its gas limit and max fee plus the [`ExExConfig::submit_value`], and
+    /// warns if it's below [`ExExConfig::min_balance`].
+    pub async fn check_balance(&self) -> eyre::Result<BalanceStatus> {
+        let account = self.config.account();
+        let balance = self.contract.balance(account).await?;
+        self.metrics.account_balance_wei.set(balance.saturating_to::<u128>() as f64);
+
+        let call = StateRootCall::new(&[(0, B256::ZERO)], self.config.legacy_state_root)?;
+        let gas = self.gas_limit(&call).await;
+        let fee = match self.estimate_fees().await {
+            Some(fees) => fees.max_fee_per_gas,
+            None => self.contract.gas_price().await?,
+        };
+        let cost = U256::from(gas) * U256::from(fee) + U256::from(self.config.submit_value);
+        if balance < cost {
+            error!(
+                target: "t1::bridge",
+                %account,
+                %balance,
+                %cost,
+                "L1 account can't pay for the next submission"
+            );
+            return Ok(BalanceStatus::Insufficient)
+        }
+        let min_balance = self.config.min_balance;
+        if balance < U256::from(min_balance) {
+            warn!(
+                target: "t1::bridge",
+                %account,
+                %balance,
+                min_balance,
+                "L1 account balance is low"
+            );
+            self.metrics.low_balance_warnings_total.increment(1);
+            return Ok(BalanceStatus::Low)
+        }
+        debug!(target: "t1::bridge", %account, %balance, "Checked L1 account balance");
+        Ok(BalanceStatus::Sufficient)
+    }
+
+    /// Sends the transaction of the given [`StateRootCall`] and waits for it to be confirmed,
+    /// returning the transaction hash.
+    ///
+    /// Failed submissions are retried with a jittered exponential backoff, see
+    /// [`ExExConfig::max_retries`] and [`ExExConfig::retry_base_delay`]. A transaction that
+    /// reverts on L1 is an error, see [`StateRootContract::wait_for_confirmation`]. Every failed
+    /// attempt is counted in [`L1Metrics::submission_failures_total`]. The hash of a confirmed
+    /// transaction is reported as
+    /// [`BridgeStatus::last_l1_tx_hash`](super::rpc::BridgeStatus::last_l1_tx_hash), along with the
+    /// state root it anchored as
+    /// [`BridgeStatus::last_anchored_root`](super::rpc::BridgeStatus::last_anchored_root).
+    /// Submissions are throttled by the [`RateLimiter`] of [`ExExConfig::submit_rate`].
+    pub async fn submit(&self, call: StateRootCall) -> Result<TxHash, L1SubmitError> {
+        self.wait_for_rate_limit().await;
+        let (tx_id, params) = (|| self.send(&call))
+            .retry(&self.retry_backoff())
+            .notify(|err, delay| {
+                warn!(
+                    target: "t1::bridge",
+                    %err,
+                    ?delay,
+                    "Failed to submit state root to L1, retrying"
+                )
+            })
+            .await?;
+        let tx_id = self
+            .confirm(&call, tx_id, params)
+            .await
+            .inspect_err(|_| self.metrics.submission_failures_total.increment(1))?;
+        let state_root = call.last_state_root();
+        self.status.update(|status| {
+            status.last_l1_tx_hash = Some(tx_id);
+            status.last_anchored_root = Some(state_root);
+        });
+        *self.last_state_root.lock() = Some(state_root);
+        Ok(tx_id)
+    }
+
+    /// Anchors the given state roots in the given [`StateRootSink`], one by one, returning the
+    /// transaction hash of the last one.
+    ///
+    /// Like the submissions to the contract, see [`Self::submit`], every submission is throttled
+    /// by the [`RateLimiter`], and every failed attempt is counted in
+    /// [`L1Metrics::submission_failures_total`]. Only [`L1SubmitError::is_retryable`] failures are
+    /// retried with the jittered exponential backoff.
+    async fn submit_to_sink(
+        &self,
+        sink: &dyn StateRootSink,
+        roots: &[(BlockNumber, B256)],
+    ) -> Result<TxHash, L1SubmitError> {
+        let mut tx_hash = TxHash::ZERO;
+        for &(number, state_root) in roots {
+            self.wait_for_rate_limit().await;
+            let submit = || async {
+                sink.submit(number, state_root)
+                    .await
+                    .inspect_err(|_| self.metrics.submission_failures_total.increment(1))
+            };
+            tx_hash = submit
+                .retry(&self.retry_backoff())
+                .when(L1SubmitError::is_retryable)
+                .notify(|err, delay| {
+                    warn!(
+                        target: "t1::bridge",
+                        %err,
+                        number,
+                        ?delay,
+                        "Failed to anchor state root in the sink, retrying"
+                    )
+                })
+                .await?;
+            *self.last_state_root.lock() = Some(state_root);
+        }
+        let last_state_root = *self.last_state_root.lock();
+        self.status.update(|status| {
+            status.last_l1_tx_hash = Some(tx_hash);
+            status.last_anchored_root = last_state_root;
+        });
+        Ok(tx_hash)
+    }
+
+    /// Returns the backoff failed submissions are retried with, see [`ExExConfig::max_retries`]
+    /// and [`ExExConfig::retry_base_delay`].
+    fn retry_backoff(&self) -> ExponentialBuilder {
+        ExponentialBuilder::default()
+            .with_min_delay(self.config.retry_base_delay)
+            .with_factor(L1_SUBMIT_RETRY_FACTOR)
+            .with_max_times(self.config.max_retries)
+            .with_jitter()
+    }
+
+    /// Waits until the [`RateLimiter`] admits another submission.
+    async fn wait_for_rate_limit(&self) {
+        loop {
+            let wait = match self.rate_limiter.lock().try_acquire() {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            debug!(target: "t1::bridge", ?wait, "L1 submission rate limited, waiting");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Waits for the sent transaction of the given [`StateRootCall`] to be confirmed, returning
+    /// the hash of the confirmed transaction.
+    ///
+    /// A transaction that isn't mined within [`ExExConfig::fee_bump_timeout`] is replaced by one
+    /// with the same nonce and fees bumped by [`ExExConfig::fee_bump_percent`], at most
+    /// [`ExExConfig::max_fee_bumps`] times and without exceeding
+    /// [`ExExConfig::max_fee_per_gas_cap`]. Any of the replaced transactions may be confirmed.
+    ///
+    /// Fails with [`L1SubmitError::Timeout`] if none of them is confirmed within
+    /// [`ExExConfig::confirm_timeout`], or with [`L1SubmitError::BlockTimeout`] if none of them is
+    /// confirmed within [`ExExConfig::submit_timeout_blocks`] L1 blocks, in which case they may
+    /// still be mined later.
+    async fn confirm(
+        &self,
+        call: &StateRootCall,
+        tx_hash: TxHash,
+        params: TxParams,
+    ) -> Result<TxHash, L1SubmitError> {
+        if let Some(blocks) = self.config.submit_timeout_blocks {
+            let start = self.contract.block_number().await?;
+            tokio::select! {
+                biased;
+                confirmed = self.confirm_or_replace(call, tx_hash, params) => return confirmed,
+                () = self.wait_for_l1_block(start + blocks) => {
+                    return Err(L1SubmitError::BlockTimeout { tx_hash, blocks })
+                }
+            }
+        }
+
+        let timeout = self.config.confirm_timeout;
+        if timeout.is_zero() {
+            return self.confirm_or_replace(call, tx_hash, params).await
+        }
+        tokio::time::timeout(timeout, self.confirm_or_replace(call, tx_hash, params))
+            .await
+            .unwrap_or(Err(L1SubmitError::Timeout { tx_hash, timeout }))
+    }
+
+    /// Polls the L1 every [`ExExConfig::confirm_poll_interval`] until it reaches the given block.
+    ///
+    /// A failed poll is repeated at the next interval.
+    async fn wait_for_l1_block(&self, number: BlockNumber) {
+        loop {
+            tokio::time::sleep(self.config.confirm_poll_interval).await;
+            match self.contract.block_number().await {
+                Ok(latest) if latest >= number => return,
+                Ok(_) => {}
+                Err(err) => {
+                    debug!(target: "t1::bridge", %err, "Failed to poll the L1 block number")
+                }
+            }
+        }
+    }
+
+    /// Waits for the sent transaction of the given [`StateRootCall`], or any transaction replacing
+    /// it, to be confirmed, see [`Self::confirm`].
+    async fn confirm_or_replace(
+        &self,
+        call: &StateRootCall,
+        tx_hash: TxHash,
+        mut params: TxParams,
+    ) -> Result<TxHash, L1SubmitError> {
+        let (depth, interval) = (self.config.confirmations, self.config.confirm_poll_interval);
+        let mut sent = vec![tx_hash];
+        let mut bumps = 0;
+        loop {
+            let confirmed = futures::future::select_ok(sent.iter().map(|tx_hash| {
+                Box::pin(self.contract.wait_for_confirmation(*tx_hash, depth, interval))
+            }));
+
+            // Without bumps left, the last transaction is waited for indefinitely
+            let bumped = params.bumped(self.config.fee_bump_percent);
+            let exceeds_cap = self
+                .config
+                .max_fee_per_gas_cap
+                .zip(bumped.max_fee_per_gas())
+                .is_some_and(|(cap, fee)| fee > cap);
+            if bumps >= self.config.max_fee_bumps || exceeds_cap {
+                let (receipt, _) = confirmed.await?;
+                self.gas_estimates.observe(call, receipt.gas_used as u64);
+                return Ok(receipt.transaction_hash)
+            }
+
+            let timeout = self.config.fee_bump_timeout;
+            if let Ok(result) = tokio::time::timeout(timeout, confirmed).await {
+                let (receipt, _) = result?;
+                self.gas_estimates.observe(call, receipt.gas_used as u64);
+                return Ok(receipt.transaction_hash)
+            }
+
+            bumps += 1;
+            let nonce = params.nonce;
+            let fee = bumped.max_fee_per_gas();
+            warn!(
+                target: "t1::bridge",
+                nonce,
+                bumps,
+                ?fee,
+                ?timeout,
+                "L1 transaction not mined, replacing it"
+            );
+            match self.contract.send(self.signer.as_ref(), call, bumped.clone()).await {
+                Ok(tx_hash) => {
+                    sent.push(tx_hash);
+                    params = bumped;
+                }
+                Err(err) => {
+                    warn!(target: "t1::bridge", %err, nonce, "Failed to replace L1 transaction")
+                }
+            }
+        }
+    }
+
+    /// Returns the gas limit of the transaction of the given [`StateRootCall`].
+    ///
+    /// Unless overridden by [`ExExConfig::gas_limit`], the gas is estimated, or taken from the
+    /// [`GasEstimateCache`], and scaled by [`ExExConfig::gas_multiplier`], falling back to
+    /// [`ExExConfig::default_gas_limit`] if the estimation fails.
+    async fn gas_limit(&self, call: &StateRootCall) -> u64 {
+        if let Some(gas_limit) = self.config.gas_limit {
+            return gas_limit
+        }
+        if let Some(estimate) = self.gas_estimates.get(call) {
+            return scale_gas_estimate(estimate, self.config.gas_multiplier)
+        }
+
+        match self.contract.estimate_gas(self.config.account(), call).await {
+            Ok(estimate) => {
+                self.gas_estimates.insert(call, estimate);
+                scale_gas_estimate(estimate, self.config.gas_multiplier)
+            }
+            Err(err) => {
+                warn!(
+                    target: "t1::bridge",
+                    %err,
+                    default = self.config.default_gas_limit,
+                    "Failed to estimate L1 gas"
+                );
+                self.config.default_gas_limit
+            }
+        }
+    }
+
+    /// Estimates the gas of a submission anchoring a single state root into the
+    /// [`GasEstimateCache`], so the first submission doesn't wait for the estimation.
+    async fn warm_up_gas_estimate(&self) {
+        if self.config.gas_limit.is_some() || self.config.gas_estimate_refresh.is_zero() {
+            return
+        }
+        let Ok(call) = StateRootCall::new(&[(0, B256::ZERO)], self.config.legacy_state_root) else {
+            return
+        };
+        let gas = self.gas_limit(&call).await;
+        debug!(target: "t1::bridge", gas, "Warmed up the L1 gas estimate");
+    }
+
+    /// Returns the access list of the transaction of the given [`StateRootCall`], if enabled by
+    /// [`ExExConfig::access_list`].
+    ///
+    /// The transaction is sent without an access list if it can't be created.
+    async fn access_list(&self, call: &StateRootCall) -> Option<AccessList> {
+        if !self.config.access_list {
+            return None
+        }
+
+        match self.contract.create_access_list(self.config.account(), call).await {
+            Ok(access_list) => Some(access_list),
+            Err(err) => {
+                warn!(
+                    target: "t1::bridge",
+                    %err,
+                    "Failed to create L1 access list, sending without one"
+                );
+                None
+            }
+        }
+    }
+
+    /// Estimates the EIP-1559 fees of the next submission from the L1 fee history.
+    ///
+    /// Returns `None` to fall back to the legacy gas price if the L1 doesn't support EIP-1559.
+    async fn estimate_fees(&self) -> Option<Eip1559Fees> {
+        let history = match self.contract.fee_history().await {
+            Ok(history) => history,
+            Err(err) => {
+                debug!(
+                    target: "t1::bridge",
+                    %err,
+                    "L1 fee history unavailable, falling back to legacy gas price"
+                );
+                return None
+            }
+        };
+        estimate_eip1559_fees(
+            &history,
+            self.config.priority_fee_multiplier,
+            self.config.max_fee_per_gas_cap,
+        )
+    }
+
+    /// Sends the transaction of the given [`StateRootCall`] once, with the next nonce of the
+    /// [`NonceManager`].
+    ///
+    /// The nonce is advanced if the transaction was accepted, and resynced from L1 if it was
+    /// rejected for a nonce that is too low. Concurrent sends are signed and sent one at a time,
+    /// see [`NonceManager::lock`]. Returns the hash of the transaction and the params it was sent
+    /// with.
+    async fn send(&self, call: &StateRootCall) -> TransportResult<(TxHash, TxParams)> {
+        let fees = self.estimate_fees().await;
+        let gas_price = match fees {
+            Some(_) => None,
+            None => Some(self.contract.gas_price().await?),
+        };
+        // The max blob fee leaves room for the blob base fee to double, like the max fee
+        let max_fee_per_blob_gas = match call {
+            StateRootCall::BlobDiff(..) => {
+                Some(self.contract.blob_base_fee().await?.saturating_mul(2))
+            }
+            _ => None,
+        };
+        let gas = self.gas_limit(call).await;
+        let access_list = self.access_list(call).await;
+
+        let _sending = self.nonces.lock().await;
+        let nonce = self.nonces.next();
+        let params = TxParams { nonce, gas, fees, gas_price, access_list, max_fee_per_blob_gas };
+        let result = self
+            .contract
+            .send(self.signer.as_ref(), call, params.clone())
+            .await
+            .map(|tx_hash| (tx_hash, params));
+
+        match &result {
+            Ok(_) => self.nonces.advance(nonce),
+            Err(err) if is_nonce_too_low(err) => {
+                let synced = self.contract.pending_nonce(self.config.account()).await?;
+                warn!(target: "t1::bridge", nonce, synced, "L1 nonce too low, resynced");
+                self.nonces.reset(synced);
+            }
+            Err(_) => {}
+        }
+        if result.is_err() {
+            self.metrics.submission_failures_total.increment(1);
+        }
+
+        result
+    }
+}
+
+#[async_trait]
+impl<T: Transport + Clone> StateRootSink for L1Client<T> {
+    /// Anchors the state root with [`Self::submit_state_roots`], failing in
+    /// [`ExExConfig::dry_run`] mode.
+    async fn submit(&self, number: BlockNumber, root: B256) -> Result<TxHash, L1SubmitError> {
+        self.submit_state_roots(&[(number, root)]).await?.ok_or_else(|| {
+            L1SubmitError::Other(eyre::eyre!("dry run, state root of block {number} not anchored"))
+        })
+    }
+
+    async fn anchored_state_root(&self, number: BlockNumber) -> TransportResult<Option<B256>> {
+        Self::anchored_state_root(self, number).await
+    }
+
+    async fn is_included(&self, tx_hash: TxHash) -> TransportResult<bool> {
+        self.tx_included(tx_hash).await
+    }
+}
+
+/// Scales the estimated gas of a transaction by the given safety multiplier, rounding up.
+pub fn scale_gas_estimate(estimate: u64, multiplier: f64) -> u64 {
+    (estimate as f64 * multiplier).ceil() as u64
+}
+
+/// Fees of an EIP-1559 transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Eip1559Fees {
+    /// Maximum total fee per gas, including the base fee.
+    pub max_fee_per_gas: u128,
+    /// Maximum fee per gas paid to the L1 block producer on top of the base fee.
+    pub max_priority_fee_per_gas: u128,
+}
+
+/// Estimates the fees of an EIP-1559 transaction from a sample of the L1 fee history.
+///
+/// The priority fee is the median of the sampled rewards, scaled by `priority_fee_multiplier`.
+/// The max fee leaves room for the base fee to double, and is capped at `max_fee_per_gas_cap`.
+///
+/// Returns `None` if the sample has no base fee, i.e. the L1 doesn't support EIP-1559.
+pub fn estimate_eip1559_fees(
+    history: &FeeHistory,
+    priority_fee_multiplier: f64,
+    max_fee_per_gas_cap: Option<u128>,
+) -> Option<Eip1559Fees> {
+    // The last base fee is the one of the next block
+    let base_fee = history.base_fee_per_gas.last().copied().filter(|fee| *fee != 0)?;
+
+    let mut rewards = history
+        .reward
+        .iter()
+        .flatten()
+        .filter_map(|block| block.first().copied())
+        .collect::<Vec<_>>();
+    rewards.sort_unstable();
+    let reward = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+    let mut max_priority_fee_per_gas = (reward as f64 * priority_fee_multiplier) as u128;
+
+    let mut max_fee_per_gas = base_fee.saturating_mul(2).saturating_add(max_priority_fee_per_gas);
+    if let Some(cap) = max_fee_per_gas_cap {
+        max_fee_per_gas = max_fee_per_gas.min(cap);
+        max_priority_fee_per_gas = max_priority_fee_per_gas.min(max_fee_per_gas);
+    }
+
+    Some(Eip1559Fees { max_fee_per_gas, max_priority_fee_per_gas })
+}
+
+/// Result of [`L1Client::check_balance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceStatus {
+    /// The balance is at least [`ExExConfig::min_balance`].
+    Sufficient,
+    /// The balance is below [`ExExConfig::min_balance`], but covers the next submission.
+    Low,
+    /// The balance can't cover the next submission.
+    Insufficient,
+}
+
+/// Confirmed submission of the [`L1Client`], re-checked for being reorged out of the L1, see
+/// [`L1Client::recheck_anchored`].
+#[derive(Debug, Clone)]
+struct ConfirmedSubmission {
+    /// Call the state roots were anchored with.
+    call: StateRootCall,
+    /// Block numbers and state roots anchored by the call.
+    roots: Vec<(BlockNumber, B256)>,
+    /// Hash of the confirmed transaction.
+    tx_hash: TxHash,
+}
+
+/// Tracks the nonce of the next transaction of the prefunded L1 account.
+///
+/// The nonce is advanced locally after every sent transaction, so consecutive submissions don't
+/// reuse a nonce that is still pending on L1.
+#[derive(Debug)]
+pub struct NonceManager {
+    next: AtomicU64,
+    /// Held while a transaction is signed and sent, see [`Self::lock`].
+    sending: tokio::sync::Mutex<()>,
+}
+
+impl NonceManager {
+    /// Creates a manager starting at the given nonce.
+    pub fn new(nonce: u64) -> Self {
+        Self { next: AtomicU64::new(nonce), sending: tokio::sync::Mutex::new(()) }
+    }
+
+    /// Waits for the transactions being sent by others, and returns a guard to hold from taking
+    /// the [`Self::next`] nonce until it's advanced or reset, so concurrent submissions don't send
+    /// different transactions with the same nonce.
+    pub async fn lock(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.sending.lock().await
+    }
+
+    /// Returns the nonce of the next transaction.
+    pub fn next(&self) -> u64 {
+        self.next.load(Ordering::SeqCst)
+    }
+
+    /// Advances past the given nonce, after a transaction with it was sent.
+    pub fn advance(&self, nonce: u64) {
+        self.next.fetch_max(nonce + 1, Ordering::SeqCst);
+    }
+
+    /// Resets the next nonce, e.g. to the pending nonce read from L1.
+    pub fn reset(&self, nonce: u64) {
+        self.next.store(nonce, Ordering::SeqCst);
+    }
+}
+
+/// Caches the estimated gas of the submissions anchoring a single state root, which is stable, so
+/// it isn't estimated before every submission.
+///
+/// A cached estimate is refreshed once it's older than the refresh interval, or once a confirmed
+/// submission used more or less gas than the allowed deviation from it, see [`Self::observe`].
+/// Batches and state diffs vary in size and are always estimated.
+#[derive(Debug)]
+pub struct GasEstimateCache {
+    refresh: Duration,
+    deviation_percent: f64,
+    cached: Mutex<Option<CachedGasEstimate>>,
+}
+
+/// A gas estimate in the [`GasEstimateCache`].
+#[derive(Debug, Clone, Copy)]
+struct CachedGasEstimate {
+    call: Discriminant<StateRootCall>,
+    estimate: u64,
+    estimated_at: Instant,
+}
+
+impl GasEstimateCache {
+    /// Creates a cache refreshing its estimate after `refresh`, or once the gas used by a
+    /// submission deviates from it by more than `deviation_percent`. A zero `refresh` disables
+    /// the cache.
+    pub const fn new(refresh: Duration, deviation_percent: f64) -> Self {
+        Self { refresh, deviation_percent, cached: Mutex::new(None) }
+    }
+
+    /// Returns the cached estimate of the given call, if it's cached and not due for a refresh.
+    pub fn get(&self, call: &StateRootCall) -> Option<u64> {
+        let key = self.key(call)?;
+        let cached = (*self.cached.lock())?;
+        (cached.call == key && cached.estimated_at.elapsed() < self.refresh)
+            .then_some(cached.estimate)
+    }
+
+    /// Caches the estimate of the given call, unless its gas isn't stable.
+    pub fn insert(&self, call: &StateRootCall, estimate: u64) {
+        if let Some(call) = self.key(call) {
+            let estimated_at = Instant::now();
+            *self.cached.lock() = Some(CachedGasEstimate { call, estimate, estimated_at });
+        }
+    }
+
+    /// Compares the gas used by a confirmed submission of the given call with the cached
+    /// estimate, dropping the estimate if it deviates too much.
+    ///
+    /// Returns `true` if the estimate was dropped.
+    pub fn observe(&self, call: &StateRootCall, gas_used: u64) -> bool {
+        let mut cached = self.cached.lock();
+        let Some(estimate) = cached
+            .filter(|cached| Some(cached.call) == self.key(call))
+            .map(|cached| cached.estimate)
+        else {
+            return false
+        };
+        let deviation = gas_used.abs_diff(estimate) as f64;
+        if deviation <= estimate as f64 * self.deviation_percent / 100.0 {
+            return false
+        }
+
+        debug!(
+            target: "t1::bridge",
+            estimate,
+            gas_used,
+            "L1 gas used deviates from the cached estimate, refreshing it"
+        );
+        *cached = None;
+        true
+    }
+
+    /// Returns the key the estimate of the given call is cached under, or `None` if it isn't
+    /// cached.
+    fn key(&self, call: &StateRootCall) -> Option<Discriminant<StateRootCall>> {
+        let stable = matches!(call, StateRootCall::Single(_) | StateRootCall::At(..));
+        (stable && !self.refresh.is_zero()).then(|| mem::discriminant(call))
+    }
+}
+
+/// Returns `true` if the L1 node rejected a transaction because its nonce was already used.
+fn is_nonce_too_low(err: &TransportError) -> bool {
+    err.as_error_resp().is_some_and(|err| err.message.to_lowercase().contains("nonce too low"))
+}
+
+/// Error of an L1 RPC call that didn't complete within the timeout of the
+/// [`StateRootContract`], see [`StateRootContract::with_rpc_timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("L1 RPC call timed out after {0:?}")]
+pub struct L1RpcTimeout(pub Duration);
+
+/// Returns the timeout of the given error, if it's an [`L1RpcTimeout`].
+pub(super) fn rpc_timeout(err: &TransportError) -> Option<Duration> {
+    match err.as_transport_err()? {
+        TransportErrorKind::Custom(err) => err.downcast_ref::<L1RpcTimeout>().map(|err| err.0),
+        _ => None,
+    }
+}
+
+/// Error anchoring state roots on L1.
+///
+/// The errors of the L1 node are classified, so callers can tell whether a submission can be
+/// retried as is, has to be resent, or needs an operator to intervene.
+#[derive(Debug, thiserror::Error)]
+pub enum L1SubmitError {
+    /// The L1 RPC endpoint, or the remote signer, couldn't be reached.
+    #[error("L1 RPC endpoint unavailable: {0}")]
+    RpcUnavailable(TransportError),
+    /// An L1 RPC call didn't complete within [`ExExConfig::l1_rpc_timeout`].
+    #[error("L1 RPC call timed out after {0:?}")]
+    RpcTimeout(Duration),
+    /// The L1 transaction was mined, but reverted.
+    #[error("L1 transaction {0} reverted")]
+    TxReverted(TxHash),
+    /// Neither the L1 transaction nor any replacing it was confirmed within the timeout, see
+    /// [`ExExConfig::confirm_timeout`].
+    #[error("L1 transaction {tx_hash} not confirmed within {timeout:?}")]
+    Timeout {
+        /// Hash of the first sent transaction.
+        tx_hash: TxHash,
+        /// Time waited for the confirmation.
+        timeout: Duration,
+    },
+    /// Neither the L1 transaction nor any replacing it was confirmed within a number of L1 blocks,
+    /// see [`ExExConfig::submit_timeout_blocks`].
+    #[error("L1 transaction {tx_hash} not confirmed within {blocks} L1 blocks")]
+    BlockTimeout {
+        /// Hash of the first sent transaction.
+        tx_hash: TxHash,
+        /// Number of L1 blocks waited for the confirmation.
+        blocks: u64,
+    },
+    /// The L1 node rejected the transaction because its nonce was already used.
+    #[error("L1 transaction nonce too low: {0}")]
+    NonceTooLow(TransportError),
+    /// The L1 account can't pay for the gas of the transaction.
+    #[error("insufficient funds to anchor state roots on L1: {0}")]
+    InsufficientFunds(TransportError),
+    /// Any other error, e.g. of the L1 node or of the local bookkeeping.
+    #[error("{0}")]
+    Other(eyre::Report),
+}
+
+impl L1SubmitError {
+    /// Returns `true` if the submission may succeed when retried as is, i.e. the L1 or the sink
+    /// couldn't be reached or didn't answer in time.
+    pub const fn is_retryable(&self) -> bool {
+        matches!(self, Self::RpcUnavailable(_) | Self::RpcTimeout(_))
+    }
+}
+
+impl From<TransportError> for L1SubmitError {
+    fn from(err: TransportError) -> Self {
+        if let Some(timeout) = rpc_timeout(&err) {
+            Self::RpcTimeout(timeout)
+        } else if is_nonce_too_low(&err) {
+            Self::NonceTooLow(err)
+        } else if err
+            .as_error_resp()
+            .is_some_and(|resp| resp.message.to_lowercase().contains("insufficient funds"))
+        {
+            Self::InsufficientFunds(err)
+        } else if err.is_transport_error() {
+            Self::RpcUnavailable(err)
+        } else {
+            Self::Other(err.into())
+        }
+    }
+}
+
+impl From<eyre::Report> for L1SubmitError {
+    fn from(err: eyre::Report) -> Self {
+        Self::Other(err)
+    }
+}
+
+/// Token bucket limiting the rate of L1 submissions.
+///
+/// The bucket holds up to `burst` tokens and is refilled at `rate` tokens per second. Every
+/// submission takes a token, and waits for one to be refilled while the bucket is empty, so
+/// bursts of submissions are spread out instead of failing. A zero rate disables the limit.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a full bucket of `burst` tokens, refilled at `rate` tokens per second.
+    pub fn new(rate: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self { rate, burst, tokens: burst, refilled_at: Instant::now() }
+    }
+
+    /// Returns `true` if the rate is limited.
+    pub fn is_enabled(&self) -> bool {
+        self.rate > 0.0
+    }
+
+    /// Takes a token if one is available, or returns the time until one is refilled.
+    pub fn try_acquire(&mut self) -> Result<(), Duration> {
+        if !self.is_enabled() {
+            return Ok(())
+        }
+
+        let now = Instant::now();
+        let refilled = now.duration_since(self.refilled_at).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refilled).min(self.burst);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(())
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+    }
+}
+
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Submissions are sent.
+    Closed,
+    /// Submissions are paused until the cooldown has passed.
+    Open,
+    /// The cooldown has passed, and the next submission probes whether the L1 recovered.
+    HalfOpen,
+}
+
+/// Circuit breaker pausing L1 submissions after repeated failures.
+///
+/// The breaker opens after `threshold` consecutive failed submissions and pauses submissions for
+/// the cooldown. Once the cooldown has passed it's half-open: the next successful submission
+/// closes it, while a failed one opens it again. A zero threshold disables the breaker.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    /// Number of consecutive failed submissions.
+    failures: u32,
+    /// Time the breaker was last opened at, unless it's closed.
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed breaker opening after `threshold` consecutive failures for `cooldown`.
+    pub const fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self { threshold, cooldown, failures: 0, opened_at: None }
+    }
+
+    /// Returns `true` unless the breaker is disabled by a zero threshold.
+    pub const fn is_enabled(&self) -> bool {
+        self.threshold > 0
+    }
+
+    /// Returns the current state of the breaker.
+    pub fn state(&self) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Returns the time the cooldown of the breaker ends at, unless it's closed.
+    pub fn cooldown_end(&self) -> Option<Instant> {
+        self.opened_at.map(|opened_at| opened_at + self.cooldown)
+    }
+
+    /// Returns the number of consecutive failed submissions.
+    pub const fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Records a successful submission, closing the breaker.
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.opened_at = None;
+    }
+
+    /// Records a failed submission, returning `true` if it opened the breaker.
+    pub fn record_failure(&mut self) -> bool {
+        self.failures = self.failures.saturating_add(1);
+        if !self.is_enabled() || self.failures < self.threshold {
+            return false
+        }
+        self.opened_at = Some(Instant::now());
+        true
+    }
+}
+
+/// Checks that the L1 RPC endpoint of the [`StateRootContract`] is reachable and reports the
+/// [`ExExConfig::l1_chain_id`], if configured.
+///
+/// Also fails if the L1 is on the [`ExExConfig::local_chain_id`], which usually means that the L1
+/// RPC endpoint points back at the local node, unless [`ExExConfig::allow_same_chain`].
+async fn check_l1_chain<T: Transport + Clone>(
+    contract: &StateRootContract<T>,
+    config: &ExExConfig,
+) -> eyre::Result<()> {
+    let chain_id = contract
+        .chain_id()
+        .await
+        .map_err(|err| eyre::eyre!("L1 RPC endpoint {} is unreachable: {err}", config.l1_rpc))?;
+    if let Some(expected) = config.l1_chain_id {
+        if chain_id != expected {
+            eyre::bail!(
+                "L1 RPC endpoint {} is on chain {chain_id}, expected chain {expected}",
+                config.l1_rpc
+            )
+        }
+    }
+    if config.local_chain_id == Some(chain_id) {
+        if !config.allow_same_chain {
+            eyre::bail!(
+                "L1 RPC endpoint {} is on chain {chain_id}, the chain of the local node, so the \
+                 bridge would anchor the chain to itself; point it at the L1 or pass \
+                 --exex.allow-same-chain",
+                config.l1_rpc
+            )
+        }
+        warn!(target: "t1::bridge", chain_id, "L1 is the local chain, anchoring it to itself");
+    }
+    info!(target: "t1::bridge", rpc = %config.l1_rpc, chain_id, "Connected to L1");
+
+    Ok(())
+}
+
+/// Returns the address and code of the implementation of the state root contract, an EIP-1967
+/// proxy, see [`StateRootContract::implementation`].
+///
+/// Fails if the implementation slot of the contract is empty or there's no code at the
+/// implementation.
+pub(super) async fn proxy_implementation<T: Transport + Clone>(
+    contract: &StateRootContract<T>,
+) -> eyre::Result<(Address, Bytes)> {
+    let proxy = contract.address;
+    let implementation = contract.implementation().await?.ok_or_else(|| {
+        eyre::eyre!("state root contract {proxy} is not an EIP-1967 proxy, no implementation set")
+    })?;
+    let code = contract.code_at(implementation).await?;
+    if code.is_empty() {
+        eyre::bail!("no contract deployed at the implementation {implementation} of {proxy}")
+    }
+    Ok((implementation, code))
+}
+
+/// Resolves the implementation of the state root contract with [`ExExConfig::state_root_proxy`]
+/// and checks that its code contains the selectors of the methods the client calls, which are
+/// checked in the ABI by [`ExExConfig::state_root_selector`].
+///
+/// Returns the address of the implementation.
+async fn check_proxy_implementation<T: Transport + Clone>(
+    contract: &StateRootContract<T>,
+    config: &ExExConfig,
+) -> eyre::Result<Address> {
+    let proxy = contract.address;
+    let (implementation, code) = proxy_implementation(contract).await?;
+    let mut required: Vec<(&str, Selector)> = config.required_state_root_methods();
+    if config.legacy_state_root {
+        required.push((&config.state_root_method, config.state_root_selector()?));
+    }
+    for (signature, selector) in required {
+        if !code.windows(4).any(|window| window == selector.as_slice()) {
+            eyre::bail!(
+                "state root method {signature} not found in the code of the implementation \
+                 {implementation} of {proxy}"
+            )
+        }
+    }
+    info!(
+        target: "t1::bridge",
+        %proxy,
+        %implementation,
+        "Resolved the implementation of the state root contract"
+    );
+    Ok(implementation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        batch::RootBatch,
+        commands::selftest,
+        config::{L1_BATCH_SIZE, L1_DEFAULT_GAS_LIMIT},
+        contract::{blob_sidecar, EIP1967_IMPLEMENTATION_SLOT},
+        handler::{flush_l1, notify_l1},
+        test_utils::{
+            counter_chain, fee_history_json, metric_values, rpc_error, test_env, CapturedLogs,
+            MockTransport,
+        },
+        watermark::{Watermark, L1_WATERMARK_FILE},
+        AccountDiff,
+        StateRootAbi::{
+            self, changeStateRootAtCall, changeStateRootBatchCall, changeStateRootCall,
+        },
+    };
+    use alloy_consensus::{TxEip4844Variant, TxEnvelope};
+    use alloy_eips::{eip2718::Decodable2718, eip4844::kzg_to_versioned_hash};
+    use alloy_sol_types::SolCall;
+    use eyre::OptionExt;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use reth_testing_utils::generators;
+    use serde_json::json;
+    use std::str::FromStr;
+    use url::Url;
+
+    #[tokio::test]
+    async fn test_l1_client_unreachable() -> eyre::Result<()> {
+        let config = ExExConfig {
+            l1_rpc: Url::parse("http://127.0.0.1:1")?,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+
+        let err = L1Client::new(config.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("http://127.0.0.1:1/ is unreachable"), "{err}");
+
+        // The L1 isn't accessed in dry run mode
+        L1Client::new(ExExConfig { dry_run: true, ..config }).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_l1_client_unreachable_ipc() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("l1.ipc");
+        let config = ExExConfig {
+            l1_rpc: Url::from_file_path(&path).unwrap(),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+
+        let err = L1Client::new(config).await.unwrap_err();
+        assert!(err.to_string().contains("is unreachable"), "{err}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_l1_client_chain_id() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+
+        // The mock L1 reports chain 1
+        let transport = MockTransport::default();
+        let config = ExExConfig { l1_chain_id: Some(1), ..config };
+        L1Client::with_transport(config.clone(), transport.clone()).await?;
+        assert_eq!(transport.calls("eth_chainId"), 1);
+
+        let config = ExExConfig { l1_chain_id: Some(5), ..config };
+        let err = L1Client::with_transport(config, transport.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("is on chain 1, expected chain 5"), "{err}");
+        assert_eq!(transport.calls("eth_getTransactionCount"), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_l1_client_same_chain() -> eyre::Result<()> {
+        // The mock L1 reports chain 1, like the local node
+        let transport = MockTransport::default();
+        let config = ExExConfig { local_chain_id: Some(1), ..ExExConfig::from_env_with(test_env)? };
+        let err = L1Client::with_transport(config.clone(), transport.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("the chain of the local node"), "{err}");
+        assert_eq!(transport.calls("eth_getTransactionCount"), 0);
+
+        // Explicitly allowed for test setups
+        let config = ExExConfig { allow_same_chain: true, ..config };
+        L1Client::with_transport(config.clone(), transport.clone()).await?;
+
+        // Any other local chain is fine
+        let config = ExExConfig { local_chain_id: Some(5), allow_same_chain: false, ..config };
+        L1Client::with_transport(config, transport.clone()).await?;
+        assert_eq!(transport.calls("eth_getTransactionCount"), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submit_state_root_retries() -> eyre::Result<()> {
+        let config = ExExConfig {
+            max_retries: 2,
+            retry_base_delay: Duration::from_millis(1),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+
+        // Two failures are retried
+        let transport = MockTransport::failing(2);
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 3);
+
+        // The error is returned once the retries are exhausted
+        let transport = MockTransport::failing(3);
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        assert!(client.submit_state_root(B256::random()).await.is_err());
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submit_state_root_reverted() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig { breaker_threshold: 0, ..ExExConfig::from_env_with(test_env)? };
+        let transport = MockTransport::reverting();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        // Without the circuit breaker, the reverted submission is an error and the height is not
+        // anchored
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        let err = notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, L1SubmitError::TxReverted(_)), "{err}");
+        assert!(err.to_string().contains("reverted"), "{err}");
+        assert_eq!(transport.calls("eth_getTransactionReceipt"), 1);
+        assert_eq!(watermark.height(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_l1_submit_error() {
+        let err = L1SubmitError::from(TransportErrorKind::custom_str("connection refused"));
+        assert!(matches!(err, L1SubmitError::RpcUnavailable(_)), "{err}");
+
+        let err = L1SubmitError::from(TransportError::ErrorResp(rpc_error("nonce too low")));
+        assert!(matches!(err, L1SubmitError::NonceTooLow(_)), "{err}");
+
+        let err = L1SubmitError::from(TransportError::ErrorResp(rpc_error(
+            "insufficient funds for gas * price + value",
+        )));
+        assert!(matches!(err, L1SubmitError::InsufficientFunds(_)), "{err}");
+
+        // Errors the caller can't react to specifically, like a rejected transaction
+        let err = L1SubmitError::from(TransportError::ErrorResp(rpc_error("execution reverted")));
+        assert!(matches!(err, L1SubmitError::Other(_)), "{err}");
+        assert!(err.to_string().contains("execution reverted"), "{err}");
+        let err = L1SubmitError::from(eyre::eyre!("failed to write the L1 watermark"));
+        assert!(matches!(err, L1SubmitError::Other(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_submit_state_root_increments_nonce() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        transport.nonce.store(7, Ordering::SeqCst);
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+
+        client.submit_state_root(B256::random()).await?;
+        client.submit_state_root(B256::random()).await?;
+
+        let nonces = transport.sent_transactions().iter().map(|tx| tx.nonce()).collect::<Vec<_>>();
+        assert_eq!(nonces, vec![7, 8]);
+        assert_eq!(client.nonces.next(), 9);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_submit_confirm_timeout() -> eyre::Result<()> {
+        let config = ExExConfig {
+            confirm_poll_interval: Duration::from_millis(10),
+            confirm_timeout: Duration::from_millis(100),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport { unmined: usize::MAX, ..Default::default() };
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+
+        // The receipt is polled at the poll interval until the timeout passes
+        let start = Instant::now();
+        let err = client.submit_state_root(B256::random()).await.unwrap_err();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100) && elapsed < Duration::from_millis(110));
+        let L1SubmitError::Timeout { tx_hash, timeout } = err else {
+            panic!("expected a timeout, got {err}")
+        };
+        assert_eq!((tx_hash, timeout), (B256::repeat_byte(0x11), Duration::from_millis(100)));
+        assert!(transport.calls("eth_getTransactionReceipt") >= 10);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_submit_timeout_blocks() -> eyre::Result<()> {
+        let config = ExExConfig {
+            confirm_poll_interval: Duration::from_millis(10),
+            submit_timeout_blocks: Some(3),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport { unmined: usize::MAX, ..Default::default() };
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let submission = client.submit_state_root(B256::random());
+        tokio::pin!(submission);
+
+        // The submission waits regardless of the time passing, as long as the L1 doesn't reach
+        // the third block after the one it was sent at
+        let wait = Duration::from_millis(100);
+        assert!(tokio::time::timeout(wait, &mut submission).await.is_err());
+        transport.mine(2);
+        assert!(tokio::time::timeout(wait, &mut submission).await.is_err());
+
+        transport.mine(1);
+        let err = tokio::time::timeout(wait, submission).await?.unwrap_err();
+        let L1SubmitError::BlockTimeout { tx_hash, blocks } = err else {
+            panic!("expected a block timeout, got {err}")
+        };
+        assert_eq!((tx_hash, blocks), (B256::repeat_byte(0x11), 3));
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_submit_rpc_timeout() -> eyre::Result<()> {
+        let timeout = Duration::from_millis(100);
+        let config = ExExConfig {
+            l1_rpc_timeout: timeout,
+            retry_base_delay: Duration::from_millis(1),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+
+        // An L1 that never answers fails the client at startup instead of blocking it
+        let transport = MockTransport::hanging("eth_chainId");
+        let err = L1Client::with_transport(config.clone(), transport).await.unwrap_err();
+        assert!(err.to_string().contains("timed out after 100ms"), "{err}");
+
+        // Every attempt to send times out, and is retried up to the maximum
+        let transport = MockTransport::hanging("eth_sendRawTransaction");
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let start = Instant::now();
+        let err = client.submit_state_root(B256::random()).await.unwrap_err();
+        assert!(matches!(err, L1SubmitError::RpcTimeout(t) if t == timeout), "{err}");
+        let attempts = config.max_retries + 1;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), attempts);
+        assert!(start.elapsed() >= timeout * attempts as u32);
+
+        // A receipt poll that times out is repeated instead of sending the transaction again
+        let config = ExExConfig {
+            confirm_poll_interval: Duration::from_millis(10),
+            confirm_timeout: Duration::from_secs(1),
+            ..config
+        };
+        let transport = MockTransport::hanging("eth_getTransactionReceipt");
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let err = client.submit_state_root(B256::random()).await.unwrap_err();
+        assert!(matches!(err, L1SubmitError::Timeout { .. }), "{err}");
+        assert!(transport.calls("eth_getTransactionReceipt") > 1);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_submit_rate_limited() -> eyre::Result<()> {
+        let config = ExExConfig {
+            submit_rate: 2.0,
+            submit_burst: 2,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+
+        let start = Instant::now();
+        let mut elapsed = Vec::new();
+        for _ in 0..5 {
+            client.submit_state_root(B256::random()).await?;
+            elapsed.push(start.elapsed());
+        }
+
+        // The burst is submitted right away, and the rest at 2 submissions per second
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 5);
+        for (elapsed, expected) in elapsed.into_iter().zip([0, 0, 500, 1_000, 1_500]) {
+            let expected = Duration::from_millis(expected);
+            assert!(elapsed >= expected, "{elapsed:?} < {expected:?}");
+            assert!(elapsed < expected + Duration::from_millis(100), "{elapsed:?} !~ {expected:?}");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submit_state_root_resyncs_nonce_too_low() -> eyre::Result<()> {
+        let config = ExExConfig {
+            retry_base_delay: Duration::from_millis(1),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::failing_with(VecDeque::from([rpc_error("nonce too low")]));
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+
+        // Another transaction of the account was included in the meantime
+        transport.nonce.store(1, Ordering::SeqCst);
+        client.submit_state_root(B256::random()).await?;
+
+        let nonces = transport.sent_transactions().iter().map(|tx| tx.nonce()).collect::<Vec<_>>();
+        assert_eq!(nonces, vec![0, 1]);
+        assert_eq!(client.nonces.next(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate_eip1559_fees() -> eyre::Result<()> {
+        let history: FeeHistory = serde_json::from_value(fee_history_json())?;
+
+        // Median reward of 2, scaled to 3, on top of twice the next base fee of 20
+        assert_eq!(
+            estimate_eip1559_fees(&history, 1.5, None),
+            Some(Eip1559Fees { max_fee_per_gas: 43, max_priority_fee_per_gas: 3 })
+        );
+
+        // The max fee is capped, and the priority fee with it
+        assert_eq!(
+            estimate_eip1559_fees(&history, 1.0, Some(30)),
+            Some(Eip1559Fees { max_fee_per_gas: 30, max_priority_fee_per_gas: 2 })
+        );
+        assert_eq!(
+            estimate_eip1559_fees(&history, 1.0, Some(1)),
+            Some(Eip1559Fees { max_fee_per_gas: 1, max_priority_fee_per_gas: 1 })
+        );
+
+        // No fees are estimated without a base fee
+        let history = FeeHistory { base_fee_per_gas: vec![0], ..history };
+        assert_eq!(estimate_eip1559_fees(&history, 1.0, None), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submit_state_root_fees() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        let tx = transport.sent_transactions().remove(0);
+        assert_eq!(tx.tx_type(), reth_primitives::TxType::Eip1559);
+        assert_eq!(tx.max_fee_per_gas(), 42);
+        assert_eq!(tx.max_priority_fee_per_gas(), Some(2));
+
+        // Legacy gas pricing is used if the L1 doesn't support EIP-1559
+        let transport = MockTransport::legacy();
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        let tx = transport.sent_transactions().remove(0);
+        assert_eq!(tx.tx_type(), reth_primitives::TxType::Legacy);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_submit_state_root_fee_bump() -> eyre::Result<()> {
+        let config = ExExConfig {
+            fee_bump_timeout: Duration::from_secs(30),
+            max_fee_bumps: 2,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+
+        // A transaction that isn't mined in time is replaced with the same nonce and fees bumped
+        // by 20%
+        let transport = MockTransport { unmined: 1, ..Default::default() };
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 2);
+        assert_eq!((txs[0].nonce(), txs[1].nonce()), (0, 0));
+        assert_eq!(txs[0].input(), txs[1].input());
+        assert_eq!((txs[0].max_fee_per_gas(), txs[1].max_fee_per_gas()), (42, 51));
+        assert_eq!(txs[1].max_priority_fee_per_gas(), Some(3));
+        assert_eq!(client.nonces.next(), 1);
+
+        // Legacy transactions get a bumped gas price
+        let transport = MockTransport { unmined: 1, ..MockTransport::legacy() };
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        let txs = transport.sent_transactions();
+        assert_eq!((txs[0].max_fee_per_gas(), txs[1].max_fee_per_gas()), (1, 2));
+
+        // A transaction that is never mined is replaced at most `max_fee_bumps` times
+        let transport = MockTransport { unmined: usize::MAX, ..Default::default() };
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let submit = client.submit_state_root(B256::random());
+        assert!(tokio::time::timeout(Duration::from_secs(3600), submit).await.is_err());
+        let fees = transport
+            .sent_transactions()
+            .iter()
+            .map(|tx| (tx.nonce(), tx.max_fee_per_gas()))
+            .collect::<Vec<_>>();
+        assert_eq!(fees, vec![(0, 42), (0, 51), (0, 62)]);
+
+        // The max fee cap isn't exceeded by a replacement
+        let config = ExExConfig { max_fee_per_gas_cap: Some(50), ..config };
+        let transport = MockTransport { unmined: usize::MAX, ..Default::default() };
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let submit = client.submit_state_root(B256::random());
+        assert!(tokio::time::timeout(Duration::from_secs(3600), submit).await.is_err());
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submit_state_root_gas_limit() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+
+        // The estimate is scaled by the multiplier and rounded up
+        let transport = MockTransport::with_gas_estimate(30_001);
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.sent_transactions()[0].gas_limit(), 37_502);
+
+        // The default is used if the estimation fails
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.sent_transactions()[0].gas_limit(), L1_DEFAULT_GAS_LIMIT);
+
+        // The override takes precedence over the estimate
+        let config = ExExConfig { gas_limit: Some(60_000), ..config };
+        let transport = MockTransport::with_gas_estimate(30_001);
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.sent_transactions()[0].gas_limit(), 60_000);
+        assert_eq!(transport.calls("eth_estimateGas"), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_submit_state_root_cached_gas_estimate() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::with_gas_estimate(30_001);
+
+        // The gas is estimated once when the client is created
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        assert_eq!(transport.calls("eth_estimateGas"), 1);
+
+        // And the cached estimate is scaled by the multiplier for every submission
+        client.submit_state_root(B256::random()).await?;
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.calls("eth_estimateGas"), 1);
+        let gas_limits = transport.sent_transactions().iter().map(|tx| tx.gas_limit());
+        assert_eq!(gas_limits.collect::<Vec<_>>(), vec![37_502, 37_502]);
+
+        // Until it's refreshed after the interval
+        tokio::time::advance(config.gas_estimate_refresh).await;
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.calls("eth_estimateGas"), 2);
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.calls("eth_estimateGas"), 2);
+
+        // Without a refresh interval every submission is estimated
+        let config = ExExConfig { gas_estimate_refresh: Duration::ZERO, ..config };
+        let transport = MockTransport::with_gas_estimate(30_001);
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        client.submit_state_root(B256::random()).await?;
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.calls("eth_estimateGas"), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gas_estimate_cache() {
+        let cache = GasEstimateCache::new(Duration::from_secs(60), 20.0);
+        let (single, at) = (StateRootCall::Single(B256::ZERO), StateRootCall::At(1, B256::ZERO));
+        assert_eq!(cache.get(&at), None);
+
+        // The estimate is only returned for the same kind of call
+        cache.insert(&at, 30_000);
+        assert_eq!(cache.get(&at), Some(30_000));
+        assert_eq!(cache.get(&single), None);
+
+        // A batch varies in size and isn't cached
+        let batch = StateRootCall::Batch(vec![(1, B256::ZERO), (2, B256::ZERO)]);
+        cache.insert(&batch, 50_000);
+        assert_eq!(cache.get(&batch), None);
+        assert_eq!(cache.get(&at), Some(30_000));
+
+        // Gas used within the deviation keeps the estimate
+        assert!(!cache.observe(&at, 24_000));
+        assert!(!cache.observe(&batch, 100_000));
+        assert_eq!(cache.get(&at), Some(30_000));
+
+        // Gas used beyond it drops the estimate, in either direction
+        assert!(cache.observe(&at, 36_001));
+        assert_eq!(cache.get(&at), None);
+        cache.insert(&at, 30_000);
+        assert!(cache.observe(&at, 23_999));
+        assert_eq!(cache.get(&at), None);
+
+        // A zero refresh interval disables the cache
+        let cache = GasEstimateCache::new(Duration::ZERO, 20.0);
+        cache.insert(&at, 30_000);
+        assert_eq!(cache.get(&at), None);
+    }
+
+    #[tokio::test]
+    async fn test_mock_state_root_sink() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config =
+            ExExConfig { dedup_state_roots: false, ..ExExConfig::from_env_with(test_env)? };
+        let transport = MockTransport::default();
+        let sink = Arc::new(MockStateRootSink::default());
+        let client = L1Client::with_transport(config.clone(), transport.clone())
+            .await?
+            .with_sink(sink.clone());
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(2, Duration::ZERO);
+
+        // The state roots are anchored in the sink one by one, nothing is sent to L1
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        let roots = chain.blocks().values().map(|block| (block.number, block.header.state_root));
+        assert_eq!(sink.submissions(), roots.collect::<Vec<_>>());
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert_eq!(watermark.height(), Some(2));
+        assert_eq!(client.status().get().last_l1_tx_hash, Some(TxHash::from(U256::from(2))));
+
+        // A failing sink keeps the state root queued by the circuit breaker
+        sink.fail_next(L1SubmitError::TxReverted(TxHash::ZERO));
+        let chain = counter_chain(&mut rng, 3..=3, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(sink.submissions().len(), 2);
+        assert_eq!((batch.len(), watermark.height()), (1, Some(2)));
+
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert_eq!(sink.submissions().last(), Some(&(3, chain.tip().header.state_root)));
+        assert_eq!(watermark.height(), Some(3));
+
+        // Without the circuit breaker, the error of the sink is returned
+        let config = ExExConfig { breaker_threshold: 0, ..config };
+        let sink =
+            Arc::new(MockStateRootSink::failing_with([L1SubmitError::TxReverted(TxHash::ZERO)]));
+        let client = L1Client::with_transport(config, transport).await?.with_sink(sink);
+        let err = client.submit_state_roots(&[(4, B256::ZERO)]).await.unwrap_err();
+        assert!(matches!(err, L1SubmitError::TxReverted(_)), "{err}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_l1_client_state_root_sink() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let sink: &dyn StateRootSink = &client;
+
+        let state_root = B256::with_last_byte(1);
+        sink.submit(5, state_root).await?;
+        assert_eq!(transport.anchored_state_roots()?, vec![vec![(5, state_root)]]);
+
+        let config = ExExConfig { dry_run: true, ..config };
+        let client = L1Client::with_transport(config, transport).await?;
+        assert!(StateRootSink::submit(&client, 6, state_root).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_explicit_state_root_sink() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            dedup_state_roots: false,
+            retry_base_delay: Duration::from_millis(1),
+            submit_rate: 2.0,
+            submit_burst: 1,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let mut client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        client.metrics = reth_metrics::metrics::with_local_recorder(&recorder, L1Metrics::default);
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(2, Duration::ZERO);
+
+        // The given sink anchors the state roots instead of the contract of the client. Transient
+        // failures are retried and counted, and every state root waits for the rate limiter
+        let sink = MockStateRootSink::failing_with(
+            (0..2).map(|_| L1SubmitError::RpcTimeout(Duration::from_secs(1))),
+        );
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        let start = Instant::now();
+        notify_l1(&client, &sink, &mut watermark, &mut batch, &chain).await?;
+        assert!(start.elapsed() >= Duration::from_millis(500), "{:?}", start.elapsed());
+        let roots = chain.blocks().values().map(|block| (block.number, block.header.state_root));
+        assert_eq!(sink.submissions(), roots.collect::<Vec<_>>());
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert_eq!(watermark.height(), Some(2));
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.submission_failures_total"], DebugValue::Counter(2));
+
+        // Other failures aren't retried
+        sink.fail_next(L1SubmitError::TxReverted(TxHash::ZERO));
+        let err = client.submit_state_roots_to(&sink, &[(3, B256::ZERO)]).await.unwrap_err();
+        assert!(matches!(err, L1SubmitError::TxReverted(_)), "{err}");
+        assert_eq!(sink.submissions().len(), 2);
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.submission_failures_total"], DebugValue::Counter(1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submit_state_diff_blob() -> eyre::Result<()> {
+        let config = ExExConfig {
+            state_diffs: true,
+            state_diff_blobs: true,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let diff = StateDiff(vec![AccountDiff {
+            account: Address::with_last_byte(1),
+            destroyed: false,
+            nonce: 1,
+            balance: U256::from(100),
+            codeHash: B256::repeat_byte(0xc0),
+            storage: vec![],
+        }]);
+        let state_root = B256::random();
+        client.submit_state_diff(1, state_root, &diff).await?;
+
+        // The state root is anchored with `changeStateRootAt`, the diff is carried by the blob
+        let requests = transport.requests.lock().unwrap().clone();
+        let (_, params) = requests
+            .iter()
+            .find(|(method, _)| method == "eth_sendRawTransaction")
+            .ok_or_eyre("no transaction sent")?;
+        let raw = params[0].as_str().ok_or_eyre("raw transaction isn't a string")?;
+        let raw = alloy_primitives::hex::decode(raw)?;
+        let TxEnvelope::Eip4844(tx) = TxEnvelope::decode_2718(&mut raw.as_slice())? else {
+            panic!("not a blob transaction")
+        };
+        let TxEip4844Variant::TxEip4844WithSidecar(tx) = tx.tx() else {
+            panic!("blob transaction without a sidecar")
+        };
+        let call = changeStateRootAtCall::abi_decode(&tx.tx.input, true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(1), state_root));
+        let sidecar = blob_sidecar(&diff.encode())?;
+        assert_eq!(tx.sidecar, sidecar);
+
+        // The versioned hash commits to the blob, at twice the blob base fee
+        let versioned_hash = kzg_to_versioned_hash(sidecar.commitments[0].as_slice());
+        assert_eq!(tx.tx.blob_versioned_hashes, vec![versioned_hash]);
+        assert_eq!(tx.tx.max_fee_per_blob_gas, 2);
+
+        // Blobs are only used for diffs
+        let err = ExExConfig::from_env_with(|key| {
+            (key == "L1_STATE_DIFF_BLOBS").then(|| "true".to_string())
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("requires L1_STATE_DIFFS"), "{err}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_balance() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = ExExConfig {
+            min_balance: 1_000_000_000_000_000_000,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let client = |balance: u128| {
+            let transport = MockTransport { balance, ..Default::default() };
+            let metrics = reth_metrics::metrics::with_local_recorder(&recorder, L1Metrics::default);
+            let config = config.clone();
+            async move {
+                let client = L1Client::with_transport(config, transport).await?;
+                eyre::Ok(client.with_metrics(metrics))
+            }
+        };
+
+        // A balance below the threshold is warned about, including the balance
+        let low = 1_000_000_000_000;
+        assert_eq!(client(low).await?.check_balance().await?, BalanceStatus::Low);
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("L1 account balance is low"))
+            .ok_or_eyre("low balance not warned about")?;
+        assert!(line.contains("WARN"), "{line}");
+        assert!(line.contains(&format!("balance={low}")), "{line}");
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.account_balance_wei"], DebugValue::Gauge((low as f64).into()));
+        assert_eq!(values["exex.l1.low_balance_warnings_total"], DebugValue::Counter(1));
+
+        // A balance that can't pay for the default gas limit at the max fee is an error
+        assert_eq!(client(1_000).await?.check_balance().await?, BalanceStatus::Insufficient);
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("can't pay for the next submission"))
+            .ok_or_eyre("insufficient balance not reported")?;
+        assert!(line.contains("ERROR"), "{line}");
+        assert!(line.contains("balance=1000"), "{line}");
+
+        // Neither is a balance at the threshold
+        let balance = config.min_balance;
+        assert_eq!(client(balance).await?.check_balance().await?, BalanceStatus::Sufficient);
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.low_balance_warnings_total"], DebugValue::Counter(1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_submit_value() -> eyre::Result<()> {
+        let value = 1_000_000_000_000_000_000;
+        let config = ExExConfig { submit_value: value, ..ExExConfig::from_env_with(test_env)? };
+        let transport =
+            MockTransport { balance: value, ..MockTransport::with_gas_estimate(21_000) };
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+
+        // The value is attached to the submitted transaction and to its gas estimation
+        client.submit_state_root(B256::random()).await?;
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].value(), U256::from(value));
+        let requests = transport.requests.lock().unwrap().clone();
+        let (_, params) = requests
+            .iter()
+            .find(|(method, _)| method == "eth_estimateGas")
+            .ok_or_eyre("gas not estimated")?;
+        assert_eq!(params[0]["value"], json!(U256::from(value)));
+
+        // A balance covering only the value can't pay for the gas of the next submission on top
+        assert_eq!(client.check_balance().await?, BalanceStatus::Insufficient);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_state_root_proxy() -> eyre::Result<()> {
+        let config = ExExConfig { state_root_proxy: true, ..ExExConfig::from_env_with(test_env)? };
+        let proxy = config.state_root_contract;
+        let implementation = Address::random();
+        // Code dispatching the given selectors
+        let dispatching = |selectors: &[[u8; 4]]| {
+            Bytes::from(selectors.iter().flat_map(|selector| [&[0x63][..], selector].concat()))
+        };
+        let methods = [
+            changeStateRootCall::SELECTOR,
+            changeStateRootAtCall::SELECTOR,
+            changeStateRootBatchCall::SELECTOR,
+        ];
+        // The proxy only delegates to its implementation, which has all the methods
+        let transport = MockTransport {
+            code: Bytes::from_static(&[0x36, 0x3d, 0xf4]),
+            implementation: Some((implementation, dispatching(&methods))),
+            ..Default::default()
+        };
+
+        // The implementation is resolved from its EIP-1967 slot of the proxy
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        assert_eq!(check_proxy_implementation(&client.contract, &config).await?, implementation);
+        let requests = transport.requests.lock().unwrap().clone();
+        let (_, params) = requests
+            .iter()
+            .find(|(method, _)| method == "eth_getStorageAt")
+            .ok_or_eyre("implementation slot not read")?;
+        assert_eq!(params[0], json!(proxy));
+        let slot = U256::from_str(params[1].as_str().ok_or_eyre("slot is not a string")?)?;
+        assert_eq!(B256::from(slot), EIP1967_IMPLEMENTATION_SLOT);
+
+        // The transactions are still sent to the proxy
+        client.submit_state_root(B256::random()).await?;
+        assert_eq!(transport.sent_transactions()[0].to(), Some(proxy));
+
+        // So is the selftest, which checks the method in the code of the implementation
+        let transport = MockTransport { balance: u128::MAX, ..transport };
+        let checks = selftest(&config, transport, false).await;
+        assert!(checks.iter().all(|check| check.passed), "{checks:?}");
+
+        // An implementation without a method the client calls can't be submitted to
+        let transport = MockTransport {
+            implementation: Some((implementation, dispatching(&methods[..2]))),
+            ..Default::default()
+        };
+        let err = L1Client::with_transport(config.clone(), transport).await.unwrap_err();
+        let signature = changeStateRootBatchCall::SIGNATURE;
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "state root method {signature} not found in the code of the implementation \
+                 {implementation} of {proxy}"
+            )
+        );
+
+        // Neither can a contract that isn't a proxy
+        let transport = MockTransport { code: dispatching(&methods), ..Default::default() };
+        let err = L1Client::with_transport(config.clone(), transport).await.unwrap_err();
+        assert!(err.to_string().contains("is not an EIP-1967 proxy"), "{err}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_anchored_state_root() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport_holding = |state_root: B256| {
+            let anchored = Bytes::copy_from_slice(state_root.as_slice());
+            let output = StateRootAbi::stateRootCall::abi_encode_returns(&(anchored,));
+            MockTransport::with_call_output(output)
+        };
+        let (submitted, accepted) = (B256::repeat_byte(0x11), B256::repeat_byte(0x22));
+
+        // The transaction is confirmed, but the contract holds another state root
+        let transport = transport_holding(accepted);
+        let mut client = L1Client::with_transport(config.clone(), transport).await?;
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        client.metrics = reth_metrics::metrics::with_local_recorder(&recorder, L1Metrics::default);
+        assert!(client.submit_state_roots(&[(1, submitted)]).await?.is_some());
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.anchored_root_mismatches_total"], DebugValue::Counter(1));
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("doesn't hold the state root of the confirmed submission"))
+            .ok_or_eyre("mismatch not reported")?;
+        assert!(line.contains("ERROR"), "{line}");
+        assert!(line.contains(&format!("state_root={submitted}")), "{line}");
+        assert!(line.contains(&format!("anchored=Some({accepted})")), "{line}");
+
+        // The contract holds the submitted state root
+        let mut client = L1Client::with_transport(config, transport_holding(submitted)).await?;
+        client.metrics = reth_metrics::metrics::with_local_recorder(&recorder, L1Metrics::default);
+        assert!(client.submit_state_roots(&[(2, submitted)]).await?.is_some());
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.anchored_root_mismatches_total"], DebugValue::Counter(0));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,702 @@
+//! The `reth t1` commands of the bridge.
+
+use super::{
+    client::{proxy_implementation, BalanceStatus, L1Client},
+    config::{ExExConfig, L1Endpoint},
+    contract::StateRootCall,
+    history::{SubmissionHistory, L1_HISTORY_FILE},
+    watermark::Watermark,
+};
+use crate::args::{utils::DefaultChainSpecParser, LogArgs};
+use alloy_primitives::{BlockNumber, Selector, TxHash, B256};
+use alloy_transport::Transport;
+use clap::{Parser, Subcommand};
+use futures::StreamExt;
+use reth_chainspec::{EthChainSpec, EthereumHardforks};
+use reth_cli::chainspec::ChainSpecParser;
+use reth_cli_commands::common::{AccessRights, Environment, EnvironmentArgs};
+use reth_cli_runner::CliRunner;
+use reth_node_builder::NodeTypesWithEngine;
+use reth_node_ethereum::EthereumNode;
+use reth_provider::HeaderProvider;
+use std::{cell::Cell, fmt, num::NonZeroUsize, ops::RangeInclusive, time::Duration};
+use tokio::time::Instant;
+use tracing::info;
+
+/// Name of the file in the datadir the progress of the `t1 backfill` command is persisted to.
+const L1_BACKFILL_PROGRESS_FILE: &str = "t1-l1-backfill";
+
+/// Anchors the state root of the block `number`, read from the provider, on L1, returning the
+/// transaction hash.
+///
+/// Unlike [`notify_l1`](super::handler::notify_l1), the [`Watermark`] is ignored, so an already
+/// anchored state root can be replayed.
+async fn reanchor<T: Transport + Clone, P: HeaderProvider>(
+    client: &L1Client<T>,
+    provider: &P,
+    number: BlockNumber,
+) -> eyre::Result<TxHash> {
+    let header = provider
+        .sealed_header(number)?
+        .ok_or_else(|| eyre::eyre!("block {number} not found in the local database"))?;
+    let state_root = client.state_root(&header)?;
+    info!(target: "t1::bridge", number, %state_root, "Re-anchoring state root");
+    client
+        .submit_state_roots(&[(number, state_root)])
+        .await?
+        .ok_or_else(|| eyre::eyre!("dry run, state root of block {number} not re-anchored"))
+}
+
+/// Anchors the state roots of the blocks in `range`, read from the provider, on L1, in
+/// transactions of at most [`L1Client::max_batch_len`] state roots.
+///
+/// Up to `workers` transactions are in flight at once. With
+/// [`ExExConfig::state_roots_per_height`] the contract holds the state root of every block
+/// number, so the order the transactions are confirmed in doesn't matter. A contract holding only
+/// the latest state root may hold an earlier one than the previous transaction anchored while the
+/// backfill runs, so the last transaction is only sent once all others are confirmed, leaving the
+/// contract at the state root of the end of the range.
+///
+/// The `progress` is advanced in order after every confirmed transaction, never past one that
+/// isn't confirmed yet, and a backfill of the same range resumes after it. Transactions are sent
+/// at least `delay` apart, to respect the rate limits of the L1 RPC endpoint.
+async fn backfill<T: Transport + Clone, P: HeaderProvider>(
+    client: &L1Client<T>,
+    provider: &P,
+    range: RangeInclusive<BlockNumber>,
+    progress: &mut Watermark,
+    delay: Duration,
+    workers: NonZeroUsize,
+) -> eyre::Result<()> {
+    let (from, to) = range.clone().into_inner();
+    let start = match progress.height() {
+        Some(height) if range.contains(&height) => {
+            info!(target: "t1::bridge", from, to, height, "Resuming backfill");
+            height + 1
+        }
+        _ => from,
+    };
+
+    let numbers = (start..=to).collect::<Vec<_>>();
+    let mut chunks = numbers.chunks(client.max_batch_len()).map(<[_]>::to_vec).collect::<Vec<_>>();
+    let last = if client.config().state_roots_per_height { None } else { chunks.pop() };
+
+    // Instant the next transaction may be sent at
+    let next_send = &Cell::new(Instant::now());
+    let submit_chunk = |chunk: Vec<BlockNumber>| async move {
+        let send_at = next_send.get().max(Instant::now());
+        next_send.set(send_at + delay);
+        tokio::time::sleep_until(send_at).await;
+
+        let roots = chunk
+            .iter()
+            .map(|&number| {
+                let header = provider
+                    .sealed_header(number)?
+                    .ok_or_else(|| eyre::eyre!("block {number} not found in the local database"))?;
+                Ok((number, client.state_root(&header)?))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let tx_id = client.submit_state_roots(&roots).await?;
+        eyre::Ok((roots, tx_id))
+    };
+    let submissions = futures::stream::iter(chunks)
+        .map(submit_chunk)
+        .buffered(workers.get())
+        .chain(futures::stream::iter(last).then(submit_chunk));
+    let mut submissions = std::pin::pin!(submissions);
+
+    while let Some((roots, tx_id)) = submissions.next().await.transpose()? {
+        let Some(tx_id) = tx_id else { continue };
+
+        let number = roots[roots.len() - 1].0;
+        info!(
+            target: "t1::bridge",
+            from,
+            to,
+            number,
+            roots = roots.len(),
+            %tx_id,
+            "Backfilled state roots"
+        );
+        progress.update(number)?;
+    }
+
+    Ok(())
+}
+
+/// Commands of the L1 bridge, run as `reth t1 <COMMAND>`.
+///
+/// The bridge is configured from the environment like the ExEx, see [`ExExConfig::from_env`].
+#[derive(Debug, Parser)]
+#[command(name = "reth t1", about = "Commands of the t1 L1 bridge")]
+pub struct T1Cli {
+    /// The command to run
+    #[command(subcommand)]
+    command: T1Commands,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+impl T1Cli {
+    /// Executes the command.
+    pub fn run(self) -> eyre::Result<()> {
+        let _guard = self.logs.init_tracing()?;
+
+        let runner = CliRunner::default();
+        match self.command {
+            T1Commands::Reanchor(command) => {
+                runner.run_blocking_until_ctrl_c(command.execute::<EthereumNode>())
+            }
+            T1Commands::Backfill(command) => {
+                runner.run_blocking_until_ctrl_c(command.execute::<EthereumNode>())
+            }
+            T1Commands::History(command) => command.execute(),
+            T1Commands::Selftest(command) => runner.run_blocking_until_ctrl_c(command.execute()),
+        }
+    }
+}
+
+/// Commands of the L1 bridge
+#[derive(Debug, Subcommand)]
+pub enum T1Commands {
+    /// Re-anchor the state root of a historical block on L1
+    #[command(name = "reanchor")]
+    Reanchor(ReanchorCommand<DefaultChainSpecParser>),
+    /// Anchor the state roots of a range of historical blocks on L1
+    #[command(name = "backfill")]
+    Backfill(BackfillCommand<DefaultChainSpecParser>),
+    /// Print the history of the state roots anchored on L1
+    #[command(name = "history")]
+    History(HistoryCommand<DefaultChainSpecParser>),
+    /// Check that the configured L1 bridge can anchor state roots, without running a node
+    #[command(name = "selftest")]
+    Selftest(SelftestCommand),
+}
+
+/// Submits the state root of a locally stored block to L1 again, e.g. after an L1-side incident
+#[derive(Debug, Parser)]
+pub struct ReanchorCommand<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+
+    /// Number of the block whose state root is anchored
+    #[arg(long, value_name = "BLOCK_NUMBER")]
+    block: BlockNumber,
+}
+
+impl<C: ChainSpecParser<ChainSpec: EthChainSpec + EthereumHardforks>> ReanchorCommand<C> {
+    /// Execute the `t1 reanchor` command
+    pub async fn execute<N: NodeTypesWithEngine<ChainSpec = C::ChainSpec>>(
+        self,
+    ) -> eyre::Result<()> {
+        let Environment { provider_factory, data_dir, .. } =
+            self.env.init::<N>(AccessRights::RO)?;
+        let client = L1Client::new(ExExConfig::from_env()?)
+            .await?
+            .with_history(SubmissionHistory::new(data_dir.data_dir().join(L1_HISTORY_FILE)));
+
+        let tx_hash = reanchor(&client, &provider_factory, self.block).await?;
+        println!("{tx_hash}");
+
+        Ok(())
+    }
+}
+
+/// Submits the state roots of a range of locally stored blocks to L1, e.g. when onboarding a new
+/// L1 contract
+///
+/// An interrupted backfill resumes where it stopped when run again with the same range.
+#[derive(Debug, Parser)]
+pub struct BackfillCommand<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+
+    /// Number of the first block whose state root is anchored
+    #[arg(long, value_name = "BLOCK_NUMBER")]
+    from: BlockNumber,
+
+    /// Number of the last block whose state root is anchored
+    #[arg(long, value_name = "BLOCK_NUMBER")]
+    to: BlockNumber,
+
+    /// Minimum time between two L1 submissions in milliseconds
+    #[arg(long = "delay-ms", value_name = "MILLIS", default_value_t = 0)]
+    delay_ms: u64,
+
+    /// Maximum number of L1 submissions in flight at once. Without per-height state roots, the
+    /// last submission is only sent once all others are confirmed
+    #[arg(long, value_name = "N", default_value = "1")]
+    workers: NonZeroUsize,
+}
+
+impl<C: ChainSpecParser<ChainSpec: EthChainSpec + EthereumHardforks>> BackfillCommand<C> {
+    /// Execute the `t1 backfill` command
+    pub async fn execute<N: NodeTypesWithEngine<ChainSpec = C::ChainSpec>>(
+        self,
+    ) -> eyre::Result<()> {
+        if self.from > self.to {
+            eyre::bail!("--from {} is above --to {}", self.from, self.to)
+        }
+
+        let Environment { provider_factory, data_dir, .. } =
+            self.env.init::<N>(AccessRights::RO)?;
+        let client = L1Client::new(ExExConfig::from_env()?)
+            .await?
+            .with_history(SubmissionHistory::new(data_dir.data_dir().join(L1_HISTORY_FILE)));
+        let mut progress = Watermark::load(data_dir.data_dir().join(L1_BACKFILL_PROGRESS_FILE))?;
+
+        let delay = Duration::from_millis(self.delay_ms);
+        let range = self.from..=self.to;
+        backfill(&client, &provider_factory, range, &mut progress, delay, self.workers).await
+    }
+}
+
+/// Prints the [`SubmissionRecord`](super::history::SubmissionRecord)s of the [`SubmissionHistory`]
+/// in the datadir as JSON lines, in the order the state roots were anchored in
+#[derive(Debug, Parser)]
+pub struct HistoryCommand<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+
+    /// Number of the first block whose records are printed
+    #[arg(long, value_name = "BLOCK_NUMBER", default_value_t = 0)]
+    from: BlockNumber,
+}
+
+impl<C: ChainSpecParser<ChainSpec: EthChainSpec>> HistoryCommand<C> {
+    /// Execute the `t1 history` command
+    pub fn execute(self) -> eyre::Result<()> {
+        let data_dir = self.env.datadir.clone().resolve_datadir(self.env.chain.chain());
+        let history = SubmissionHistory::new(data_dir.data_dir().join(L1_HISTORY_FILE));
+        for record in history.read(self.from)? {
+            println!("{}", serde_json::to_string(&record)?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the L1 bridge configuration against the L1 and prints a checklist of the results, see
+/// [`selftest`]
+#[derive(Debug, Parser)]
+pub struct SelftestCommand {
+    /// Also anchor a zero state root with `changeStateRoot` and wait for it to be confirmed. This
+    /// overwrites the latest state root held by the contract, so only use it on a test deployment
+    #[arg(long, default_value = "false")]
+    send: bool,
+}
+
+impl SelftestCommand {
+    /// Execute the `t1 selftest` command, failing if any check failed
+    pub async fn execute(self) -> eyre::Result<()> {
+        let config = ExExConfig::from_env()?;
+        let transport = L1Endpoint::parse(&config.l1_rpc)?.connect().await.map_err(|err| {
+            eyre::eyre!("L1 RPC endpoint {} is unreachable: {err}", config.l1_rpc)
+        })?;
+
+        let checks = selftest(&config, transport, self.send).await;
+        for check in &checks {
+            println!("{check}");
+        }
+        let failed = checks.iter().filter(|check| !check.passed).count();
+        if failed > 0 {
+            eyre::bail!("{failed} of {} checks failed", checks.len())
+        }
+
+        Ok(())
+    }
+}
+
+/// Result of a check of the `t1 selftest` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelftestCheck {
+    /// Name of the check.
+    pub name: &'static str,
+    /// Whether the check passed.
+    pub passed: bool,
+    /// What the check found, or why it failed.
+    pub detail: String,
+}
+
+impl SelftestCheck {
+    fn new(name: &'static str, result: eyre::Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self { name, passed: true, detail },
+            Err(err) => Self { name, passed: false, detail: err.to_string() },
+        }
+    }
+}
+
+impl fmt::Display for SelftestCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = if self.passed { "PASS" } else { "FAIL" };
+        write!(f, "[{status}] {}: {}", self.name, self.detail)
+    }
+}
+
+/// Checks that the L1 bridge can anchor state roots with the given configuration over the given
+/// transport, returning the result of every check, in order:
+/// 1. The state root contract ABI has the methods the client calls, see
+///    [`ExExConfig::state_root_selector`], unless the selector is overridden.
+/// 2. The L1 is reachable and on the expected chain, see [`L1Client::with_transport`].
+/// 3. The state root contract is deployed.
+/// 4. Its code contains the selector a single state root is anchored with. A proxy dispatching to
+///    another contract fails this check, unless [`ExExConfig::state_root_proxy`] is set and the
+///    code of its implementation contains it.
+/// 5. The L1 account can pay for a submission, see [`L1Client::check_balance`].
+/// 6. With `send`, a zero state root is anchored with `changeStateRoot` and confirmed.
+///
+/// Checks that depend on a failed one aren't run, and nothing is sent if any check failed.
+pub(super) async fn selftest<T: Transport + Clone>(
+    config: &ExExConfig,
+    transport: T,
+    send: bool,
+) -> Vec<SelftestCheck> {
+    let mut checks = Vec::new();
+    let abi = match config.selector_override {
+        Some(selector) => Ok(format!("selector overridden with {selector}")),
+        None => config.state_root_selector().map(|_| "all methods found".to_string()),
+    };
+    checks.push(SelftestCheck::new("State root contract ABI", abi));
+    if !checks[0].passed {
+        return checks
+    }
+
+    let client = match L1Client::with_transport(config.clone(), transport).await {
+        Ok(client) => client,
+        Err(err) => {
+            checks.push(SelftestCheck::new("L1 chain", Err(err)));
+            return checks
+        }
+    };
+    let chain_id = client.contract.chain_id().await.map_err(eyre::Report::from);
+    checks.push(SelftestCheck::new(
+        "L1 chain",
+        chain_id.map(|chain_id| format!("{} is on chain {chain_id}", config.l1_rpc)),
+    ));
+
+    let address = config.state_root_contract;
+    let code = match client.contract.code().await {
+        Ok(code) if code.is_empty() => Err(eyre::eyre!("no contract deployed at {address}")),
+        code => code.map_err(eyre::Report::from),
+    };
+    match code {
+        Ok(code) => {
+            let detail = format!("{} bytes at {address}", code.len());
+            checks.push(SelftestCheck::new("State root contract code", Ok(detail)));
+            let code = if config.state_root_proxy {
+                proxy_implementation(&client.contract).await.map(|(_, code)| code)
+            } else {
+                Ok(code)
+            };
+            let method = code.and_then(|code| {
+                let call = StateRootCall::new(&[(0, B256::ZERO)], config.legacy_state_root)?;
+                let selector = Selector::from_slice(&client.contract.calldata(&call)[..4]);
+                if !code.windows(4).any(|window| window == selector.as_slice()) {
+                    eyre::bail!("selector {selector} not found in the contract code")
+                }
+                Ok(format!("selector {selector} found in the contract code"))
+            });
+            checks.push(SelftestCheck::new("State root method", method));
+        }
+        Err(err) => checks.push(SelftestCheck::new("State root contract code", Err(err))),
+    }
+
+    let account = config.account();
+    let balance = client.check_balance().await.and_then(|status| match status {
+        BalanceStatus::Sufficient => Ok(format!("{account} can pay for submissions")),
+        BalanceStatus::Low => Ok(format!("{account} can pay for submissions, but is low")),
+        BalanceStatus::Insufficient => eyre::bail!("{account} can't pay for a submission"),
+    });
+    checks.push(SelftestCheck::new("L1 account balance", balance));
+
+    if send && checks.iter().all(|check| check.passed) {
+        let submitted = client.submit_state_root(B256::ZERO).await;
+        checks.push(SelftestCheck::new(
+            "Test submission",
+            submitted.map(|tx_hash| format!("confirmed in {tx_hash}")).map_err(eyre::Report::from),
+        ));
+    }
+
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        client::{L1SubmitError, MockStateRootSink, StateRootSink},
+        test_utils::{test_env, MockTransport},
+        StateRootAbi::{changeStateRootAtCall, changeStateRootBatchCall, changeStateRootCall},
+    };
+    use alloy_primitives::{Bytes, U256};
+    use alloy_sol_types::SolCall;
+    use alloy_transport::TransportResult;
+    use async_trait::async_trait;
+    use reth_provider::test_utils::MockEthProvider;
+    use reth_testing_utils::generators::{self, random_block};
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    #[tokio::test]
+    async fn test_reanchor() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let provider = MockEthProvider::default();
+        let block = random_block(&mut rng, 5, Default::default());
+        provider.add_header(block.hash(), block.header.header().clone());
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+
+        // The state root of the stored block is submitted
+        let tx_hash = reanchor(&client, &provider, 5).await?;
+        assert_eq!(tx_hash, B256::repeat_byte(0x11));
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 1);
+        let call = changeStateRootAtCall::abi_decode(txs[0].input(), true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(5), block.header.state_root));
+
+        // Blocks that aren't stored locally can't be re-anchored
+        let err = reanchor(&client, &provider, 6).await.unwrap_err();
+        assert!(err.to_string().contains("block 6 not found"), "{err}");
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_t1_cli() {
+        let cli = T1Cli::parse_from(["t1", "reanchor", "--block", "5"]);
+        let T1Commands::Reanchor(command) = cli.command else { panic!("expected reanchor") };
+        assert_eq!(command.block, 5);
+
+        let cli = T1Cli::parse_from(["t1", "backfill", "--from", "1", "--to", "10"]);
+        let T1Commands::Backfill(command) = cli.command else { panic!("expected backfill") };
+        assert_eq!((command.from, command.to, command.delay_ms), (1, 10, 0));
+
+        let cli = T1Cli::parse_from(["t1", "history", "--from", "3"]);
+        let T1Commands::History(command) = cli.command else { panic!("expected history") };
+        assert_eq!(command.from, 3);
+
+        let cli = T1Cli::parse_from(["t1", "selftest", "--send"]);
+        let T1Commands::Selftest(command) = cli.command else { panic!("expected selftest") };
+        assert!(command.send);
+    }
+
+    #[tokio::test]
+    async fn test_selftest() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+        // Code dispatching the `changeStateRoot` selector
+        let code = [&[0x63][..], &changeStateRootCall::SELECTOR, &[0x14]].concat();
+        let summary = |checks: &[SelftestCheck]| {
+            checks.iter().map(|check| (check.name, check.passed)).collect::<Vec<_>>()
+        };
+
+        // The account has no balance, so nothing is sent
+        let transport = MockTransport { code: code.clone().into(), ..Default::default() };
+        let checks = selftest(&config, transport.clone(), true).await;
+        assert_eq!(
+            summary(&checks),
+            vec![
+                ("State root contract ABI", true),
+                ("L1 chain", true),
+                ("State root contract code", true),
+                ("State root method", true),
+                ("L1 account balance", false),
+            ]
+        );
+        assert_eq!(
+            checks[4].to_string(),
+            format!("[FAIL] L1 account balance: {} can't pay for a submission", config.account())
+        );
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        // Once every check passes, the test submission anchors a zero state root
+        let transport =
+            MockTransport { code: code.into(), balance: u128::MAX, ..Default::default() };
+        let checks = selftest(&config, transport.clone(), true).await;
+        assert!(checks.iter().all(|check| check.passed), "{checks:?}");
+        assert_eq!(checks.last().map(|check| check.name), Some("Test submission"));
+        let txs = transport.sent_transactions();
+        let call = changeStateRootCall::abi_decode(txs[0].input(), true)?;
+        assert_eq!(call.stateRoot_, Bytes::copy_from_slice(B256::ZERO.as_slice()));
+
+        // Without a contract, its method can't be checked either
+        let checks = selftest(&config, MockTransport::default(), false).await;
+        let address = config.state_root_contract;
+        assert_eq!(checks[2].detail, format!("no contract deployed at {address}"));
+        assert!(!checks.iter().any(|check| check.name == "State root method"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backfill() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let provider = MockEthProvider::default();
+        let blocks = (1..=5).map(|number| random_block(&mut rng, number, Default::default()));
+        let state_roots = blocks
+            .map(|block| {
+                provider.add_header(block.hash(), block.header.header().clone());
+                block.header.state_root
+            })
+            .collect::<Vec<_>>();
+        let config = ExExConfig { batch_size: 2, ..ExExConfig::from_env_with(test_env)? };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(L1_BACKFILL_PROGRESS_FILE);
+        let workers = NonZeroUsize::MIN;
+
+        // Blocks 2 to 4 are anchored in batches
+        let mut progress = Watermark::load(path.clone())?;
+        backfill(&client, &provider, 2..=4, &mut progress, Duration::ZERO, workers).await?;
+        assert_eq!(progress.height(), Some(4));
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 2);
+        let call = changeStateRootBatchCall::abi_decode(txs[0].input(), true)?;
+        assert_eq!(call.numbers_, vec![U256::from(2), U256::from(3)]);
+        assert_eq!(call.stateRoots_, state_roots[1..3]);
+        let call = changeStateRootAtCall::abi_decode(txs[1].input(), true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(4), state_roots[3]));
+
+        // A backfill of a range including the progress resumes after it
+        let mut progress = Watermark::load(path)?;
+        backfill(&client, &provider, 1..=5, &mut progress, Duration::ZERO, workers).await?;
+        assert_eq!(progress.height(), Some(5));
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 3);
+        let call = changeStateRootAtCall::abi_decode(txs[2].input(), true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(5), state_roots[4]));
+
+        // Missing blocks fail the backfill before anything is submitted
+        let mut progress = Watermark::load(dir.path().join("other"))?;
+        let err = backfill(&client, &provider, 5..=6, &mut progress, Duration::ZERO, workers)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("block 6 not found"), "{err}");
+        assert_eq!(transport.sent_transactions().len(), 3);
+
+        Ok(())
+    }
+
+    /// [`StateRootSink`] taking a second per state root, recording the most state roots in
+    /// flight at once.
+    #[derive(Debug, Default)]
+    struct SlowSink {
+        inner: MockStateRootSink,
+        in_flight: AtomicU64,
+        max_in_flight: AtomicU64,
+    }
+
+    #[async_trait]
+    impl StateRootSink for SlowSink {
+        async fn submit(&self, number: BlockNumber, root: B256) -> Result<TxHash, L1SubmitError> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.inner.submit(number, root).await
+        }
+
+        async fn anchored_state_root(&self, number: BlockNumber) -> TransportResult<Option<B256>> {
+            self.inner.anchored_state_root(number).await
+        }
+
+        async fn is_included(&self, tx_hash: TxHash) -> TransportResult<bool> {
+            self.inner.is_included(tx_hash).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_backfill_workers() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let provider = MockEthProvider::default();
+        for number in 1..=10 {
+            let block = random_block(&mut rng, number, Default::default());
+            provider.add_header(block.hash(), block.header.header().clone());
+        }
+        let dir = tempfile::tempdir()?;
+        let workers = NonZeroUsize::new(3).unwrap();
+
+        // With per-height state roots, up to 3 batches of 2 state roots are in flight at once
+        let config = ExExConfig {
+            batch_size: 2,
+            state_roots_per_height: true,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let sink = Arc::new(SlowSink::default());
+        let client = L1Client::with_transport(config.clone(), MockTransport::default())
+            .await?
+            .with_sink(sink.clone());
+        let mut progress = Watermark::load(dir.path().join(L1_BACKFILL_PROGRESS_FILE))?;
+        let started_at = Instant::now();
+        backfill(&client, &provider, 1..=10, &mut progress, Duration::ZERO, workers).await?;
+        assert_eq!(started_at.elapsed(), Duration::from_secs(4));
+        assert_eq!(sink.max_in_flight.load(Ordering::SeqCst), 3);
+        let mut numbers = sink.inner.submissions().into_iter().map(|(n, _)| n).collect::<Vec<_>>();
+        numbers.sort_unstable();
+        assert_eq!(numbers, (1..=10).collect::<Vec<_>>());
+        assert_eq!(progress.height(), Some(10));
+
+        // With only the latest state root, the last batch is anchored after all others
+        let config = ExExConfig { state_roots_per_height: false, ..config };
+        let sink = Arc::new(SlowSink::default());
+        let client = L1Client::with_transport(config, MockTransport::default())
+            .await?
+            .with_sink(sink.clone());
+        let mut progress = Watermark::load(dir.path().join("latest"))?;
+        backfill(&client, &provider, 1..=10, &mut progress, Duration::ZERO, workers).await?;
+        assert_eq!(sink.max_in_flight.load(Ordering::SeqCst), 3);
+        let submissions = sink.inner.submissions();
+        assert_eq!(submissions.len(), 10);
+        let last = submissions[8..].iter().map(|(number, _)| *number).collect::<Vec<_>>();
+        assert_eq!(last, vec![9, 10]);
+        assert_eq!(progress.height(), Some(10));
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_backfill_workers_nonces() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let provider = MockEthProvider::default();
+        for number in 1..=10 {
+            let block = random_block(&mut rng, number, Default::default());
+            provider.add_header(block.hash(), block.header.header().clone());
+        }
+        let dir = tempfile::tempdir()?;
+        let workers = NonZeroUsize::new(3).unwrap();
+        let config = ExExConfig {
+            batch_size: 2,
+            state_roots_per_height: true,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        // Every RPC call yields, so the workers sign and send their transactions concurrently
+        let transport = MockTransport::with_latency(Duration::from_millis(10));
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let mut progress = Watermark::load(dir.path().join(L1_BACKFILL_PROGRESS_FILE))?;
+        backfill(&client, &provider, 1..=10, &mut progress, Duration::ZERO, workers).await?;
+        assert_eq!(progress.height(), Some(10));
+
+        // Every state root is sent once, each transaction with its own nonce
+        let mut nonces =
+            transport.sent_transactions().iter().map(|tx| tx.nonce()).collect::<Vec<_>>();
+        nonces.sort_unstable();
+        assert_eq!(nonces, (0..5).collect::<Vec<_>>());
+        let mut numbers = transport
+            .anchored_state_roots()?
+            .into_iter()
+            .flatten()
+            .map(|(number, _)| number)
+            .collect::<Vec<_>>();
+        numbers.sort_unstable();
+        assert_eq!(numbers, (1..=10).collect::<Vec<_>>());
+
+        Ok(())
+    }
+}
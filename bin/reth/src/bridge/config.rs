@@ -0,0 +1,2109 @@
+//! Configuration of the bridge, read from the environment, the [`ExExFileConfig`] and the
+//! [`ExExArgs`].
+
+use super::{
+    signer::{L1Signer, LocalSigner, RemoteSigner},
+    CounterContract, StateRootAbi,
+};
+use alloy_json_abi::JsonAbi;
+use alloy_primitives::{address, Address, BlockNumber, Bytes, Selector, B256};
+use alloy_pubsub::PubSubConnect;
+use alloy_signer_local::PrivateKeySigner;
+use alloy_sol_types::SolCall;
+use alloy_transport::{BoxTransport, TransportResult};
+use alloy_transport_http::Http;
+use alloy_transport_ipc::IpcConnect;
+use alloy_transport_ws::WsConnect;
+use clap::{Args, ValueEnum};
+use reth_chainspec::EthChainSpec;
+use reth_node_builder::NodeConfig;
+use reth_primitives::Header;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use url::Url;
+
+/// Name the L1 bridge ExEx is installed with, unless overridden by [`ExExArgs::name`].
+pub const EXEX_NAME: &str = "t1";
+
+const COUNTER_CONTRACT_ADDRESS: Address = address!("b4B46bdAA835F8E4b4d8e208B6559cD267851051");
+
+const L1_SUBMIT_MAX_RETRIES: usize = 5;
+
+const L1_SUBMIT_RETRY_DELAY_MS: u64 = 500;
+
+const L1_CONFIRMATIONS: u64 = 1;
+
+/// Interval in milliseconds the L1 is polled at while waiting for a transaction to be confirmed,
+/// unless overridden by [`ExExArgs::confirm_poll_interval_ms`].
+pub(super) const L1_CONFIRM_POLL_INTERVAL_MS: u64 = 2_000;
+
+/// Time in milliseconds a single L1 RPC call may take, unless overridden by
+/// [`ExExArgs::l1_rpc_timeout_ms`].
+const L1_RPC_TIMEOUT_MS: u64 = 30_000;
+
+const L1_PRIORITY_FEE_MULTIPLIER: f64 = 1.0;
+
+const L1_FEE_BUMP_TIMEOUT_MS: u64 = 180_000;
+
+const L1_FEE_BUMP_PERCENT: f64 = 20.0;
+
+const L1_MAX_FEE_BUMPS: u32 = 3;
+
+const L1_GAS_MULTIPLIER: f64 = 1.25;
+
+pub(super) const L1_DEFAULT_GAS_LIMIT: u64 = 5_000_000;
+
+const L1_GAS_ESTIMATE_REFRESH_MS: u64 = 600_000;
+
+const L1_GAS_ESTIMATE_DEVIATION_PERCENT: f64 = 20.0;
+
+pub(super) const L1_BATCH_SIZE: usize = 100;
+
+const L1_BATCH_FLUSH_INTERVAL_MS: u64 = 0;
+
+const L1_MAX_QUEUED_ROOTS: usize = 10_000;
+
+const L1_MIN_DEPTH: u64 = 0;
+
+const L1_STATE_ROOTS_PER_HEIGHT: bool = false;
+
+const L1_ACCESS_LIST: bool = false;
+
+const L1_STATE_DIFFS: bool = false;
+
+const L1_STATE_DIFF_BLOBS: bool = false;
+
+const L1_DEDUP_STATE_ROOTS: bool = true;
+
+const L1_LEGACY_STATE_ROOT: bool = false;
+
+const L1_SKIP_MISSING_ROOTS: bool = false;
+
+const L1_REORG_DEPTH: usize = 0;
+
+const L1_RECHECK_INTERVAL_MS: u64 = 12_000;
+
+const L1_MAX_ANCHOR_GAP: u64 = 100;
+
+const L1_REORG_WARN_DEPTH: u64 = 3;
+
+const L1_HEALTH_CHECK_INTERVAL_MS: u64 = 30_000;
+
+const L1_BREAKER_THRESHOLD: u32 = 5;
+
+const L1_BREAKER_COOLDOWN_MS: u64 = 60_000;
+
+const L1_SUBMIT_RATE: f64 = 0.0;
+
+const L1_SUBMIT_BURST: u32 = 1;
+
+const L1_SUBMIT_VALUE_WEI: u128 = 0;
+
+/// Balance of the prefunded L1 account below which the balance check warns, 0.1 ether.
+const L1_MIN_BALANCE_WEI: u128 = 100_000_000_000_000_000;
+
+/// Method of the [`StateRootContract`](super::contract::StateRootContract) anchoring a single state
+/// root.
+const STATE_ROOT_METHOD: &str = "changeStateRoot";
+
+const STATE_ROOT_PROXY: bool = false;
+
+/// ABI of the [`StateRootContract`](super::contract::StateRootContract) the binary is built with.
+pub(super) const STATE_ROOT_ABI: &str = include_str!("../../state_root_abi.json");
+
+/// ABI of the Counter contract the binary is built with.
+const COUNTER_ABI: &str = include_str!("../../counter_abi.json");
+
+/// Name of the file in the [`ExExConfig::contract_abi_dir`] the state root contract ABI is read
+/// from.
+const STATE_ROOT_ABI_FILE: &str = "state_root_abi.json";
+
+/// Name of the file in the [`ExExConfig::contract_abi_dir`] the Counter contract ABI is read from.
+const COUNTER_ABI_FILE: &str = "counter_abi.json";
+
+/// Below the 5 seconds the node waits for graceful tasks on shutdown.
+const L1_SHUTDOWN_TIMEOUT_MS: u64 = 4_000;
+
+const L1_ABORT_ON_NOTIFICATION_TIMEOUT: bool = false;
+
+/// Name of the file in the datadir the [`ExExFileConfig`] is read from, unless set with
+/// [`ExExArgs::config`].
+pub(super) const L1_CONFIG_FILE: &str = "t1.toml";
+
+/// Configuration of the L1 bridge ExEx.
+///
+/// Parsed once when the ExEx is initialized, see [`ExExConfig::from_env`] and
+/// [`ExExFileConfig`].
+///
+/// The [`Debug`](fmt::Debug) representation redacts the [`ExExConfig::prefunded_key`], printing
+/// the address of the signing [`ExExConfig::account`] instead, e.g. to print the configuration
+/// resolved from the environment, the config file and the CLI, see [`ExExArgs::print_config`].
+#[derive(Clone)]
+pub struct ExExConfig {
+    /// L1 RPC endpoint the state roots are submitted to. Required, there's no default endpoint.
+    pub l1_rpc: Url,
+    /// Chain id the L1 RPC endpoint has to report, or `None` to accept any chain.
+    pub l1_chain_id: Option<u64>,
+    /// Address of the L1 contract the state roots are anchored in. Required, there's no default
+    /// contract.
+    pub state_root_contract: Address,
+    /// Further L1 destinations the same state roots are anchored to, e.g. a backup L1 chain.
+    pub l1_backup_destinations: Vec<L1Destination>,
+    /// Name of the contract method anchoring a single state root, taking it as its only `bytes`
+    /// parameter. Only used with [`ExExConfig::legacy_state_root`].
+    pub state_root_method: String,
+    /// Path of the JSON ABI of the contract, or `None` for the ABI the binary is built with.
+    /// Checked for the methods the client calls when the [`L1Client`](super::client::L1Client) is
+    /// created, see [`ExExConfig::state_root_selector`].
+    pub state_root_abi: Option<PathBuf>,
+    /// Directory both contract ABIs are read from, see [`ExExArgs::contract_abi_dir`]. The
+    /// [`ExExConfig::state_root_abi`] takes precedence over the one in the directory.
+    pub contract_abi_dir: Option<PathBuf>,
+    /// Whether the state root contract is an EIP-1967 proxy. Its implementation is then resolved
+    /// when the [`L1Client`](super::client::L1Client) is created and its code checked for the
+    /// methods the client calls,
+    /// see [`check_proxy_implementation`](super::client::check_proxy_implementation), while the
+    /// transactions are still sent to the proxy.
+    pub state_root_proxy: bool,
+    /// Selector every submission calls instead of the method derived from the ABI, e.g. of the
+    /// dispatcher of a proxy contract, see
+    /// [`StateRootContract::with_dispatch`](super::contract::StateRootContract::with_dispatch).
+    /// The ABI isn't checked if set.
+    pub selector_override: Option<Selector>,
+    /// Calldata inserted between the [`ExExConfig::selector_override`] and the ABI encoded
+    /// parameters of every submission. Requires the selector override.
+    pub calldata_prefix: Bytes,
+    /// Addresses of the rollup Counter contracts whose events are anchored.
+    pub counter_contracts: Vec<Address>,
+    /// Key of the prefunded L1 account signing the submissions, unless signed remotely. Read
+    /// from `PREFUNDED_SECRET`, or decrypted from the `L1_KEYSTORE`, see [`decrypt_keystore`].
+    /// There's no default key, either it or the [`ExExConfig::remote_signer`] is required.
+    pub prefunded_key: Option<PrivateKeySigner>,
+    /// Endpoint of the [`RemoteSigner`] signing the submissions instead of the prefunded key, if
+    /// any.
+    pub remote_signer: Option<Url>,
+    /// Address of the account the [`ExExConfig::remote_signer`] signs for.
+    pub remote_signer_address: Option<Address>,
+    /// Maximum number of retries of a failed L1 submission.
+    pub max_retries: usize,
+    /// Delay before the first retry of a failed L1 submission, growing exponentially with every
+    /// further retry.
+    pub retry_base_delay: Duration,
+    /// Number of L1 blocks, including the one it's included in, a submission has to be confirmed
+    /// by before its height is considered anchored.
+    pub confirmations: u64,
+    /// Interval the L1 is polled at while waiting for a submission to be confirmed, see
+    /// [`ExExArgs::confirm_poll_interval_ms`].
+    pub confirm_poll_interval: Duration,
+    /// Maximum time to wait for a submission to be confirmed, including its replacements, see
+    /// [`ExExArgs::confirm_timeout_ms`]. Zero waits indefinitely.
+    pub confirm_timeout: Duration,
+    /// Maximum number of L1 blocks to wait for a submission to be confirmed, including its
+    /// replacements, instead of [`Self::confirm_timeout`], see
+    /// [`ExExArgs::submit_timeout_blocks`].
+    pub submit_timeout_blocks: Option<u64>,
+    /// Maximum time a single L1 RPC call may take, see [`ExExArgs::l1_rpc_timeout_ms`]. Zero
+    /// waits indefinitely.
+    pub l1_rpc_timeout: Duration,
+    /// Multiplier applied to the priority fee estimated from the L1 fee history.
+    pub priority_fee_multiplier: f64,
+    /// Upper bound of the max fee per gas of a submission in wei, if any.
+    pub max_fee_per_gas_cap: Option<u128>,
+    /// Time a submission may stay unmined before it's replaced with bumped fees.
+    pub fee_bump_timeout: Duration,
+    /// Fee increase of a replacement transaction in percent, at least
+    /// [`MIN_FEE_BUMP_PERCENT`](super::contract::MIN_FEE_BUMP_PERCENT).
+    pub fee_bump_percent: f64,
+    /// Maximum number of times a submission is replaced with bumped fees. Zero disables fee
+    /// bumps.
+    pub max_fee_bumps: u32,
+    /// Safety multiplier applied to the estimated gas of a submission.
+    pub gas_multiplier: f64,
+    /// Gas limit of a submission if the gas can't be estimated.
+    pub default_gas_limit: u64,
+    /// Gas limit of a submission overriding the estimation, if any.
+    pub gas_limit: Option<u64>,
+    /// Time the estimated gas of a submission anchoring a single state root is cached for, see
+    /// [`GasEstimateCache`](super::client::GasEstimateCache). Zero estimates the gas of every
+    /// submission.
+    pub gas_estimate_refresh: Duration,
+    /// Deviation in percent of the gas used by a confirmed submission from the cached estimate
+    /// above which the estimate is refreshed before the next submission.
+    pub gas_estimate_deviation_percent: f64,
+    /// Maximum number of state roots anchored in a single L1 transaction.
+    pub batch_size: usize,
+    /// Maximum size of the calldata of a single L1 transaction anchoring state roots, if any,
+    /// further bounding the [`ExExConfig::batch_size`], see
+    /// [`L1Client::max_batch_len`](super::client::L1Client::max_batch_len).
+    pub max_batch_calldata_bytes: Option<usize>,
+    /// Maximum time a state root is queued for before it's anchored, even if the batch isn't
+    /// full. Zero anchors the queued state roots after every committed chain.
+    pub batch_flush_interval: Duration,
+    /// Number of queued state roots at which the ExEx stops consuming notifications until some
+    /// of them are anchored, see
+    /// [`RootBatch::is_saturated`](super::batch::RootBatch::is_saturated).
+    pub max_queued_roots: usize,
+    /// Number of blocks a committed block has to be below the committed tip for its state root to
+    /// be anchored. Shallower state roots stay queued until later commits bury them deep enough,
+    /// see [`RootBatch::with_min_depth`](super::batch::RootBatch::with_min_depth). Zero anchors
+    /// the state roots of every committed block.
+    pub min_depth: u64,
+    /// Maximum time the queued state roots are anchored for on shutdown.
+    pub shutdown_timeout: Duration,
+    /// Maximum time a single notification is handled for, if any, bounding a stalled decoding
+    /// or submission.
+    pub notification_timeout: Option<Duration>,
+    /// Whether the ExEx stops once handling a notification times out, instead of skipping the
+    /// notification without finishing its height.
+    pub abort_on_notification_timeout: bool,
+    /// Minimum time between two L1 submissions, see [`ExExArgs::submit_interval_ms`].
+    pub submit_interval: Duration,
+    /// Maximum random delay before every L1 submission, see [`ExExArgs::submit_jitter_ms`].
+    pub submit_jitter: Duration,
+    /// Number of the first block whose state root is anchored, see [`ExExArgs::start_height`].
+    pub start_height: BlockNumber,
+    /// Interval of the block numbers whose state roots are anchored, see
+    /// [`ExExArgs::anchor_every`].
+    pub anchor_every: u64,
+    /// Whether the [`StateRootContract`](super::contract::StateRootContract) holds the state root
+    /// of every anchored block number, instead of only the latest one.
+    pub state_roots_per_height: bool,
+    /// Whether to attach an EIP-2930 access list, created by the L1 with
+    /// `eth_createAccessList`, to the submissions.
+    pub access_list: bool,
+    /// Whether to anchor the state root of the tip of every committed chain along with the
+    /// [`StateDiff`](super::contract::StateDiff) of the chain, see
+    /// [`StateRootCall::WithDiff`](super::contract::StateRootCall::WithDiff). Off by default, as
+    /// diffs can be large.
+    pub state_diffs: bool,
+    /// Whether to submit the [`StateDiff`](super::contract::StateDiff)s of [`Self::state_diffs`]
+    /// in EIP-4844 blobs instead of calldata, see
+    /// [`StateRootCall::BlobDiff`](super::contract::StateRootCall::BlobDiff).
+    pub state_diff_blobs: bool,
+    /// Whether to skip submitting a state root identical to the last submitted one. Has to be
+    /// disabled if the contract requires an entry for every block number.
+    pub dedup_state_roots: bool,
+    /// Whether to anchor a single state root with the legacy `changeStateRoot(bytes)` setter,
+    /// see [`ExExConfig::state_root_method`], which doesn't convey its block number, instead of
+    /// `changeStateRootAt(uint256,bytes32)`. For contracts that predate `changeStateRootAt`.
+    pub legacy_state_root: bool,
+    /// Root of the block header anchored on L1 instead of the state root, see [`AnchoredRoot`].
+    pub anchored_root: AnchoredRoot,
+    /// Whether committed blocks without the [`ExExConfig::anchored_root`], e.g. pre-Shanghai
+    /// blocks without a withdrawals root, are skipped instead of failing the anchoring.
+    pub skip_missing_roots: bool,
+    /// Number of the latest confirmed submissions re-checked for being reorged out of the L1
+    /// before a committed chain is anchored, see
+    /// [`L1Client::recheck_anchored`](super::client::L1Client::recheck_anchored). Zero disables
+    /// the check.
+    pub l1_reorg_depth: usize,
+    /// Minimum time between two re-checks of the latest confirmed submissions, see
+    /// [`ExExConfig::l1_reorg_depth`]. Zero re-checks them before every committed chain.
+    pub l1_recheck_interval: Duration,
+    /// Number of blocks the L1 anchor may fall behind the local tip before the health check
+    /// warns about it.
+    pub max_anchor_gap: u64,
+    /// Number of blocks a reorg of the local chain may roll back before it's warned about, see
+    /// [`reorg_depth`](super::exex::reorg_depth).
+    pub reorg_warn_depth: u64,
+    /// Interval the health check compares the L1 anchor to the local tip at.
+    pub health_check_interval: Duration,
+    /// Balance of the L1 account in wei below which the balance check warns, see
+    /// [`L1Client::check_balance`](super::client::L1Client::check_balance). Zero only checks that
+    /// the next submission can be paid for.
+    pub min_balance: u128,
+    /// Number of consecutive failed submissions that open the
+    /// [`CircuitBreaker`](super::client::CircuitBreaker). Zero disables it, making a failed
+    /// submission fatal.
+    pub breaker_threshold: u32,
+    /// Time the open [`CircuitBreaker`](super::client::CircuitBreaker) pauses submissions for.
+    pub breaker_cooldown: Duration,
+    /// Maximum sustained number of submissions per second, see
+    /// [`RateLimiter`](super::client::RateLimiter). Zero disables the limit.
+    pub submit_rate: f64,
+    /// Number of submissions that may be sent at once before the [`ExExConfig::submit_rate`]
+    /// applies.
+    pub submit_burst: u32,
+    /// Value in wei attached to every submission, for state root contracts charging a fee paid as
+    /// `msg.value`.
+    pub submit_value: u128,
+    /// Whether to only log the L1 transactions anchoring state roots instead of sending them,
+    /// see [`ExExArgs::dry_run`].
+    pub dry_run: bool,
+    /// Whether to log every decoded Counter event, see [`ExExArgs::log_events`].
+    pub log_events: bool,
+    /// Whether to anchor the local state root again if it disagrees with the one anchored on L1
+    /// at startup, see [`reconcile_l1`](super::exex::reconcile_l1).
+    pub reanchor_on_mismatch: bool,
+    /// Chain id of the local node, which the L1 has to differ from, if known.
+    pub local_chain_id: Option<u64>,
+    /// Whether the L1 may be the local chain itself, see [`ExExArgs::allow_same_chain`].
+    pub allow_same_chain: bool,
+    /// Whether to only anchor the state roots of finalized blocks, see
+    /// [`ExExArgs::anchor_finalized`].
+    pub anchor_finalized: bool,
+    /// Whether to only anchor the new tip of reorgs and reverts, see [`ExExArgs::reorg_only`].
+    pub reorg_only: bool,
+    /// Whether to recompute the state root of the tip of every committed chain from the node's
+    /// state before anchoring, see [`ExExArgs::verify_root_locally`].
+    pub verify_root_locally: bool,
+    /// Name the ExEx is installed with, see [`ExExArgs::name`].
+    pub name: String,
+}
+
+impl ExExConfig {
+    /// Reads the configuration from the process environment.
+    ///
+    /// Every variable but the L1 endpoint, the state root contract and the signing key is
+    /// optional and falls back to the built-in default, and a variable that is set and can't be
+    /// parsed is an error:
+    /// - `L1_RPC_ADDRESS`, required
+    /// - `L1_CHAIN_ID`
+    /// - `STATE_ROOT_CONTRACT_ADDRESS`, required
+    /// - `L1_BACKUP_DESTINATIONS`, a semicolon separated list of [`L1Destination`]s
+    /// - `STATE_ROOT_METHOD`
+    /// - `STATE_ROOT_ABI`
+    /// - `STATE_ROOT_PROXY`
+    /// - `STATE_ROOT_SELECTOR`, 4 hex encoded bytes
+    /// - `STATE_ROOT_CALLDATA_PREFIX`, hex encoded bytes
+    /// - `COUNTER_CONTRACT_ADDRESS`, a comma separated list of addresses
+    /// - `PREFUNDED_SECRET`
+    /// - `L1_KEYSTORE`, an encrypted JSON keystore replacing `PREFUNDED_SECRET`
+    /// - `L1_KEYSTORE_PASSWORD_FILE`, prompted for on the terminal if unset
+    /// - `L1_REMOTE_SIGNER_URL`
+    /// - `L1_REMOTE_SIGNER_ADDRESS`
+    /// - `L1_SUBMIT_MAX_RETRIES`
+    /// - `L1_SUBMIT_RETRY_DELAY_MS`
+    /// - `L1_CONFIRMATIONS`
+    /// - `L1_PRIORITY_FEE_MULTIPLIER`
+    /// - `L1_MAX_FEE_PER_GAS_CAP`
+    /// - `L1_FEE_BUMP_TIMEOUT_MS`
+    /// - `L1_FEE_BUMP_PERCENT`
+    /// - `L1_MAX_FEE_BUMPS`
+    /// - `L1_GAS_MULTIPLIER`
+    /// - `L1_DEFAULT_GAS_LIMIT`
+    /// - `L1_GAS_LIMIT`
+    /// - `L1_GAS_ESTIMATE_REFRESH_MS`
+    /// - `L1_GAS_ESTIMATE_DEVIATION_PERCENT`
+    /// - `L1_BATCH_SIZE`
+    /// - `L1_MAX_BATCH_CALLDATA_BYTES`
+    /// - `L1_BATCH_FLUSH_INTERVAL_MS`
+    /// - `L1_MAX_QUEUED_ROOTS`
+    /// - `L1_MIN_DEPTH`
+    /// - `L1_SHUTDOWN_TIMEOUT_MS`
+    /// - `L1_NOTIFICATION_TIMEOUT_MS`
+    /// - `L1_ABORT_ON_NOTIFICATION_TIMEOUT`
+    /// - `L1_STATE_ROOTS_PER_HEIGHT`
+    /// - `L1_ACCESS_LIST`
+    /// - `L1_STATE_DIFFS`
+    /// - `L1_STATE_DIFF_BLOBS`
+    /// - `L1_DEDUP_STATE_ROOTS`
+    /// - `L1_LEGACY_STATE_ROOT`
+    /// - `L1_ANCHORED_ROOT`, one of `state`, `receipts` or `withdrawals`
+    /// - `L1_SKIP_MISSING_ROOTS`
+    /// - `L1_REORG_DEPTH`
+    /// - `L1_RECHECK_INTERVAL_MS`
+    /// - `L1_MAX_ANCHOR_GAP`
+    /// - `L1_REORG_WARN_DEPTH`
+    /// - `L1_HEALTH_CHECK_INTERVAL_MS`
+    /// - `L1_MIN_BALANCE_WEI`
+    /// - `L1_BREAKER_THRESHOLD`
+    /// - `L1_BREAKER_COOLDOWN_MS`
+    /// - `L1_SUBMIT_RATE`
+    /// - `L1_SUBMIT_BURST`
+    /// - `L1_SUBMIT_VALUE_WEI`
+    ///
+    /// The signing key has no default: one of `PREFUNDED_SECRET`, `L1_KEYSTORE` or
+    /// `L1_REMOTE_SIGNER_URL` is required.
+    pub fn from_env() -> eyre::Result<Self> {
+        Self::from_env_and_file(ExExFileConfig::default())
+    }
+
+    /// Reads the configuration from the process environment, with the settings of the given
+    /// [`ExExFileConfig`] overriding it, see [`Self::from_env`] and [`Self::with_file`].
+    ///
+    /// The required L1 endpoint and state root contract may be set in either.
+    pub fn from_env_and_file(file: ExExFileConfig) -> eyre::Result<Self> {
+        Self::from_sources(|key| std::env::var(key).ok(), file)
+    }
+
+    /// Reads the configuration using the given environment lookup.
+    pub(super) fn from_env_with(env: impl Fn(&str) -> Option<String>) -> eyre::Result<Self> {
+        Self::from_sources(env, ExExFileConfig::default())
+    }
+
+    /// Reads the configuration using the given environment lookup, overridden by the given file.
+    fn from_sources(
+        env: impl Fn(&str) -> Option<String>,
+        file: ExExFileConfig,
+    ) -> eyre::Result<Self> {
+        fn parse_optional<T: FromStr>(
+            env: &impl Fn(&str) -> Option<String>,
+            key: &str,
+        ) -> eyre::Result<Option<T>>
+        where
+            T::Err: std::error::Error + Send + Sync + 'static,
+        {
+            env(key)
+                .map(|value| value.parse::<T>())
+                .transpose()
+                .map_err(|err| eyre::eyre!("invalid {key}: {err}"))
+        }
+
+        fn parse<T: FromStr>(
+            env: &impl Fn(&str) -> Option<String>,
+            key: &str,
+            default: T,
+        ) -> eyre::Result<T>
+        where
+            T::Err: std::error::Error + Send + Sync + 'static,
+        {
+            parse_optional(env, key).map(|value| value.unwrap_or(default))
+        }
+
+        fn parse_list<T: FromStr>(
+            env: &impl Fn(&str) -> Option<String>,
+            key: &str,
+            default: Vec<T>,
+        ) -> eyre::Result<Vec<T>>
+        where
+            T::Err: std::error::Error + Send + Sync + 'static,
+        {
+            let Some(value) = env(key) else { return Ok(default) };
+            value
+                .split(',')
+                .map(|item| item.trim().parse::<T>())
+                .collect::<Result<_, _>>()
+                .map_err(|err| eyre::eyre!("invalid {key}: {err}"))
+        }
+
+        let selector_override = parse_optional(&env, "STATE_ROOT_SELECTOR")?;
+        let calldata_prefix = parse(&env, "STATE_ROOT_CALLDATA_PREFIX", Bytes::new())?;
+        if selector_override.is_none() && !calldata_prefix.is_empty() {
+            eyre::bail!("STATE_ROOT_CALLDATA_PREFIX requires STATE_ROOT_SELECTOR")
+        }
+
+        let state_diffs = parse(&env, "L1_STATE_DIFFS", L1_STATE_DIFFS)?;
+        let state_diff_blobs = parse(&env, "L1_STATE_DIFF_BLOBS", L1_STATE_DIFF_BLOBS)?;
+        if state_diff_blobs && !state_diffs {
+            eyre::bail!("L1_STATE_DIFF_BLOBS requires L1_STATE_DIFFS")
+        }
+
+        let keystore: Option<PathBuf> = parse_optional(&env, "L1_KEYSTORE")?;
+        let password_file: Option<PathBuf> = parse_optional(&env, "L1_KEYSTORE_PASSWORD_FILE")?;
+        let prefunded_key = match keystore {
+            Some(_) if env("PREFUNDED_SECRET").is_some() => {
+                eyre::bail!("L1_KEYSTORE and PREFUNDED_SECRET are mutually exclusive")
+            }
+            Some(keystore) => Some(
+                decrypt_keystore(&keystore, password_file.as_deref())
+                    .map_err(|err| eyre::eyre!("invalid L1_KEYSTORE: {err}"))?,
+            ),
+            None if password_file.is_some() => {
+                eyre::bail!("L1_KEYSTORE_PASSWORD_FILE requires L1_KEYSTORE")
+            }
+            None => parse_optional(&env, "PREFUNDED_SECRET")?,
+        };
+
+        // The endpoint and the contract have no defaults, so a misconfigured node fails instead of
+        // anchoring somewhere else
+        let Some(l1_rpc) = parse_optional(&env, "L1_RPC_ADDRESS")?.or_else(|| file.l1_rpc.clone())
+        else {
+            eyre::bail!("no L1 endpoint configured, set L1_RPC_ADDRESS")
+        };
+        let Some(state_root_contract) =
+            parse_optional(&env, "STATE_ROOT_CONTRACT_ADDRESS")?.or(file.state_root_contract)
+        else {
+            eyre::bail!("no state root contract configured, set STATE_ROOT_CONTRACT_ADDRESS")
+        };
+
+        let config = Self {
+            l1_rpc,
+            l1_chain_id: parse_optional(&env, "L1_CHAIN_ID")?,
+            state_root_contract,
+            l1_backup_destinations: env("L1_BACKUP_DESTINATIONS")
+                .map(|value| {
+                    value
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|destination| !destination.is_empty())
+                        .map(L1Destination::from_str)
+                        .collect::<eyre::Result<Vec<_>>>()
+                })
+                .transpose()
+                .map_err(|err| eyre::eyre!("invalid L1_BACKUP_DESTINATIONS: {err}"))?
+                .unwrap_or_default(),
+            state_root_method: parse(&env, "STATE_ROOT_METHOD", STATE_ROOT_METHOD.to_string())?,
+            state_root_abi: parse_optional(&env, "STATE_ROOT_ABI")?,
+            contract_abi_dir: None,
+            state_root_proxy: parse(&env, "STATE_ROOT_PROXY", STATE_ROOT_PROXY)?,
+            selector_override,
+            calldata_prefix,
+            counter_contracts: parse_list(
+                &env,
+                "COUNTER_CONTRACT_ADDRESS",
+                vec![COUNTER_CONTRACT_ADDRESS],
+            )?,
+            prefunded_key,
+            remote_signer: parse_optional(&env, "L1_REMOTE_SIGNER_URL")?,
+            remote_signer_address: parse_optional(&env, "L1_REMOTE_SIGNER_ADDRESS")?,
+            max_retries: parse(&env, "L1_SUBMIT_MAX_RETRIES", L1_SUBMIT_MAX_RETRIES)?,
+            retry_base_delay: Duration::from_millis(parse(
+                &env,
+                "L1_SUBMIT_RETRY_DELAY_MS",
+                L1_SUBMIT_RETRY_DELAY_MS,
+            )?),
+            confirmations: parse(&env, "L1_CONFIRMATIONS", L1_CONFIRMATIONS)?,
+            confirm_poll_interval: Duration::from_millis(L1_CONFIRM_POLL_INTERVAL_MS),
+            confirm_timeout: Duration::ZERO,
+            submit_timeout_blocks: None,
+            l1_rpc_timeout: Duration::from_millis(L1_RPC_TIMEOUT_MS),
+            priority_fee_multiplier: parse(
+                &env,
+                "L1_PRIORITY_FEE_MULTIPLIER",
+                L1_PRIORITY_FEE_MULTIPLIER,
+            )?,
+            max_fee_per_gas_cap: parse_optional(&env, "L1_MAX_FEE_PER_GAS_CAP")?,
+            fee_bump_timeout: Duration::from_millis(parse(
+                &env,
+                "L1_FEE_BUMP_TIMEOUT_MS",
+                L1_FEE_BUMP_TIMEOUT_MS,
+            )?),
+            fee_bump_percent: parse(&env, "L1_FEE_BUMP_PERCENT", L1_FEE_BUMP_PERCENT)?,
+            max_fee_bumps: parse(&env, "L1_MAX_FEE_BUMPS", L1_MAX_FEE_BUMPS)?,
+            gas_multiplier: parse(&env, "L1_GAS_MULTIPLIER", L1_GAS_MULTIPLIER)?,
+            default_gas_limit: parse(&env, "L1_DEFAULT_GAS_LIMIT", L1_DEFAULT_GAS_LIMIT)?,
+            gas_limit: parse_optional(&env, "L1_GAS_LIMIT")?,
+            gas_estimate_refresh: Duration::from_millis(parse(
+                &env,
+                "L1_GAS_ESTIMATE_REFRESH_MS",
+                L1_GAS_ESTIMATE_REFRESH_MS,
+            )?),
+            gas_estimate_deviation_percent: parse(
+                &env,
+                "L1_GAS_ESTIMATE_DEVIATION_PERCENT",
+                L1_GAS_ESTIMATE_DEVIATION_PERCENT,
+            )?,
+            batch_size: parse(&env, "L1_BATCH_SIZE", L1_BATCH_SIZE)?,
+            max_batch_calldata_bytes: parse_optional(&env, "L1_MAX_BATCH_CALLDATA_BYTES")?,
+            batch_flush_interval: Duration::from_millis(parse(
+                &env,
+                "L1_BATCH_FLUSH_INTERVAL_MS",
+                L1_BATCH_FLUSH_INTERVAL_MS,
+            )?),
+            max_queued_roots: parse(&env, "L1_MAX_QUEUED_ROOTS", L1_MAX_QUEUED_ROOTS)?,
+            min_depth: parse(&env, "L1_MIN_DEPTH", L1_MIN_DEPTH)?,
+            shutdown_timeout: Duration::from_millis(parse(
+                &env,
+                "L1_SHUTDOWN_TIMEOUT_MS",
+                L1_SHUTDOWN_TIMEOUT_MS,
+            )?),
+            notification_timeout: parse_optional(&env, "L1_NOTIFICATION_TIMEOUT_MS")?
+                .map(Duration::from_millis),
+            abort_on_notification_timeout: parse(
+                &env,
+                "L1_ABORT_ON_NOTIFICATION_TIMEOUT",
+                L1_ABORT_ON_NOTIFICATION_TIMEOUT,
+            )?,
+            state_roots_per_height: parse(
+                &env,
+                "L1_STATE_ROOTS_PER_HEIGHT",
+                L1_STATE_ROOTS_PER_HEIGHT,
+            )?,
+            access_list: parse(&env, "L1_ACCESS_LIST", L1_ACCESS_LIST)?,
+            state_diffs,
+            state_diff_blobs,
+            dedup_state_roots: parse(&env, "L1_DEDUP_STATE_ROOTS", L1_DEDUP_STATE_ROOTS)?,
+            legacy_state_root: parse(&env, "L1_LEGACY_STATE_ROOT", L1_LEGACY_STATE_ROOT)?,
+            anchored_root: env("L1_ANCHORED_ROOT")
+                .map(|root| root.parse())
+                .transpose()
+                .map_err(|err| eyre::eyre!("invalid L1_ANCHORED_ROOT: {err}"))?
+                .unwrap_or_default(),
+            skip_missing_roots: parse(&env, "L1_SKIP_MISSING_ROOTS", L1_SKIP_MISSING_ROOTS)?,
+            l1_reorg_depth: parse(&env, "L1_REORG_DEPTH", L1_REORG_DEPTH)?,
+            l1_recheck_interval: Duration::from_millis(parse(
+                &env,
+                "L1_RECHECK_INTERVAL_MS",
+                L1_RECHECK_INTERVAL_MS,
+            )?),
+            max_anchor_gap: parse(&env, "L1_MAX_ANCHOR_GAP", L1_MAX_ANCHOR_GAP)?,
+            reorg_warn_depth: parse(&env, "L1_REORG_WARN_DEPTH", L1_REORG_WARN_DEPTH)?,
+            health_check_interval: Duration::from_millis(parse(
+                &env,
+                "L1_HEALTH_CHECK_INTERVAL_MS",
+                L1_HEALTH_CHECK_INTERVAL_MS,
+            )?),
+            min_balance: parse(&env, "L1_MIN_BALANCE_WEI", L1_MIN_BALANCE_WEI)?,
+            breaker_threshold: parse(&env, "L1_BREAKER_THRESHOLD", L1_BREAKER_THRESHOLD)?,
+            breaker_cooldown: Duration::from_millis(parse(
+                &env,
+                "L1_BREAKER_COOLDOWN_MS",
+                L1_BREAKER_COOLDOWN_MS,
+            )?),
+            submit_rate: parse(&env, "L1_SUBMIT_RATE", L1_SUBMIT_RATE)?,
+            submit_burst: parse(&env, "L1_SUBMIT_BURST", L1_SUBMIT_BURST)?,
+            submit_value: parse(&env, "L1_SUBMIT_VALUE_WEI", L1_SUBMIT_VALUE_WEI)?,
+            submit_interval: Duration::ZERO,
+            submit_jitter: Duration::ZERO,
+            start_height: 0,
+            anchor_every: 1,
+            dry_run: false,
+            log_events: false,
+            reanchor_on_mismatch: false,
+            local_chain_id: None,
+            allow_same_chain: false,
+            anchor_finalized: false,
+            reorg_only: false,
+            verify_root_locally: false,
+            name: EXEX_NAME.to_string(),
+        };
+        if config.prefunded_key.is_none() && config.remote_signer.is_none() {
+            eyre::bail!(
+                "no L1 signing key configured, set PREFUNDED_SECRET, L1_KEYSTORE or \
+                 L1_REMOTE_SIGNER_URL"
+            )
+        }
+        Ok(config.with_file(file))
+    }
+
+    /// Returns the configuration anchoring to the given [`L1Destination`] instead of the primary
+    /// one, without any further backup destinations.
+    pub fn for_destination(&self, destination: &L1Destination) -> Self {
+        Self {
+            l1_rpc: destination.l1_rpc.clone(),
+            l1_chain_id: destination.l1_chain_id,
+            state_root_contract: destination.state_root_contract,
+            l1_backup_destinations: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the address of the L1 account signing the submissions, or the zero address if
+    /// neither the remote signer's address nor the prefunded key is configured.
+    pub fn account(&self) -> Address {
+        match (self.remote_signer_address, &self.prefunded_key) {
+            (Some(address), _) if self.remote_signer.is_some() => address,
+            (_, Some(key)) => key.address(),
+            (_, None) => Address::ZERO,
+        }
+    }
+
+    /// Returns the [`L1Signer`] of the submissions, a [`RemoteSigner`] if configured and a
+    /// [`LocalSigner`] of the prefunded key otherwise.
+    ///
+    /// Fails without either, see [`ExExConfig::prefunded_key`].
+    pub fn signer(&self) -> eyre::Result<Arc<dyn L1Signer>> {
+        let Some(url) = &self.remote_signer else {
+            let key = self.prefunded_key.clone().ok_or_else(|| {
+                eyre::eyre!("PREFUNDED_SECRET or L1_KEYSTORE is required without a remote signer")
+            })?;
+            return Ok(Arc::new(LocalSigner::new(key)))
+        };
+        let address = self.remote_signer_address.ok_or_else(|| {
+            eyre::eyre!("L1_REMOTE_SIGNER_ADDRESS is required with L1_REMOTE_SIGNER_URL")
+        })?;
+        Ok(Arc::new(RemoteSigner::new(Http::new(url.clone()), address)))
+    }
+
+    /// Loads the [`ExExConfig::state_root_abi`], or the one in the
+    /// [`ExExConfig::contract_abi_dir`], returning it with a description of where it was loaded
+    /// from.
+    ///
+    /// Fails if the file can't be read or isn't a valid JSON ABI.
+    pub fn load_state_root_abi(&self) -> eyre::Result<(JsonAbi, String)> {
+        let path = self
+            .state_root_abi
+            .clone()
+            .or_else(|| self.contract_abi_dir.as_ref().map(|dir| dir.join(STATE_ROOT_ABI_FILE)));
+        load_abi(path.as_deref(), STATE_ROOT_ABI, "state root contract")
+    }
+
+    /// Loads the state root contract ABI, see [`ExExConfig::load_state_root_abi`], and returns
+    /// the selector of the [`ExExConfig::state_root_method`] in it, see
+    /// [`ExExConfig::state_root_selector_in`].
+    pub fn state_root_selector(&self) -> eyre::Result<Selector> {
+        let (abi, source) = self.load_state_root_abi()?;
+        self.state_root_selector_in(&abi, &source)
+    }
+
+    /// Returns the selector of the [`ExExConfig::state_root_method`] in the given ABI, loaded
+    /// from `source`.
+    ///
+    /// Fails if the ABI lacks a method the client calls: the state root method taking a single
+    /// `bytes` parameter with [`ExExConfig::legacy_state_root`], `changeStateRootAt` otherwise,
+    /// `changeStateRootBatch` if more than one state root is submitted at once, and
+    /// `changeStateRootWithDiff` with [`ExExConfig::state_diffs`], or `changeStateRootAt` if the
+    /// diffs are submitted in blobs.
+    pub fn state_root_selector_in(&self, abi: &JsonAbi, source: &str) -> eyre::Result<Selector> {
+        for (signature, _) in self.required_state_root_methods() {
+            if !abi.functions().any(|function| function.signature() == signature) {
+                eyre::bail!("state root method {signature} not found in {source}")
+            }
+        }
+        if !self.legacy_state_root {
+            return Ok(Selector::new(StateRootAbi::changeStateRootCall::SELECTOR))
+        }
+
+        let method = &self.state_root_method;
+        abi.function(method)
+            .into_iter()
+            .flatten()
+            .find(|function| matches!(function.inputs.as_slice(), [param] if param.ty == "bytes"))
+            .map(|function| function.selector())
+            .ok_or_else(|| eyre::eyre!("state root method {method}(bytes) not found in {source}"))
+    }
+
+    /// Returns the signatures and selectors of the methods of the state root contract the client
+    /// calls, besides the legacy state root method, see [`ExExConfig::state_root_selector`].
+    pub(super) fn required_state_root_methods(&self) -> Vec<(&'static str, Selector)> {
+        let method = |signature, selector| (signature, Selector::new(selector));
+        let mut required = Vec::new();
+        if !self.legacy_state_root {
+            required.push(method(
+                StateRootAbi::changeStateRootAtCall::SIGNATURE,
+                StateRootAbi::changeStateRootAtCall::SELECTOR,
+            ));
+        }
+        if self.batch_size > 1 {
+            required.push(method(
+                StateRootAbi::changeStateRootBatchCall::SIGNATURE,
+                StateRootAbi::changeStateRootBatchCall::SELECTOR,
+            ));
+        }
+        if self.state_diff_blobs {
+            required.push(method(
+                StateRootAbi::changeStateRootAtCall::SIGNATURE,
+                StateRootAbi::changeStateRootAtCall::SELECTOR,
+            ));
+        } else if self.state_diffs {
+            required.push(method(
+                StateRootAbi::changeStateRootWithDiffCall::SIGNATURE,
+                StateRootAbi::changeStateRootWithDiffCall::SELECTOR,
+            ));
+        }
+        required
+    }
+
+    /// Loads the Counter contract ABI in the [`ExExConfig::contract_abi_dir`], if any, and checks
+    /// that it has the [`CounterContractEvents`](super::CounterContract::CounterContractEvents) the
+    /// ExEx decodes.
+    ///
+    /// The events are always decoded with the ABI the binary is built with, so an ABI whose
+    /// events differ would silently stop the state roots from being anchored.
+    pub fn check_counter_abi(&self) -> eyre::Result<()> {
+        let path = self.contract_abi_dir.as_ref().map(|dir| dir.join(COUNTER_ABI_FILE));
+        let (abi, source) = load_abi(path.as_deref(), COUNTER_ABI, "Counter contract")?;
+        let signature = CounterContract::Incremented::SIGNATURE;
+        if !abi.events().any(|event| event.signature() == signature) {
+            eyre::bail!("Counter event {signature} not found in {source}")
+        }
+        Ok(())
+    }
+
+    /// Returns the configuration with the settings of the given [`ExExFileConfig`] overriding
+    /// the ones read from the environment.
+    pub fn with_file(self, file: ExExFileConfig) -> Self {
+        let millis = |value: Option<u64>, default| value.map_or(default, Duration::from_millis);
+        Self {
+            l1_rpc: file.l1_rpc.unwrap_or(self.l1_rpc),
+            l1_chain_id: file.l1_chain_id.or(self.l1_chain_id),
+            state_root_contract: file.state_root_contract.unwrap_or(self.state_root_contract),
+            counter_contracts: file.counter_contracts.unwrap_or(self.counter_contracts),
+            max_retries: file.max_retries.unwrap_or(self.max_retries),
+            retry_base_delay: millis(file.retry_delay_ms, self.retry_base_delay),
+            confirmations: file.confirmations.unwrap_or(self.confirmations),
+            priority_fee_multiplier: file
+                .priority_fee_multiplier
+                .unwrap_or(self.priority_fee_multiplier),
+            max_fee_per_gas_cap: file
+                .max_fee_per_gas_cap
+                .map(u128::from)
+                .or(self.max_fee_per_gas_cap),
+            fee_bump_timeout: millis(file.fee_bump_timeout_ms, self.fee_bump_timeout),
+            fee_bump_percent: file.fee_bump_percent.unwrap_or(self.fee_bump_percent),
+            max_fee_bumps: file.max_fee_bumps.unwrap_or(self.max_fee_bumps),
+            gas_multiplier: file.gas_multiplier.unwrap_or(self.gas_multiplier),
+            default_gas_limit: file.default_gas_limit.unwrap_or(self.default_gas_limit),
+            gas_limit: file.gas_limit.or(self.gas_limit),
+            batch_size: file.batch_size.unwrap_or(self.batch_size),
+            max_batch_calldata_bytes: file
+                .max_batch_calldata_bytes
+                .or(self.max_batch_calldata_bytes),
+            batch_flush_interval: millis(file.batch_flush_interval_ms, self.batch_flush_interval),
+            max_queued_roots: file.max_queued_roots.unwrap_or(self.max_queued_roots),
+            ..self
+        }
+    }
+}
+
+impl fmt::Debug for ExExConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Destructured, so a new field can't be printed unredacted by accident
+        let Self {
+            l1_rpc,
+            l1_chain_id,
+            state_root_contract,
+            l1_backup_destinations,
+            state_root_method,
+            state_root_abi,
+            contract_abi_dir,
+            state_root_proxy,
+            selector_override,
+            calldata_prefix,
+            counter_contracts,
+            prefunded_key,
+            remote_signer,
+            remote_signer_address,
+            max_retries,
+            retry_base_delay,
+            confirmations,
+            confirm_poll_interval,
+            confirm_timeout,
+            submit_timeout_blocks,
+            l1_rpc_timeout,
+            priority_fee_multiplier,
+            max_fee_per_gas_cap,
+            fee_bump_timeout,
+            fee_bump_percent,
+            max_fee_bumps,
+            gas_multiplier,
+            default_gas_limit,
+            gas_limit,
+            gas_estimate_refresh,
+            gas_estimate_deviation_percent,
+            batch_size,
+            max_batch_calldata_bytes,
+            batch_flush_interval,
+            max_queued_roots,
+            min_depth,
+            shutdown_timeout,
+            notification_timeout,
+            abort_on_notification_timeout,
+            submit_interval,
+            submit_jitter,
+            start_height,
+            anchor_every,
+            state_roots_per_height,
+            access_list,
+            state_diffs,
+            state_diff_blobs,
+            dedup_state_roots,
+            legacy_state_root,
+            anchored_root,
+            skip_missing_roots,
+            l1_reorg_depth,
+            l1_recheck_interval,
+            max_anchor_gap,
+            reorg_warn_depth,
+            health_check_interval,
+            min_balance,
+            breaker_threshold,
+            breaker_cooldown,
+            submit_rate,
+            submit_burst,
+            submit_value,
+            dry_run,
+            log_events,
+            reanchor_on_mismatch,
+            local_chain_id,
+            allow_same_chain,
+            anchor_finalized,
+            reorg_only,
+            verify_root_locally,
+            name,
+        } = self;
+        let prefunded_key = if prefunded_key.is_some() { "Some(<redacted>)" } else { "None" };
+        f.debug_struct("ExExConfig")
+            .field("l1_rpc", l1_rpc)
+            .field("l1_chain_id", l1_chain_id)
+            .field("state_root_contract", state_root_contract)
+            .field("l1_backup_destinations", l1_backup_destinations)
+            .field("state_root_method", state_root_method)
+            .field("state_root_abi", state_root_abi)
+            .field("contract_abi_dir", contract_abi_dir)
+            .field("state_root_proxy", state_root_proxy)
+            .field("selector_override", selector_override)
+            .field("calldata_prefix", calldata_prefix)
+            .field("counter_contracts", counter_contracts)
+            .field("prefunded_key", &format_args!("{prefunded_key}"))
+            .field("account", &self.account())
+            .field("remote_signer", remote_signer)
+            .field("remote_signer_address", remote_signer_address)
+            .field("max_retries", max_retries)
+            .field("retry_base_delay", retry_base_delay)
+            .field("confirmations", confirmations)
+            .field("confirm_poll_interval", confirm_poll_interval)
+            .field("confirm_timeout", confirm_timeout)
+            .field("submit_timeout_blocks", submit_timeout_blocks)
+            .field("l1_rpc_timeout", l1_rpc_timeout)
+            .field("priority_fee_multiplier", priority_fee_multiplier)
+            .field("max_fee_per_gas_cap", max_fee_per_gas_cap)
+            .field("fee_bump_timeout", fee_bump_timeout)
+            .field("fee_bump_percent", fee_bump_percent)
+            .field("max_fee_bumps", max_fee_bumps)
+            .field("gas_multiplier", gas_multiplier)
+            .field("default_gas_limit", default_gas_limit)
+            .field("gas_limit", gas_limit)
+            .field("gas_estimate_refresh", gas_estimate_refresh)
+            .field("gas_estimate_deviation_percent", gas_estimate_deviation_percent)
+            .field("batch_size", batch_size)
+            .field("max_batch_calldata_bytes", max_batch_calldata_bytes)
+            .field("batch_flush_interval", batch_flush_interval)
+            .field("max_queued_roots", max_queued_roots)
+            .field("min_depth", min_depth)
+            .field("shutdown_timeout", shutdown_timeout)
+            .field("notification_timeout", notification_timeout)
+            .field("abort_on_notification_timeout", abort_on_notification_timeout)
+            .field("submit_interval", submit_interval)
+            .field("submit_jitter", submit_jitter)
+            .field("start_height", start_height)
+            .field("anchor_every", anchor_every)
+            .field("state_roots_per_height", state_roots_per_height)
+            .field("access_list", access_list)
+            .field("state_diffs", state_diffs)
+            .field("state_diff_blobs", state_diff_blobs)
+            .field("dedup_state_roots", dedup_state_roots)
+            .field("legacy_state_root", legacy_state_root)
+            .field("anchored_root", anchored_root)
+            .field("skip_missing_roots", skip_missing_roots)
+            .field("l1_reorg_depth", l1_reorg_depth)
+            .field("l1_recheck_interval", l1_recheck_interval)
+            .field("max_anchor_gap", max_anchor_gap)
+            .field("reorg_warn_depth", reorg_warn_depth)
+            .field("health_check_interval", health_check_interval)
+            .field("min_balance", min_balance)
+            .field("breaker_threshold", breaker_threshold)
+            .field("breaker_cooldown", breaker_cooldown)
+            .field("submit_rate", submit_rate)
+            .field("submit_burst", submit_burst)
+            .field("submit_value", submit_value)
+            .field("dry_run", dry_run)
+            .field("log_events", log_events)
+            .field("reanchor_on_mismatch", reanchor_on_mismatch)
+            .field("local_chain_id", local_chain_id)
+            .field("allow_same_chain", allow_same_chain)
+            .field("anchor_finalized", anchor_finalized)
+            .field("reorg_only", reorg_only)
+            .field("verify_root_locally", verify_root_locally)
+            .field("name", name)
+            .finish()
+    }
+}
+
+/// Decrypts the key of the JSON keystore at `path`, with the password in `password_file`, or the
+/// one prompted for on the terminal without it. A trailing newline of the file is ignored.
+fn decrypt_keystore(path: &Path, password_file: Option<&Path>) -> eyre::Result<PrivateKeySigner> {
+    if !path.is_file() {
+        eyre::bail!("keystore {} not found", path.display())
+    }
+    let password = match password_file {
+        Some(file) => {
+            reth_fs_util::read_to_string(file)?.trim_end_matches(['\r', '\n']).to_string()
+        }
+        None => rpassword::prompt_password(format!("Password of {}: ", path.display()))?,
+    };
+    PrivateKeySigner::decrypt_keystore(path, password)
+        .map_err(|err| eyre::eyre!("failed to decrypt keystore {}: {err}", path.display()))
+}
+
+/// Parses the JSON ABI of the given contract from the file at `path`, or the `built_in` ABI
+/// without a path, returning it with a description of where it was loaded from.
+pub(super) fn load_abi(
+    path: Option<&Path>,
+    built_in: &str,
+    contract: &str,
+) -> eyre::Result<(JsonAbi, String)> {
+    let (abi, source) = match path {
+        Some(path) => (
+            reth_fs_util::read_to_string(path)
+                .map_err(|err| eyre::eyre!("failed to read {contract} ABI: {err}"))?,
+            path.display().to_string(),
+        ),
+        None => (built_in.to_string(), "the built-in ABI".to_string()),
+    };
+    let abi = serde_json::from_str(&abi)
+        .map_err(|err| eyre::eyre!("invalid {contract} ABI in {source}: {err}"))?;
+    Ok((abi, source))
+}
+
+/// Settings of the L1 bridge ExEx in its own TOML config file, `t1.toml` in the datadir by
+/// default, see [`ExExArgs::config`].
+///
+/// The file is separate from reth's `reth.toml`, which reth rewrites without unknown sections.
+/// Every setting is optional, and overrides the environment variable named in its docs, see
+/// [`ExExConfig::with_file`]. The file only has a subset of the settings: the others, e.g. the
+/// submit rate, the minimum balance, the backup destinations, the state diffs and the submitted
+/// value, are only read from the environment, see [`ExExConfig::from_env`]. The CLI flags of
+/// [`ExExArgs`] set yet other settings, none of which is in the file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExExFileConfig {
+    /// See [`ExExConfig::l1_rpc`], overriding `L1_RPC_ADDRESS`.
+    pub l1_rpc: Option<Url>,
+    /// See [`ExExConfig::l1_chain_id`], overriding `L1_CHAIN_ID`.
+    pub l1_chain_id: Option<u64>,
+    /// See [`ExExConfig::state_root_contract`], overriding `STATE_ROOT_CONTRACT_ADDRESS`.
+    pub state_root_contract: Option<Address>,
+    /// See [`ExExConfig::counter_contracts`], overriding `COUNTER_CONTRACT_ADDRESS`.
+    pub counter_contracts: Option<Vec<Address>>,
+    /// See [`ExExConfig::max_retries`], overriding `L1_SUBMIT_MAX_RETRIES`.
+    pub max_retries: Option<usize>,
+    /// See [`ExExConfig::retry_base_delay`], overriding `L1_SUBMIT_RETRY_DELAY_MS`.
+    pub retry_delay_ms: Option<u64>,
+    /// See [`ExExConfig::confirmations`], overriding `L1_CONFIRMATIONS`.
+    pub confirmations: Option<u64>,
+    /// See [`ExExConfig::priority_fee_multiplier`], overriding `L1_PRIORITY_FEE_MULTIPLIER`.
+    pub priority_fee_multiplier: Option<f64>,
+    /// See [`ExExConfig::max_fee_per_gas_cap`], overriding `L1_MAX_FEE_PER_GAS_CAP`. At most
+    /// `i64::MAX`, the largest TOML integer.
+    pub max_fee_per_gas_cap: Option<u64>,
+    /// See [`ExExConfig::fee_bump_timeout`], overriding `L1_FEE_BUMP_TIMEOUT_MS`.
+    pub fee_bump_timeout_ms: Option<u64>,
+    /// See [`ExExConfig::fee_bump_percent`], overriding `L1_FEE_BUMP_PERCENT`.
+    pub fee_bump_percent: Option<f64>,
+    /// See [`ExExConfig::max_fee_bumps`], overriding `L1_MAX_FEE_BUMPS`.
+    pub max_fee_bumps: Option<u32>,
+    /// See [`ExExConfig::gas_multiplier`], overriding `L1_GAS_MULTIPLIER`.
+    pub gas_multiplier: Option<f64>,
+    /// See [`ExExConfig::default_gas_limit`], overriding `L1_DEFAULT_GAS_LIMIT`.
+    pub default_gas_limit: Option<u64>,
+    /// See [`ExExConfig::gas_limit`], overriding `L1_GAS_LIMIT`.
+    pub gas_limit: Option<u64>,
+    /// See [`ExExConfig::batch_size`], overriding `L1_BATCH_SIZE`.
+    pub batch_size: Option<usize>,
+    /// See [`ExExConfig::max_batch_calldata_bytes`], overriding `L1_MAX_BATCH_CALLDATA_BYTES`.
+    pub max_batch_calldata_bytes: Option<usize>,
+    /// See [`ExExConfig::batch_flush_interval`], overriding `L1_BATCH_FLUSH_INTERVAL_MS`.
+    pub batch_flush_interval_ms: Option<u64>,
+    /// See [`ExExConfig::max_queued_roots`], overriding `L1_MAX_QUEUED_ROOTS`.
+    pub max_queued_roots: Option<usize>,
+}
+
+impl ExExFileConfig {
+    /// Reads the config file at the given path. A missing file is an empty config.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(eyre::eyre!("failed to read {}: {err}", path.display())),
+        };
+        Self::parse(&contents).map_err(|err| eyre::eyre!("invalid {}: {err}", path.display()))
+    }
+
+    /// Parses the given TOML config.
+    fn parse(contents: &str) -> eyre::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+}
+
+/// L1 chain and contract the state roots are anchored to, in addition to the primary one, see
+/// [`ExExConfig::l1_backup_destinations`].
+///
+/// Parsed from `<rpc>,<contract>[,<chain id>]`, e.g.
+/// `https://backup.example,0xb4B46bdAA835F8E4b4d8e208B6559cD267851051,11155111`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1Destination {
+    /// L1 RPC endpoint the state roots are submitted to.
+    pub l1_rpc: Url,
+    /// Address of the L1 contract the state roots are anchored in.
+    pub state_root_contract: Address,
+    /// Chain id the L1 RPC endpoint has to report, or `None` to accept any chain.
+    pub l1_chain_id: Option<u64>,
+}
+
+impl FromStr for L1Destination {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        let parts = s.split(',').map(str::trim).collect::<Vec<_>>();
+        let (rpc, contract, chain_id) = match parts[..] {
+            [rpc, contract] => (rpc, contract, None),
+            [rpc, contract, chain_id] => (rpc, contract, Some(chain_id.parse()?)),
+            _ => eyre::bail!("expected `<rpc>,<contract>[,<chain id>]`, got `{s}`"),
+        };
+        Ok(Self {
+            l1_rpc: rpc.parse()?,
+            state_root_contract: contract.parse()?,
+            l1_chain_id: chain_id,
+        })
+    }
+}
+
+/// Transport to the L1 RPC endpoint, selected by the scheme of [`ExExConfig::l1_rpc`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum L1Endpoint {
+    /// `http://` or `https://` endpoint.
+    Http(Url),
+    /// `ws://` or `wss://` endpoint.
+    Ws(Url),
+    /// IPC socket of a local L1 node, given as a `file://` URL.
+    Ipc(PathBuf),
+}
+
+impl L1Endpoint {
+    /// Selects the transport for the given URL by its scheme.
+    pub fn parse(url: &Url) -> eyre::Result<Self> {
+        match url.scheme() {
+            "http" | "https" => Ok(Self::Http(url.clone())),
+            "ws" | "wss" => Ok(Self::Ws(url.clone())),
+            "file" => url
+                .to_file_path()
+                .map(Self::Ipc)
+                .map_err(|_| eyre::eyre!("invalid IPC path in L1 RPC endpoint {url}")),
+            scheme => eyre::bail!("unsupported scheme {scheme} of L1 RPC endpoint {url}"),
+        }
+    }
+
+    /// Connects to the endpoint.
+    pub async fn connect(&self) -> TransportResult<BoxTransport> {
+        match self {
+            Self::Http(url) => Ok(Http::new(url.clone()).boxed()),
+            Self::Ws(url) => Ok(WsConnect::new(url.as_str()).into_service().await?.boxed()),
+            Self::Ipc(path) => Ok(IpcConnect::new(path.clone()).into_service().await?.boxed()),
+        }
+    }
+}
+
+/// Source of the root anchored on L1 for a block.
+///
+/// By default the state root of the block's header is anchored, see [`AnchoredRoot`] for the other
+/// built-in roots, while rollups anchoring another commitment, e.g. over multiple fields of the
+/// header, compute it here. The root is derived from the header alone, since reverted and
+/// backfilled blocks are only read as headers.
+pub trait StateRootProvider: fmt::Debug + Send + Sync {
+    /// Returns the root anchored for the block of the given header, or `None` if the block has
+    /// no such root.
+    fn root_for(&self, header: &Header) -> Option<B256> {
+        Some(header.state_root)
+    }
+}
+
+/// [`StateRootProvider`] anchoring the state root of the header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeaderStateRoot;
+
+impl StateRootProvider for HeaderStateRoot {}
+
+/// [`StateRootProvider`] anchoring the receipts root of the header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeaderReceiptsRoot;
+
+impl StateRootProvider for HeaderReceiptsRoot {
+    fn root_for(&self, header: &Header) -> Option<B256> {
+        Some(header.receipts_root)
+    }
+}
+
+/// [`StateRootProvider`] anchoring the withdrawals root of the header, which pre-Shanghai blocks
+/// don't have.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeaderWithdrawalsRoot;
+
+impl StateRootProvider for HeaderWithdrawalsRoot {
+    fn root_for(&self, header: &Header) -> Option<B256> {
+        header.withdrawals_root
+    }
+}
+
+/// Root of the block header anchored on L1, selecting the built-in [`StateRootProvider`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnchoredRoot {
+    /// The state root, see [`HeaderStateRoot`].
+    #[default]
+    State,
+    /// The receipts root, see [`HeaderReceiptsRoot`].
+    Receipts,
+    /// The withdrawals root of post-Shanghai blocks, see [`HeaderWithdrawalsRoot`].
+    Withdrawals,
+}
+
+impl AnchoredRoot {
+    /// Returns the [`StateRootProvider`] of the root.
+    pub fn provider(self) -> Arc<dyn StateRootProvider> {
+        match self {
+            Self::State => Arc::new(HeaderStateRoot),
+            Self::Receipts => Arc::new(HeaderReceiptsRoot),
+            Self::Withdrawals => Arc::new(HeaderWithdrawalsRoot),
+        }
+    }
+}
+
+impl FromStr for AnchoredRoot {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        match s {
+            "state" => Ok(Self::State),
+            "receipts" => Ok(Self::Receipts),
+            "withdrawals" => Ok(Self::Withdrawals),
+            _ => eyre::bail!("expected `state`, `receipts` or `withdrawals`, got `{s}`"),
+        }
+    }
+}
+
+/// Returns the root the given [`StateRootProvider`] anchors for the block of the given header,
+/// failing if the block has no such root.
+pub(super) fn root_for(state_roots: &dyn StateRootProvider, header: &Header) -> eyre::Result<B256> {
+    state_roots.root_for(header).ok_or_else(|| {
+        eyre::eyre!("block {} has no root to anchor, see {state_roots:?}", header.number)
+    })
+}
+
+/// Parameters for configuring the L1 bridge ExEx
+#[derive(Debug, Clone, Args, PartialEq, Eq)]
+#[command(next_help_heading = "ExEx")]
+pub struct ExExArgs {
+    /// Log the L1 transactions anchoring state roots, including their calldata, instead of
+    /// sending them
+    #[arg(long = "exex.dry-run", default_value = "false")]
+    pub dry_run: bool,
+
+    /// Minimum time between two L1 submissions in milliseconds. Blocks committed in between are
+    /// coalesced, only the state root of the latest of them is anchored
+    #[arg(long = "exex.submit-interval-ms", value_name = "MILLIS", default_value_t = 0)]
+    pub submit_interval_ms: u64,
+
+    /// Maximum random delay in milliseconds before every L1 submission. Staggers multiple nodes
+    /// anchoring the same chain, so the first one to submit wins and the others find its state
+    /// roots already anchored after their delay. Zero submits without a delay
+    #[arg(long = "exex.submit-jitter-ms", value_name = "MILLIS", default_value_t = 0)]
+    pub submit_jitter_ms: u64,
+
+    /// Interval in milliseconds the L1 is polled at while waiting for a submission to be
+    /// confirmed
+    #[arg(
+        long = "exex.confirm-poll-interval-ms",
+        value_name = "MILLIS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value_t = L1_CONFIRM_POLL_INTERVAL_MS
+    )]
+    pub confirm_poll_interval_ms: u64,
+
+    /// Maximum time in milliseconds to wait for a submission to be confirmed, including the
+    /// transactions replacing it, before it fails. Zero waits indefinitely
+    #[arg(long = "exex.confirm-timeout-ms", value_name = "MILLIS", default_value_t = 0)]
+    pub confirm_timeout_ms: u64,
+
+    /// Maximum number of L1 blocks to wait for a submission to be confirmed, including the
+    /// transactions replacing it, before it fails. Bounds the confirmation by the progress of the
+    /// L1 instead of by time
+    #[arg(
+        long = "exex.submit-timeout-blocks",
+        value_name = "BLOCKS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "confirm_timeout_ms"
+    )]
+    pub submit_timeout_blocks: Option<u64>,
+
+    /// Maximum time in milliseconds a single L1 RPC call may take before it fails and, if part
+    /// of a submission, is retried. Zero waits indefinitely
+    #[arg(
+        long = "exex.l1-rpc-timeout-ms",
+        value_name = "MILLIS",
+        default_value_t = L1_RPC_TIMEOUT_MS
+    )]
+    pub l1_rpc_timeout_ms: u64,
+
+    /// Number of the first block whose state root is anchored. Committed blocks below it are
+    /// finished without being anchored, e.g. to start anchoring from a known genesis point on a
+    /// fresh sync
+    #[arg(long = "exex.start-height", value_name = "BLOCK", default_value_t = 0)]
+    pub start_height: BlockNumber,
+
+    /// Only anchor the state roots of block numbers that are a multiple of N, to reduce the
+    /// number of L1 submissions. Reorgs and reverts are always corrected, regardless of the
+    /// block number
+    #[arg(
+        long = "exex.anchor-every",
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value_t = 1
+    )]
+    pub anchor_every: u64,
+
+    /// Log every Counter event decoded from the committed chains, with its block number,
+    /// transaction hash and event, whether or not its block is anchored
+    #[arg(long = "exex.log-events", default_value = "false")]
+    pub log_events: bool,
+
+    /// Anchor the local state root of the last anchored block again if it disagrees with the one
+    /// anchored on L1 at startup, instead of only reporting the discrepancy
+    #[arg(long = "exex.reanchor-on-mismatch", default_value = "false")]
+    pub reanchor_on_mismatch: bool,
+
+    /// Allow the L1 RPC endpoint to be on the same chain as the local node, e.g. in test setups
+    /// anchoring a chain to itself
+    #[arg(long = "exex.allow-same-chain", default_value = "false")]
+    pub allow_same_chain: bool,
+
+    /// Only anchor the state roots of blocks the consensus layer has finalized. The state roots
+    /// of later blocks stay queued until they are finalized, and are never anchored if they are
+    /// reorged out before
+    #[arg(long = "exex.anchor-finalized", default_value = "false")]
+    pub anchor_finalized: bool,
+
+    /// Only anchor the state root of the new canonical tip after a reorg or revert. Committed
+    /// chains are finished without being anchored, for verification schemes that only track
+    /// reorg boundaries
+    #[arg(long = "exex.reorg-only", default_value = "false", conflicts_with = "anchor_finalized")]
+    pub reorg_only: bool,
+
+    /// Recompute the state root of the tip of every committed chain from the node's state and
+    /// refuse to anchor the chain if it differs from the state root in the header. Catches
+    /// corrupted state before it's anchored, at the cost of a state root computation per chain
+    #[arg(long = "exex.verify-root-locally", default_value = "false")]
+    pub verify_root_locally: bool,
+
+    /// Directory to read both contract ABIs from, as `counter_abi.json` and
+    /// `state_root_abi.json`, instead of the ABIs the binary is built with. `STATE_ROOT_ABI`
+    /// takes precedence over the state root contract ABI in the directory
+    #[arg(long = "exex.contract-abi-dir", value_name = "DIR")]
+    pub contract_abi_dir: Option<PathBuf>,
+
+    /// TOML config file of the L1 bridge, `t1.toml` in the datadir by default. A missing file is
+    /// ignored
+    #[arg(long = "exex.config", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Name the ExEx is installed with, which has to be unique among the installed ExExes
+    #[arg(long = "exex.name", value_name = "NAME", default_value = EXEX_NAME)]
+    pub name: String,
+
+    /// Install the ExEx without anchoring anything, e.g. during L1 maintenance. Committed blocks
+    /// are finished right away and the L1 is never contacted
+    #[arg(long = "exex.disable", default_value = "false")]
+    pub disable: bool,
+
+    /// Log the configuration of the L1 bridge resolved from the environment, the config file and
+    /// the CLI, with the private key redacted. Then either continue starting the node, or print
+    /// it and exit before the node is launched
+    #[arg(
+        long = "exex.print-config",
+        value_name = "THEN",
+        num_args = 0..=1,
+        default_missing_value = "continue"
+    )]
+    pub print_config: Option<PrintConfig>,
+}
+
+/// What the node does after printing the resolved configuration, see [`ExExArgs::print_config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PrintConfig {
+    /// Continue starting the node.
+    Continue,
+    /// Exit the node.
+    Exit,
+}
+
+impl Default for ExExArgs {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            submit_interval_ms: 0,
+            submit_jitter_ms: 0,
+            confirm_poll_interval_ms: L1_CONFIRM_POLL_INTERVAL_MS,
+            confirm_timeout_ms: 0,
+            submit_timeout_blocks: None,
+            l1_rpc_timeout_ms: L1_RPC_TIMEOUT_MS,
+            start_height: 0,
+            anchor_every: 1,
+            log_events: false,
+            reanchor_on_mismatch: false,
+            allow_same_chain: false,
+            anchor_finalized: false,
+            reorg_only: false,
+            verify_root_locally: false,
+            contract_abi_dir: None,
+            config: None,
+            name: EXEX_NAME.to_string(),
+            disable: false,
+            print_config: None,
+        }
+    }
+}
+
+impl ExExArgs {
+    /// Resolves the [`ExExConfig`] of the node with the given configuration from the environment,
+    /// the [`ExExFileConfig`] of [`Self::config`] and the arguments, see [`ExExConfig::from_env`].
+    ///
+    /// The node's configuration is all that's needed, so the configuration can be resolved, and
+    /// printed with [`Self::print_config`], before the node is launched.
+    pub fn resolve_config<ChainSpec: EthChainSpec>(
+        &self,
+        node: &NodeConfig<ChainSpec>,
+    ) -> eyre::Result<ExExConfig> {
+        let config_path =
+            self.config.clone().unwrap_or_else(|| node.datadir().data_dir().join(L1_CONFIG_FILE));
+        Ok(ExExConfig {
+            dry_run: self.dry_run,
+            submit_interval: Duration::from_millis(self.submit_interval_ms),
+            submit_jitter: Duration::from_millis(self.submit_jitter_ms),
+            confirm_poll_interval: Duration::from_millis(self.confirm_poll_interval_ms),
+            confirm_timeout: Duration::from_millis(self.confirm_timeout_ms),
+            submit_timeout_blocks: self.submit_timeout_blocks,
+            l1_rpc_timeout: Duration::from_millis(self.l1_rpc_timeout_ms),
+            start_height: self.start_height,
+            anchor_every: self.anchor_every,
+            log_events: self.log_events,
+            reanchor_on_mismatch: self.reanchor_on_mismatch,
+            local_chain_id: Some(node.chain.chain().id()),
+            allow_same_chain: self.allow_same_chain,
+            anchor_finalized: self.anchor_finalized,
+            reorg_only: self.reorg_only,
+            verify_root_locally: self.verify_root_locally,
+            contract_abi_dir: self.contract_abi_dir.clone(),
+            name: self.name.clone(),
+            ..ExExConfig::from_env_and_file(ExExFileConfig::load(&config_path)?)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        test_utils::{
+            set_test_env, test_env, L1_RPC_ADDRESS, STATE_ROOT_CONTRACT_ADDRESS, TEST_SECRET,
+        },
+        StateRootAbi::changeStateRootCall,
+    };
+    use eyre::OptionExt;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_exex_config_from_env() -> eyre::Result<()> {
+        let state_root_contract = address!("1111111111111111111111111111111111111111");
+        let counter_contract = address!("2222222222222222222222222222222222222222");
+        let other_counter_contract = address!("3333333333333333333333333333333333333333");
+        let secret = "0101010101010101010101010101010101010101010101010101010101010101";
+        let env = HashMap::from([
+            ("L1_RPC_ADDRESS", "http://localhost:8545".to_string()),
+            ("L1_CHAIN_ID", "11155111".to_string()),
+            ("STATE_ROOT_CONTRACT_ADDRESS", state_root_contract.to_string()),
+            (
+                "L1_BACKUP_DESTINATIONS",
+                format!(
+                    "http://localhost:8546,{state_root_contract};\
+                     ws://localhost:8547, {counter_contract}, 17000"
+                ),
+            ),
+            ("STATE_ROOT_METHOD", "submitStateRoot".to_string()),
+            ("STATE_ROOT_ABI", "/etc/t1/state_root_abi.json".to_string()),
+            ("STATE_ROOT_PROXY", "true".to_string()),
+            ("STATE_ROOT_SELECTOR", "0x12345678".to_string()),
+            ("STATE_ROOT_CALLDATA_PREFIX", "0xabcd".to_string()),
+            ("COUNTER_CONTRACT_ADDRESS", format!("{counter_contract}, {other_counter_contract}")),
+            ("PREFUNDED_SECRET", secret.to_string()),
+            ("L1_REMOTE_SIGNER_URL", "http://localhost:9000".to_string()),
+            ("L1_REMOTE_SIGNER_ADDRESS", counter_contract.to_string()),
+            ("L1_SUBMIT_MAX_RETRIES", "2".to_string()),
+            ("L1_SUBMIT_RETRY_DELAY_MS", "100".to_string()),
+            ("L1_CONFIRMATIONS", "3".to_string()),
+            ("L1_PRIORITY_FEE_MULTIPLIER", "1.5".to_string()),
+            ("L1_MAX_FEE_PER_GAS_CAP", "100000000000".to_string()),
+            ("L1_FEE_BUMP_TIMEOUT_MS", "60000".to_string()),
+            ("L1_FEE_BUMP_PERCENT", "25".to_string()),
+            ("L1_MAX_FEE_BUMPS", "5".to_string()),
+            ("L1_GAS_MULTIPLIER", "1.5".to_string()),
+            ("L1_DEFAULT_GAS_LIMIT", "100000".to_string()),
+            ("L1_GAS_LIMIT", "50000".to_string()),
+            ("L1_GAS_ESTIMATE_REFRESH_MS", "60000".to_string()),
+            ("L1_GAS_ESTIMATE_DEVIATION_PERCENT", "10".to_string()),
+            ("L1_BATCH_SIZE", "10".to_string()),
+            ("L1_MAX_BATCH_CALLDATA_BYTES", "4096".to_string()),
+            ("L1_BATCH_FLUSH_INTERVAL_MS", "1000".to_string()),
+            ("L1_MAX_QUEUED_ROOTS", "500".to_string()),
+            ("L1_MIN_DEPTH", "6".to_string()),
+            ("L1_SHUTDOWN_TIMEOUT_MS", "2000".to_string()),
+            ("L1_NOTIFICATION_TIMEOUT_MS", "30000".to_string()),
+            ("L1_ABORT_ON_NOTIFICATION_TIMEOUT", "true".to_string()),
+            ("L1_STATE_ROOTS_PER_HEIGHT", "true".to_string()),
+            ("L1_ACCESS_LIST", "true".to_string()),
+            ("L1_STATE_DIFFS", "true".to_string()),
+            ("L1_STATE_DIFF_BLOBS", "true".to_string()),
+            ("L1_DEDUP_STATE_ROOTS", "false".to_string()),
+            ("L1_LEGACY_STATE_ROOT", "true".to_string()),
+            ("L1_ANCHORED_ROOT", "withdrawals".to_string()),
+            ("L1_SKIP_MISSING_ROOTS", "true".to_string()),
+            ("L1_REORG_DEPTH", "4".to_string()),
+            ("L1_RECHECK_INTERVAL_MS", "6000".to_string()),
+            ("L1_MAX_ANCHOR_GAP", "10".to_string()),
+            ("L1_REORG_WARN_DEPTH", "12".to_string()),
+            ("L1_HEALTH_CHECK_INTERVAL_MS", "5000".to_string()),
+            ("L1_MIN_BALANCE_WEI", "1000000000000000000".to_string()),
+            ("L1_BREAKER_THRESHOLD", "3".to_string()),
+            ("L1_BREAKER_COOLDOWN_MS", "10000".to_string()),
+            ("L1_SUBMIT_RATE", "0.5".to_string()),
+            ("L1_SUBMIT_BURST", "3".to_string()),
+            ("L1_SUBMIT_VALUE_WEI", "1000000000000000".to_string()),
+        ]);
+
+        let config = ExExConfig::from_env_with(|key| env.get(key).cloned())?;
+        assert_eq!(config.l1_rpc, Url::parse("http://localhost:8545")?);
+        assert_eq!(config.l1_chain_id, Some(11_155_111));
+        assert_eq!(config.state_root_contract, state_root_contract);
+        assert_eq!(
+            config.l1_backup_destinations,
+            vec![
+                L1Destination {
+                    l1_rpc: Url::parse("http://localhost:8546")?,
+                    state_root_contract,
+                    l1_chain_id: None,
+                },
+                L1Destination {
+                    l1_rpc: Url::parse("ws://localhost:8547")?,
+                    state_root_contract: counter_contract,
+                    l1_chain_id: Some(17_000),
+                },
+            ]
+        );
+        assert_eq!(config.state_root_method, "submitStateRoot");
+        assert_eq!(config.state_root_abi, Some(PathBuf::from("/etc/t1/state_root_abi.json")));
+        assert!(config.state_root_proxy);
+        assert_eq!(config.selector_override, Some(Selector::new([0x12, 0x34, 0x56, 0x78])));
+        assert_eq!(config.calldata_prefix, Bytes::from_static(&[0xab, 0xcd]));
+        assert_eq!(config.counter_contracts, vec![counter_contract, other_counter_contract]);
+        let key = config.prefunded_key.as_ref().map(|key| key.to_bytes());
+        assert_eq!(key, Some(PrivateKeySigner::from_str(secret)?.to_bytes()));
+        assert_eq!(config.remote_signer, Some(Url::parse("http://localhost:9000")?));
+        assert_eq!(config.remote_signer_address, Some(counter_contract));
+        assert_eq!(config.account(), counter_contract);
+        assert_eq!(config.max_retries, 2);
+        assert_eq!(config.retry_base_delay, Duration::from_millis(100));
+        assert_eq!(config.confirmations, 3);
+        assert_eq!(config.priority_fee_multiplier, 1.5);
+        assert_eq!(config.max_fee_per_gas_cap, Some(100_000_000_000));
+        assert_eq!(config.fee_bump_timeout, Duration::from_secs(60));
+        assert_eq!(config.fee_bump_percent, 25.0);
+        assert_eq!(config.max_fee_bumps, 5);
+        assert_eq!(config.gas_multiplier, 1.5);
+        assert_eq!(config.default_gas_limit, 100_000);
+        assert_eq!(config.gas_limit, Some(50_000));
+        assert_eq!(config.gas_estimate_refresh, Duration::from_secs(60));
+        assert_eq!(config.gas_estimate_deviation_percent, 10.0);
+        assert_eq!(config.batch_size, 10);
+        assert_eq!(config.max_batch_calldata_bytes, Some(4096));
+        assert_eq!(config.batch_flush_interval, Duration::from_secs(1));
+        assert_eq!(config.max_queued_roots, 500);
+        assert_eq!(config.min_depth, 6);
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(2));
+        assert_eq!(config.notification_timeout, Some(Duration::from_secs(30)));
+        assert!(config.abort_on_notification_timeout);
+        assert!(config.state_roots_per_height);
+        assert!(config.access_list);
+        assert!(config.state_diffs);
+        assert!(config.state_diff_blobs);
+        assert!(!config.dedup_state_roots);
+        assert!(config.legacy_state_root);
+        assert_eq!(config.anchored_root, AnchoredRoot::Withdrawals);
+        assert!(config.skip_missing_roots);
+        assert_eq!(config.l1_recheck_interval, Duration::from_secs(6));
+        assert_eq!(config.max_anchor_gap, 10);
+        assert_eq!(config.reorg_warn_depth, 12);
+        assert_eq!(config.health_check_interval, Duration::from_secs(5));
+        assert_eq!(config.min_balance, 1_000_000_000_000_000_000);
+        assert_eq!(config.breaker_threshold, 3);
+        assert_eq!(config.breaker_cooldown, Duration::from_secs(10));
+        assert_eq!(config.submit_rate, 0.5);
+        assert_eq!(config.submit_burst, 3);
+        assert_eq!(config.submit_value, 1_000_000_000_000_000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exex_config_defaults() -> eyre::Result<()> {
+        // There's no default L1 endpoint, state root contract or key
+        let without = |missing: &'static str| {
+            ExExConfig::from_env_with(move |key| (key != missing).then(|| test_env(key)).flatten())
+        };
+        for (missing, expected) in [
+            ("L1_RPC_ADDRESS", "no L1 endpoint configured"),
+            ("STATE_ROOT_CONTRACT_ADDRESS", "no state root contract configured"),
+            ("PREFUNDED_SECRET", "no L1 signing key configured"),
+        ] {
+            let err = without(missing).unwrap_err();
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+
+        // The endpoint and the contract may be set in the file instead
+        let file = ExExFileConfig {
+            l1_rpc: Some(Url::parse(L1_RPC_ADDRESS)?),
+            state_root_contract: Some(STATE_ROOT_CONTRACT_ADDRESS),
+            ..Default::default()
+        };
+        let env = |key: &str| (key == "PREFUNDED_SECRET").then(|| TEST_SECRET.to_string());
+        let config = ExExConfig::from_sources(env, file)?;
+        assert_eq!(config.state_root_contract, STATE_ROOT_CONTRACT_ADDRESS);
+
+        // A remote signer replaces the key
+        let remote = |key: &str| match key {
+            "L1_REMOTE_SIGNER_URL" => Some("http://localhost:9000".to_string()),
+            "PREFUNDED_SECRET" => None,
+            _ => test_env(key),
+        };
+        let config = ExExConfig::from_env_with(remote)?;
+        assert!(config.prefunded_key.is_none());
+        assert_eq!(config.account(), Address::ZERO);
+        let err = ExExConfig { remote_signer: None, ..config }.signer().err().unwrap();
+        assert!(err.to_string().contains("PREFUNDED_SECRET"), "{err}");
+
+        let config = ExExConfig::from_env_with(test_env)?;
+        assert_eq!(config.l1_rpc, Url::parse(L1_RPC_ADDRESS)?);
+        assert_eq!(config.l1_chain_id, None);
+        assert_eq!(config.state_root_contract, STATE_ROOT_CONTRACT_ADDRESS);
+        assert_eq!(config.l1_backup_destinations, vec![]);
+        assert_eq!(config.state_root_method, STATE_ROOT_METHOD);
+        assert_eq!(config.state_root_abi, None);
+        assert_eq!(config.state_root_proxy, STATE_ROOT_PROXY);
+        assert_eq!(config.selector_override, None);
+        assert_eq!(config.calldata_prefix, Bytes::new());
+        assert_eq!(config.counter_contracts, vec![COUNTER_CONTRACT_ADDRESS]);
+        let key = config.prefunded_key.as_ref().map(|key| key.to_bytes());
+        assert_eq!(key, Some(PrivateKeySigner::from_str(TEST_SECRET)?.to_bytes()));
+        assert_eq!(config.remote_signer, None);
+        assert_eq!(config.remote_signer_address, None);
+        assert_eq!(config.account(), PrivateKeySigner::from_str(TEST_SECRET)?.address());
+        assert_eq!(config.max_retries, L1_SUBMIT_MAX_RETRIES);
+        assert_eq!(config.retry_base_delay, Duration::from_millis(L1_SUBMIT_RETRY_DELAY_MS));
+        assert_eq!(config.confirmations, L1_CONFIRMATIONS);
+        assert_eq!(config.priority_fee_multiplier, L1_PRIORITY_FEE_MULTIPLIER);
+        assert_eq!(config.max_fee_per_gas_cap, None);
+        assert_eq!(config.fee_bump_timeout, Duration::from_millis(L1_FEE_BUMP_TIMEOUT_MS));
+        assert_eq!(config.fee_bump_percent, L1_FEE_BUMP_PERCENT);
+        assert_eq!(config.max_fee_bumps, L1_MAX_FEE_BUMPS);
+        assert_eq!(config.gas_multiplier, L1_GAS_MULTIPLIER);
+        assert_eq!(config.default_gas_limit, L1_DEFAULT_GAS_LIMIT);
+        assert_eq!(config.gas_limit, None);
+        assert_eq!(config.gas_estimate_refresh, Duration::from_millis(L1_GAS_ESTIMATE_REFRESH_MS));
+        assert_eq!(config.gas_estimate_deviation_percent, L1_GAS_ESTIMATE_DEVIATION_PERCENT);
+        assert_eq!(config.batch_size, L1_BATCH_SIZE);
+        assert_eq!(config.max_batch_calldata_bytes, None);
+        assert_eq!(config.batch_flush_interval, Duration::from_millis(L1_BATCH_FLUSH_INTERVAL_MS));
+        assert_eq!(config.max_queued_roots, L1_MAX_QUEUED_ROOTS);
+        assert_eq!(config.min_depth, L1_MIN_DEPTH);
+        assert_eq!(config.shutdown_timeout, Duration::from_millis(L1_SHUTDOWN_TIMEOUT_MS));
+        assert_eq!(config.notification_timeout, None);
+        assert_eq!(config.abort_on_notification_timeout, L1_ABORT_ON_NOTIFICATION_TIMEOUT);
+        assert_eq!(config.state_roots_per_height, L1_STATE_ROOTS_PER_HEIGHT);
+        assert_eq!(config.access_list, L1_ACCESS_LIST);
+        assert_eq!(config.state_diffs, L1_STATE_DIFFS);
+        assert_eq!(config.state_diff_blobs, L1_STATE_DIFF_BLOBS);
+        assert_eq!(config.dedup_state_roots, L1_DEDUP_STATE_ROOTS);
+        assert_eq!(config.legacy_state_root, L1_LEGACY_STATE_ROOT);
+        assert_eq!(config.anchored_root, AnchoredRoot::State);
+        assert_eq!(config.skip_missing_roots, L1_SKIP_MISSING_ROOTS);
+        assert_eq!(config.l1_reorg_depth, L1_REORG_DEPTH);
+        assert_eq!(config.l1_recheck_interval, Duration::from_millis(L1_RECHECK_INTERVAL_MS));
+        assert_eq!(config.max_anchor_gap, L1_MAX_ANCHOR_GAP);
+        assert_eq!(config.reorg_warn_depth, L1_REORG_WARN_DEPTH);
+        assert_eq!(
+            config.health_check_interval,
+            Duration::from_millis(L1_HEALTH_CHECK_INTERVAL_MS)
+        );
+        assert_eq!(config.min_balance, L1_MIN_BALANCE_WEI);
+        assert_eq!(config.breaker_threshold, L1_BREAKER_THRESHOLD);
+        assert_eq!(config.breaker_cooldown, Duration::from_millis(L1_BREAKER_COOLDOWN_MS));
+        assert_eq!(config.submit_rate, L1_SUBMIT_RATE);
+        assert_eq!(config.submit_burst, L1_SUBMIT_BURST);
+        assert_eq!(config.submit_value, L1_SUBMIT_VALUE_WEI);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exex_config_redacted() -> eyre::Result<()> {
+        let secret = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+        let env = |key: &str| {
+            if key == "PREFUNDED_SECRET" {
+                Some(secret.to_string())
+            } else {
+                test_env(key)
+            }
+        };
+        let config = ExExConfig::from_env_with(env)?;
+
+        // The configuration is printed in full, but for the key
+        let key = config.prefunded_key.as_ref().ok_or_eyre("no prefunded key")?;
+        let hex_key = alloy_primitives::hex::encode(key.to_bytes());
+        assert_eq!(hex_key, secret.trim_start_matches("0x"));
+        for redacted in [format!("{config:?}"), format!("{config:#?}")] {
+            assert!(redacted.contains("prefunded_key: Some(<redacted>)"), "{redacted}");
+            let contract = config.state_root_contract;
+            assert!(redacted.contains(&format!("state_root_contract: {contract:?}")), "{redacted}");
+            // The key's address is printed, the key itself isn't
+            assert!(redacted.contains(&format!("account: {:?}", key.address())), "{redacted}");
+            assert!(!redacted.to_lowercase().contains(&hex_key), "{redacted}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exex_config_invalid_env() {
+        for key in [
+            "L1_RPC_ADDRESS",
+            "L1_CHAIN_ID",
+            "STATE_ROOT_CONTRACT_ADDRESS",
+            "L1_BACKUP_DESTINATIONS",
+            "STATE_ROOT_PROXY",
+            "STATE_ROOT_SELECTOR",
+            "STATE_ROOT_CALLDATA_PREFIX",
+            "COUNTER_CONTRACT_ADDRESS",
+            "PREFUNDED_SECRET",
+            "L1_KEYSTORE",
+            "L1_KEYSTORE_PASSWORD_FILE",
+            "L1_REMOTE_SIGNER_URL",
+            "L1_REMOTE_SIGNER_ADDRESS",
+            "L1_SUBMIT_MAX_RETRIES",
+            "L1_SUBMIT_RETRY_DELAY_MS",
+            "L1_CONFIRMATIONS",
+            "L1_PRIORITY_FEE_MULTIPLIER",
+            "L1_MAX_FEE_PER_GAS_CAP",
+            "L1_FEE_BUMP_TIMEOUT_MS",
+            "L1_FEE_BUMP_PERCENT",
+            "L1_MAX_FEE_BUMPS",
+            "L1_GAS_MULTIPLIER",
+            "L1_DEFAULT_GAS_LIMIT",
+            "L1_GAS_LIMIT",
+            "L1_GAS_ESTIMATE_REFRESH_MS",
+            "L1_GAS_ESTIMATE_DEVIATION_PERCENT",
+            "L1_BATCH_SIZE",
+            "L1_MAX_BATCH_CALLDATA_BYTES",
+            "L1_BATCH_FLUSH_INTERVAL_MS",
+            "L1_MAX_QUEUED_ROOTS",
+            "L1_MIN_DEPTH",
+            "L1_SHUTDOWN_TIMEOUT_MS",
+            "L1_NOTIFICATION_TIMEOUT_MS",
+            "L1_ABORT_ON_NOTIFICATION_TIMEOUT",
+            "L1_STATE_ROOTS_PER_HEIGHT",
+            "L1_ACCESS_LIST",
+            "L1_STATE_DIFFS",
+            "L1_STATE_DIFF_BLOBS",
+            "L1_DEDUP_STATE_ROOTS",
+            "L1_LEGACY_STATE_ROOT",
+            "L1_ANCHORED_ROOT",
+            "L1_SKIP_MISSING_ROOTS",
+            "L1_REORG_DEPTH",
+            "L1_RECHECK_INTERVAL_MS",
+            "L1_MAX_ANCHOR_GAP",
+            "L1_REORG_WARN_DEPTH",
+            "L1_HEALTH_CHECK_INTERVAL_MS",
+            "L1_MIN_BALANCE_WEI",
+            "L1_BREAKER_THRESHOLD",
+            "L1_BREAKER_COOLDOWN_MS",
+            "L1_SUBMIT_RATE",
+            "L1_SUBMIT_BURST",
+            "L1_SUBMIT_VALUE_WEI",
+        ] {
+            let env = |k: &str| if k == key { Some("invalid".to_string()) } else { test_env(k) };
+            let err = ExExConfig::from_env_with(env).unwrap_err();
+            assert!(err.to_string().contains(key), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_exex_config_keystore() -> eyre::Result<()> {
+        // V3 keystore of the key 0x4c0883a6...3f362318, encrypted with cheap scrypt parameters
+        const KEYSTORE: &str = r#"{
+            "address": "2c7536e3605d9c16a7a3d7b1898e529396a65c23",
+            "crypto": {
+                "cipher": "aes-128-ctr",
+                "cipherparams": { "iv": "6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b" },
+                "ciphertext": "ef599389b13c1d16b7d714f1cc0dbff66e16356aa6af7c03a5e6193bdb2cabc1",
+                "kdf": "scrypt",
+                "kdfparams": {
+                    "dklen": 32,
+                    "n": 1024,
+                    "p": 1,
+                    "r": 8,
+                    "salt": "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a"
+                },
+                "mac": "c2d0b575b8652f58676980fc316cd1ac23cf89ffef8a850ae9d3abf08666a7fc"
+            },
+            "id": "7e59dc02-8d42-409d-b29a-a8a0f862cc81",
+            "version": 3
+        }"#;
+        let dir = tempfile::tempdir()?;
+        let write = |name: &str, contents: &str| -> eyre::Result<String> {
+            let path = dir.path().join(name);
+            std::fs::write(&path, contents)?;
+            Ok(path.display().to_string())
+        };
+        let keystore = write("keystore.json", KEYSTORE)?;
+        let password_file = write("password", "t1-keystore-password\n")?;
+
+        let env = |entries: Vec<(&'static str, String)>| {
+            ExExConfig::from_env_with(move |key| match entries.iter().find(|(k, _)| *k == key) {
+                Some((_, value)) => Some(value.clone()),
+                None if key == "PREFUNDED_SECRET" => None,
+                None => test_env(key),
+            })
+        };
+        let config = env(vec![
+            ("L1_KEYSTORE", keystore.clone()),
+            ("L1_KEYSTORE_PASSWORD_FILE", password_file.clone()),
+        ])?;
+        assert_eq!(config.account(), address!("2c7536E3605D9C16a7a3D7b1898e529396a65c23"));
+
+        // A wrong password fails, as does a keystore along with a raw key
+        let wrong_password = write("wrong-password", "wrong")?;
+        let err = env(vec![
+            ("L1_KEYSTORE", keystore.clone()),
+            ("L1_KEYSTORE_PASSWORD_FILE", wrong_password),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("failed to decrypt keystore"), "{err}");
+        let err = env(vec![
+            ("L1_KEYSTORE", keystore),
+            ("L1_KEYSTORE_PASSWORD_FILE", password_file),
+            ("PREFUNDED_SECRET", TEST_SECRET.to_string()),
+        ])
+        .unwrap_err();
+        assert!(err.to_string().contains("mutually exclusive"), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exex_file_config() -> eyre::Result<()> {
+        let contract = Address::with_last_byte(1);
+        let counter = Address::with_last_byte(2);
+        let contents = format!(
+            r#"
+            l1_rpc = "http://l1.example:8545/"
+            l1_chain_id = 17000
+            state_root_contract = "{contract}"
+            counter_contracts = ["{counter}"]
+            retry_delay_ms = 250
+            max_fee_per_gas_cap = 1000000000
+            batch_size = 8
+            "#
+        );
+        let file = ExExFileConfig::parse(&contents)?;
+        assert_eq!(
+            file,
+            ExExFileConfig {
+                l1_rpc: Some(Url::parse("http://l1.example:8545")?),
+                l1_chain_id: Some(17000),
+                state_root_contract: Some(contract),
+                counter_contracts: Some(vec![counter]),
+                retry_delay_ms: Some(250),
+                max_fee_per_gas_cap: Some(1_000_000_000),
+                batch_size: Some(8),
+                ..Default::default()
+            }
+        );
+
+        // The config round-trips through TOML
+        assert_eq!(ExExFileConfig::parse(&toml::to_string(&file)?)?, file);
+
+        // The file overrides the environment, settings it doesn't set are read from the environment
+        let env = |key: &str| match key {
+            "L1_CHAIN_ID" => Some("1".to_string()),
+            "L1_BATCH_SIZE" => Some("2".to_string()),
+            "L1_CONFIRMATIONS" => Some("5".to_string()),
+            _ => test_env(key),
+        };
+        let config = ExExConfig::from_env_with(env)?.with_file(file);
+        assert_eq!(config.l1_rpc.as_str(), "http://l1.example:8545/");
+        assert_eq!(config.l1_chain_id, Some(17000));
+        assert_eq!(config.state_root_contract, contract);
+        assert_eq!(config.counter_contracts, vec![counter]);
+        assert_eq!(config.retry_base_delay, Duration::from_millis(250));
+        assert_eq!(config.max_fee_per_gas_cap, Some(1_000_000_000));
+        assert_eq!(config.batch_size, 8);
+        assert_eq!(config.confirmations, 5);
+        assert_eq!(config.fee_bump_timeout, Duration::from_millis(L1_FEE_BUMP_TIMEOUT_MS));
+
+        // A missing file is empty, an unknown setting is an error
+        let dir = tempfile::tempdir()?;
+        assert_eq!(ExExFileConfig::load(&dir.path().join(L1_CONFIG_FILE))?, Default::default());
+        assert_eq!(ExExFileConfig::parse("")?, Default::default());
+        assert!(ExExFileConfig::parse("batch_sise = 8").is_err());
+        assert!(ExExFileConfig::parse("batch_size = \"8\"").is_err());
+        // Like a section of reth's config
+        assert!(ExExFileConfig::parse("[stages]").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_l1_endpoint() -> eyre::Result<()> {
+        for url in ["http://localhost:8545", "https://l1.example.com/rpc"] {
+            let url = Url::parse(url)?;
+            assert_eq!(L1Endpoint::parse(&url)?, L1Endpoint::Http(url));
+        }
+        for url in ["ws://localhost:8546", "wss://l1.example.com/ws"] {
+            let url = Url::parse(url)?;
+            assert_eq!(L1Endpoint::parse(&url)?, L1Endpoint::Ws(url));
+        }
+        assert_eq!(
+            L1Endpoint::parse(&Url::parse("file:///tmp/geth.ipc")?)?,
+            L1Endpoint::Ipc(PathBuf::from("/tmp/geth.ipc"))
+        );
+
+        let err = L1Endpoint::parse(&Url::parse("ftp://localhost")?).unwrap_err();
+        assert!(err.to_string().contains("unsupported scheme ftp"), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_root_selector() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+        assert_eq!(config.state_root_selector()?, Selector::new(changeStateRootCall::SELECTOR));
+
+        // The legacy method has to exist and take a single `bytes` parameter
+        let legacy = ExExConfig { legacy_state_root: true, ..config.clone() };
+        assert_eq!(legacy.state_root_selector()?, Selector::new(changeStateRootCall::SELECTOR));
+        for method in ["commitRoot", "changeStateRootBatch"] {
+            let config = ExExConfig { state_root_method: method.to_string(), ..legacy.clone() };
+            let err = config.state_root_selector().unwrap_err();
+            assert!(err.to_string().contains(&format!("{method}(bytes) not found")), "{err}");
+        }
+
+        // An ABI loaded from a file only needs the methods the client calls
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("abi.json");
+        let function = |name: &str, inputs: Value| {
+            json!({
+                "type": "function",
+                "name": name,
+                "inputs": inputs,
+                "outputs": [],
+                "stateMutability": "nonpayable",
+            })
+        };
+        let at = function(
+            "changeStateRootAt",
+            json!([
+                { "name": "number_", "type": "uint256" },
+                { "name": "stateRoot_", "type": "bytes32" },
+            ]),
+        );
+        std::fs::write(&path, json!([at]).to_string())?;
+        let config = ExExConfig { state_root_abi: Some(path.clone()), ..config };
+        let err = config.state_root_selector().unwrap_err();
+        assert!(err.to_string().contains("changeStateRootBatch(uint256[],bytes32[])"), "{err}");
+        let single = ExExConfig { batch_size: 1, ..config.clone() };
+        assert_eq!(single.state_root_selector()?, Selector::new(changeStateRootCall::SELECTOR));
+
+        let batch = function(
+            "changeStateRootBatch",
+            json!([
+                { "name": "numbers_", "type": "uint256[]" },
+                { "name": "stateRoots_", "type": "bytes32[]" },
+            ]),
+        );
+        std::fs::write(&path, json!([at, batch]).to_string())?;
+        assert_eq!(config.state_root_selector()?, Selector::new(changeStateRootCall::SELECTOR));
+        let err = ExExConfig { legacy_state_root: true, ..config.clone() }
+            .state_root_selector()
+            .unwrap_err();
+        assert!(err.to_string().contains("changeStateRoot(bytes) not found"), "{err}");
+
+        std::fs::write(&path, "not an ABI")?;
+        let err = config.state_root_selector().unwrap_err();
+        assert!(err.to_string().contains(&path.display().to_string()), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_contract_abi_dir() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+        config.check_counter_abi()?;
+
+        // Both ABIs are read from the directory
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join(COUNTER_ABI_FILE), COUNTER_ABI)?;
+        std::fs::write(dir.path().join(STATE_ROOT_ABI_FILE), STATE_ROOT_ABI)?;
+        let config = ExExConfig { contract_abi_dir: Some(dir.path().to_path_buf()), ..config };
+        config.check_counter_abi()?;
+        assert_eq!(config.state_root_selector()?, Selector::new(changeStateRootCall::SELECTOR));
+
+        // Each of them is validated
+        std::fs::write(dir.path().join(COUNTER_ABI_FILE), "[]")?;
+        let err = config.check_counter_abi().unwrap_err();
+        assert!(err.to_string().contains("Incremented() not found"), "{err}");
+        std::fs::write(dir.path().join(STATE_ROOT_ABI_FILE), "[]")?;
+        let err = config.state_root_selector().unwrap_err();
+        assert!(err.to_string().contains("changeStateRootAt(uint256,bytes32) not found"), "{err}");
+
+        // An explicit state root contract ABI takes precedence over the one in the directory
+        let path = dir.path().join("abi.json");
+        std::fs::write(&path, STATE_ROOT_ABI)?;
+        let explicit = ExExConfig { state_root_abi: Some(path), ..config.clone() };
+        assert_eq!(explicit.state_root_selector()?, Selector::new(changeStateRootCall::SELECTOR));
+
+        // Both files have to exist
+        std::fs::remove_file(dir.path().join(COUNTER_ABI_FILE))?;
+        assert!(config.check_counter_abi().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_anchored_root() -> eyre::Result<()> {
+        let header = Header {
+            state_root: B256::random(),
+            receipts_root: B256::random(),
+            withdrawals_root: Some(B256::random()),
+            ..Default::default()
+        };
+        assert_eq!(AnchoredRoot::State.provider().root_for(&header), Some(header.state_root));
+        assert_eq!(AnchoredRoot::Receipts.provider().root_for(&header), Some(header.receipts_root));
+        assert_eq!(AnchoredRoot::Withdrawals.provider().root_for(&header), header.withdrawals_root);
+
+        // Pre-Shanghai blocks have no withdrawals root to anchor
+        let pre_shanghai = Header { withdrawals_root: None, ..header.clone() };
+        assert_eq!(HeaderWithdrawalsRoot.root_for(&pre_shanghai), None);
+        assert_eq!(HeaderReceiptsRoot.root_for(&pre_shanghai), Some(header.receipts_root));
+        let err = root_for(&HeaderWithdrawalsRoot, &pre_shanghai).unwrap_err();
+        assert!(err.to_string().contains("has no root to anchor"), "{err}");
+
+        assert_eq!("state".parse::<AnchoredRoot>()?, AnchoredRoot::State);
+        assert_eq!("receipts".parse::<AnchoredRoot>()?, AnchoredRoot::Receipts);
+        assert_eq!("withdrawals".parse::<AnchoredRoot>()?, AnchoredRoot::Withdrawals);
+        assert!("transactions".parse::<AnchoredRoot>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exex_args_resolve_config() -> eyre::Result<()> {
+        // The required settings are read from the process environment like by the node
+        set_test_env();
+        let dir = tempfile::tempdir()?;
+        let mut node = NodeConfig::test();
+        node.datadir.datadir = dir.path().to_path_buf().into();
+        std::fs::create_dir_all(node.datadir().data_dir())?;
+        std::fs::write(node.datadir().data_dir().join(L1_CONFIG_FILE), "batch_size = 8\n")?;
+
+        // The node's configuration suffices, without launching the node
+        let args = ExExArgs { dry_run: true, name: "t1-replica".to_string(), ..Default::default() };
+        let config = args.resolve_config(&node)?;
+        assert!(config.dry_run);
+        assert_eq!(config.batch_size, 8);
+        assert_eq!(config.local_chain_id, Some(node.chain.chain().id()));
+        assert_eq!(config.name, "t1-replica");
+
+        // The config file may be set explicitly, and isn't reth's config file
+        let config_path = dir.path().join("bridge.toml");
+        std::fs::write(&config_path, "batch_size = 4\n")?;
+        node.config = Some(dir.path().join("reth.toml"));
+        let args = ExExArgs { config: Some(config_path), ..Default::default() };
+        assert_eq!(args.resolve_config(&node)?.batch_size, 4);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,831 @@
+//! The state root contract on L1 and the calls anchoring state roots in it.
+
+use super::{
+    client::{rpc_timeout, Eip1559Fees, L1RpcTimeout, L1SubmitError},
+    config::ExExConfig,
+    signer::L1Signer,
+    AccountDiff, StateRootAbi, StorageDiff,
+};
+use alloy_consensus::{BlobTransactionSidecar, SidecarBuilder, SimpleCoder};
+use alloy_eips::BlockNumberOrTag;
+use alloy_json_abi::JsonAbi;
+use alloy_network::TransactionBuilder4844;
+use alloy_primitives::{b256, Address, BlockNumber, Bytes, Selector, TxHash, B256, U256};
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::{AccessList, FeeHistory, TransactionReceipt, TransactionRequest};
+use alloy_sol_types::{SolCall, SolValue};
+use alloy_transport::{BoxTransport, Transport, TransportErrorKind, TransportResult};
+use reth_execution_types::Chain;
+use std::{future::IntoFuture, time::Duration};
+use tracing::debug;
+
+/// Minimum fee increase, in percent, of a transaction replacing another one in the L1 mempool.
+const MIN_FEE_BUMP_PERCENT: f64 = 12.5;
+
+/// Number of L1 blocks the fee history is sampled from.
+const L1_FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Percentile of the priority fees paid in the sampled L1 blocks.
+const L1_FEE_HISTORY_PERCENTILE: f64 = 50.0;
+
+/// Storage slot of the implementation address of an EIP-1967 proxy,
+/// `keccak256("eip1967.proxy.implementation") - 1`.
+pub(super) const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+/// The state root contract on L1, anchoring the state roots sent to it by [`StateRootCall`]s.
+#[derive(Debug)]
+pub struct StateRootContract<T: Transport + Clone = BoxTransport> {
+    pub(super) address: Address,
+    provider: RootProvider<T>,
+    /// Selector of the method anchoring a single state root.
+    setter: Selector,
+    /// Selector and calldata prefix every call is sent with instead, see [`Self::with_dispatch`].
+    dispatch: Option<(Selector, Bytes)>,
+    /// Maximum time a single RPC call may take, see [`Self::with_rpc_timeout`].
+    rpc_timeout: Option<Duration>,
+    /// Value attached to every transaction, see [`Self::with_value`].
+    value: U256,
+}
+
+impl<T: Transport + Clone> StateRootContract<T> {
+    /// Creates the contract at the [`ExExConfig::state_root_contract`], called over the given
+    /// provider, with the state root method in the given ABI loaded from `source`, see
+    /// [`ExExConfig::load_state_root_abi`]. The ABI isn't used with an
+    /// [`ExExConfig::selector_override`], which is dispatched to instead.
+    ///
+    /// Fails for the zero address and if the ABI lacks a method the client calls, see
+    /// [`ExExConfig::state_root_selector_in`], naming the address and the ABI file.
+    pub fn new(
+        provider: RootProvider<T>,
+        config: &ExExConfig,
+        abi: &JsonAbi,
+        source: &str,
+    ) -> eyre::Result<Self> {
+        let address = config.state_root_contract;
+        if address.is_zero() {
+            eyre::bail!("invalid state root contract address {address}: the zero address")
+        }
+        let contract = Self {
+            address,
+            provider,
+            setter: Selector::new(StateRootAbi::changeStateRootCall::SELECTOR),
+            dispatch: None,
+            rpc_timeout: None,
+            value: U256::ZERO,
+        };
+        Ok(match config.selector_override {
+            Some(selector) => contract.with_dispatch(selector, config.calldata_prefix.clone()),
+            None => contract.with_setter(
+                config
+                    .state_root_selector_in(abi, source)
+                    .map_err(|err| eyre::eyre!("state root contract {address}: {err}"))?,
+            ),
+        })
+    }
+
+    /// Sets the selector of the method anchoring a single state root, which takes it as its only
+    /// `bytes` parameter, see [`ExExConfig::state_root_selector`].
+    pub const fn with_setter(mut self, setter: Selector) -> Self {
+        self.setter = setter;
+        self
+    }
+
+    /// Calls the method of the given selector, followed by the given prefix, instead of the
+    /// method of every [`StateRootCall`], e.g. to go through the dispatcher of a proxy contract.
+    ///
+    /// The ABI encoded parameters of the call follow the prefix.
+    pub fn with_dispatch(mut self, selector: Selector, prefix: Bytes) -> Self {
+        self.dispatch = Some((selector, prefix));
+        self
+    }
+
+    /// Fails every RPC call, including the signing of transactions, that takes longer than the
+    /// given timeout with an [`L1RpcTimeout`] error. Zero waits indefinitely.
+    pub const fn with_rpc_timeout(mut self, timeout: Duration) -> Self {
+        self.rpc_timeout = if timeout.is_zero() { None } else { Some(timeout) };
+        self
+    }
+
+    /// Attaches the given value in wei to every transaction, including the estimation of its gas
+    /// and access list, see [`ExExConfig::submit_value`].
+    pub const fn with_value(mut self, value: U256) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Runs the given RPC call, see [`Self::with_rpc_timeout`].
+    async fn timed<R>(
+        &self,
+        call: impl IntoFuture<Output = TransportResult<R>>,
+    ) -> TransportResult<R> {
+        let Some(timeout) = self.rpc_timeout else { return call.await };
+        tokio::time::timeout(timeout, call)
+            .await
+            .unwrap_or_else(|_| Err(TransportErrorKind::custom(L1RpcTimeout(timeout))))
+    }
+
+    /// Sends a transaction anchoring the given state root with the method of the setter, see
+    /// [`Self::with_setter`].
+    pub async fn update_state_root(
+        &self,
+        signer: &dyn L1Signer,
+        state_root: B256,
+        params: TxParams,
+    ) -> TransportResult<TxHash> {
+        self.send_transaction(signer, self.setter_calldata(state_root), params).await
+    }
+
+    /// Sends a `changeStateRootAt` transaction anchoring the state root of the given block
+    /// number.
+    pub async fn update_state_root_at(
+        &self,
+        signer: &dyn L1Signer,
+        number: BlockNumber,
+        state_root: B256,
+        params: TxParams,
+    ) -> TransportResult<TxHash> {
+        let number_ = U256::from(number);
+        let call = StateRootAbi::changeStateRootAtCall { number_, stateRoot_: state_root };
+        self.send_transaction(signer, call.abi_encode(), params).await
+    }
+
+    /// Returns the calldata of the given [`StateRootCall`], anchoring a legacy single state root
+    /// with the setter of the contract.
+    ///
+    /// With [`Self::with_dispatch`], the selector of the call is replaced by the dispatch
+    /// selector and prefix.
+    pub fn calldata(&self, call: &StateRootCall) -> Vec<u8> {
+        let calldata = match call {
+            StateRootCall::Single(state_root) => self.setter_calldata(*state_root),
+            StateRootCall::At(..) |
+            StateRootCall::Batch(_) |
+            StateRootCall::WithDiff(..) |
+            StateRootCall::BlobDiff(..) => call.calldata(),
+        };
+        let Some((selector, prefix)) = &self.dispatch else { return calldata };
+        [selector.as_slice(), prefix.as_ref(), &calldata[4..]].concat()
+    }
+
+    /// Returns the calldata of the setter anchoring the given state root, which takes it as
+    /// dynamic `bytes` of exactly 32 bytes.
+    fn setter_calldata(&self, state_root: B256) -> Vec<u8> {
+        let state_root = Bytes::copy_from_slice(state_root.as_slice());
+        let call = StateRootAbi::changeStateRootCall { stateRoot_: state_root };
+        let mut calldata = self.setter.to_vec();
+        call.abi_encode_raw(&mut calldata);
+        calldata
+    }
+
+    /// Sends a `changeStateRootBatch` transaction anchoring the state roots of the given block
+    /// numbers, in order.
+    pub async fn update_state_roots_batch(
+        &self,
+        signer: &dyn L1Signer,
+        numbers: Vec<U256>,
+        state_roots: Vec<B256>,
+        params: TxParams,
+    ) -> TransportResult<TxHash> {
+        let call =
+            StateRootAbi::changeStateRootBatchCall { numbers_: numbers, stateRoots_: state_roots };
+        self.send_transaction(signer, call.abi_encode(), params).await
+    }
+
+    /// Sends the transaction of the given [`StateRootCall`].
+    pub async fn send(
+        &self,
+        signer: &dyn L1Signer,
+        call: &StateRootCall,
+        params: TxParams,
+    ) -> TransportResult<TxHash> {
+        if let StateRootCall::BlobDiff(_, _, diff) = call {
+            let sidecar = blob_sidecar(diff).map_err(TransportErrorKind::custom)?;
+            return self.send_blob_transaction(signer, self.calldata(call), sidecar, params).await
+        }
+        if self.dispatch.is_some() {
+            return self.send_transaction(signer, self.calldata(call), params).await
+        }
+        match call {
+            StateRootCall::Single(state_root) => {
+                self.update_state_root(signer, *state_root, params).await
+            }
+            StateRootCall::At(number, state_root) => {
+                self.update_state_root_at(signer, *number, *state_root, params).await
+            }
+            StateRootCall::Batch(roots) => {
+                let call = batch_call(roots);
+                self.update_state_roots_batch(signer, call.numbers_, call.stateRoots_, params).await
+            }
+            StateRootCall::WithDiff(..) | StateRootCall::BlobDiff(..) => {
+                self.send_transaction(signer, call.calldata(), params).await
+            }
+        }
+    }
+
+    /// Signs a transaction calling the contract with the given input and sends it.
+    ///
+    /// Without [`TxParams::fees`] the transaction is priced at the legacy gas price of the L1.
+    async fn send_transaction(
+        &self,
+        signer: &dyn L1Signer,
+        input: Vec<u8>,
+        params: TxParams,
+    ) -> TransportResult<TxHash> {
+        let request = self.transaction_request(signer, input, params).await?;
+        self.sign_and_send(signer, request).await
+    }
+
+    /// Signs an EIP-4844 transaction calling the contract with the given input and carrying the
+    /// blobs of the given sidecar, and sends it.
+    ///
+    /// Fails without [`TxParams::fees`] and [`TxParams::max_fee_per_blob_gas`], as blob
+    /// transactions can't be priced at the legacy gas price.
+    async fn send_blob_transaction(
+        &self,
+        signer: &dyn L1Signer,
+        input: Vec<u8>,
+        sidecar: BlobTransactionSidecar,
+        params: TxParams,
+    ) -> TransportResult<TxHash> {
+        let (Some(max_fee_per_blob_gas), Some(_)) = (params.max_fee_per_blob_gas, params.fees)
+        else {
+            return Err(TransportErrorKind::custom_str("blob transactions require EIP-1559 fees"))
+        };
+        let request = self
+            .transaction_request(signer, input, params)
+            .await?
+            .with_blob_sidecar(sidecar)
+            .with_max_fee_per_blob_gas(max_fee_per_blob_gas);
+        self.sign_and_send(signer, request).await
+    }
+
+    /// Returns the request of a transaction calling the contract with the given input, see
+    /// [`Self::send_transaction`].
+    async fn transaction_request(
+        &self,
+        signer: &dyn L1Signer,
+        input: Vec<u8>,
+        params: TxParams,
+    ) -> TransportResult<TransactionRequest> {
+        let request = TransactionRequest::default()
+            .with_from(signer.address())
+            .with_to(self.address)
+            .with_input(input)
+            .with_value(self.value)
+            .with_chain_id(self.timed(self.provider.get_chain_id()).await?)
+            .with_nonce(params.nonce)
+            .with_gas_limit(params.gas);
+        let request = match params.access_list {
+            Some(access_list) => request.with_access_list(access_list),
+            None => request,
+        };
+        let request = match params.fees {
+            Some(fees) => request
+                .with_max_fee_per_gas(fees.max_fee_per_gas)
+                .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas),
+            None => match params.gas_price {
+                Some(gas_price) => request.with_gas_price(gas_price),
+                None => request.with_gas_price(self.timed(self.provider.get_gas_price()).await?),
+            },
+        };
+        Ok(request)
+    }
+
+    /// Signs the given transaction and sends it.
+    async fn sign_and_send(
+        &self,
+        signer: &dyn L1Signer,
+        request: TransactionRequest,
+    ) -> TransportResult<TxHash> {
+        let raw = self.timed(signer.sign_transaction(request)).await?;
+        let pending = self.timed(self.provider.send_raw_transaction(&raw)).await?;
+        Ok(*pending.tx_hash())
+    }
+
+    /// Estimates the gas used by the transaction of the given [`StateRootCall`] sent from the
+    /// given account.
+    pub async fn estimate_gas(
+        &self,
+        account: Address,
+        call: &StateRootCall,
+    ) -> TransportResult<u64> {
+        let request = TransactionRequest::default()
+            .with_from(account)
+            .with_to(self.address)
+            .with_input(self.calldata(call))
+            .with_value(self.value);
+        self.timed(self.provider.estimate_gas(&request)).await
+    }
+
+    /// Creates the access list of the transaction of the given [`StateRootCall`] sent from the
+    /// given account with `eth_createAccessList`.
+    pub async fn create_access_list(
+        &self,
+        account: Address,
+        call: &StateRootCall,
+    ) -> TransportResult<AccessList> {
+        let request = TransactionRequest::default()
+            .with_from(account)
+            .with_to(self.address)
+            .with_input(self.calldata(call))
+            .with_value(self.value);
+        let result = self.timed(self.provider.create_access_list(&request)).await?;
+        if let Some(err) = result.error {
+            return Err(TransportErrorKind::custom_str(&err))
+        }
+        Ok(result.access_list)
+    }
+
+    /// Returns the latest state root held by the contract, or `None` if none was anchored yet.
+    ///
+    /// The contract holds the state root as dynamic `bytes`, which is an error unless it's empty
+    /// or exactly 32 bytes long.
+    pub async fn current_state_root(&self) -> TransportResult<Option<B256>> {
+        let output = self.call(StateRootAbi::stateRootCall {}.abi_encode()).await?;
+        let state_root = StateRootAbi::stateRootCall::abi_decode_returns(&output, true)
+            .map_err(TransportErrorKind::custom)?
+            ._0;
+        if state_root.is_empty() {
+            return Ok(None)
+        }
+        let state_root = B256::try_from(state_root.as_ref()).map_err(|_| {
+            TransportErrorKind::custom_str(&format!(
+                "state root held by the contract has {} bytes, expected 32",
+                state_root.len()
+            ))
+        })?;
+        Ok(Some(state_root))
+    }
+
+    /// Returns the state root the contract holds for the given block number, which is zero if
+    /// none was anchored.
+    ///
+    /// Only supported by contracts holding the state root of every anchored block number, see
+    /// [`ExExConfig::state_roots_per_height`].
+    pub async fn state_root_at(&self, number: BlockNumber) -> TransportResult<B256> {
+        let call = StateRootAbi::stateRootsCall { _0: U256::from(number) };
+        let output = self.call(call.abi_encode()).await?;
+        let state_root = StateRootAbi::stateRootsCall::abi_decode_returns(&output, true)
+            .map_err(TransportErrorKind::custom)?;
+        Ok(state_root._0)
+    }
+
+    /// Calls the contract with the given input without sending a transaction.
+    async fn call(&self, input: Vec<u8>) -> TransportResult<Bytes> {
+        let request = TransactionRequest::default().with_to(self.address).with_input(input);
+        self.timed(self.provider.call(&request)).await
+    }
+
+    /// Returns the chain id of the L1.
+    pub async fn chain_id(&self) -> TransportResult<u64> {
+        self.timed(self.provider.get_chain_id()).await
+    }
+
+    /// Returns the current legacy gas price of the L1.
+    pub async fn gas_price(&self) -> TransportResult<u128> {
+        self.timed(self.provider.get_gas_price()).await
+    }
+
+    /// Returns the blob base fee of the next L1 block.
+    pub async fn blob_base_fee(&self) -> TransportResult<u128> {
+        self.timed(self.provider.get_blob_base_fee()).await
+    }
+
+    /// Returns the deployed code of the contract, empty if there's no contract at its address.
+    pub async fn code(&self) -> TransportResult<Bytes> {
+        self.code_at(self.address).await
+    }
+
+    /// Returns the deployed code at the given address, empty if there's no contract.
+    pub async fn code_at(&self, address: Address) -> TransportResult<Bytes> {
+        self.timed(self.provider.get_code_at(address)).await
+    }
+
+    /// Returns the implementation address of the contract as an EIP-1967 proxy, read from its
+    /// [`EIP1967_IMPLEMENTATION_SLOT`], or `None` if the slot is empty.
+    pub async fn implementation(&self) -> TransportResult<Option<Address>> {
+        let slot = U256::from_be_bytes(EIP1967_IMPLEMENTATION_SLOT.0);
+        let word = self.timed(self.provider.get_storage_at(self.address, slot)).await?;
+        let implementation = Address::from_word(word.into());
+        Ok((!implementation.is_zero()).then_some(implementation))
+    }
+
+    /// Returns the balance of the given account in wei.
+    pub async fn balance(&self, account: Address) -> TransportResult<U256> {
+        self.timed(self.provider.get_balance(account)).await
+    }
+
+    /// Returns the receipt of the given transaction, or `None` if it isn't included in the L1,
+    /// e.g. because it was reorged out.
+    pub async fn transaction_receipt(
+        &self,
+        tx_hash: TxHash,
+    ) -> TransportResult<Option<TransactionReceipt>> {
+        self.timed(self.provider.get_transaction_receipt(tx_hash)).await
+    }
+
+    /// Returns the number of the latest L1 block.
+    pub async fn block_number(&self) -> TransportResult<u64> {
+        self.timed(self.provider.get_block_number()).await
+    }
+
+    /// Returns the nonce of the next transaction of the given account, including transactions
+    /// pending in the L1 mempool.
+    pub async fn pending_nonce(&self, account: Address) -> TransportResult<u64> {
+        self.timed(self.provider.get_transaction_count(account).pending()).await
+    }
+
+    /// Returns the fee history of the latest L1 blocks, sampling the priority fees at
+    /// [`L1_FEE_HISTORY_PERCENTILE`].
+    pub async fn fee_history(&self) -> TransportResult<FeeHistory> {
+        self.timed(self.provider.get_fee_history(
+            L1_FEE_HISTORY_BLOCKS,
+            BlockNumberOrTag::Latest,
+            &[L1_FEE_HISTORY_PERCENTILE],
+        ))
+        .await
+    }
+
+    /// Polls the receipt of the given transaction every `poll_interval` until it's included in a
+    /// block that is `confirmations` deep, returning an error if the transaction reverted.
+    ///
+    /// A poll that times out, see [`Self::with_rpc_timeout`], is repeated at the next interval.
+    pub async fn wait_for_confirmation(
+        &self,
+        tx_hash: TxHash,
+        confirmations: u64,
+        poll_interval: Duration,
+    ) -> Result<TransactionReceipt, L1SubmitError> {
+        loop {
+            match self.poll_confirmation(tx_hash, confirmations).await {
+                Ok(Some(receipt)) => {
+                    if !receipt.status() {
+                        return Err(L1SubmitError::TxReverted(tx_hash))
+                    }
+                    return Ok(receipt)
+                }
+                Ok(None) => {}
+                Err(err) if rpc_timeout(&err).is_some() => {
+                    debug!(
+                        target: "t1::bridge",
+                        %tx_hash,
+                        %err,
+                        "Polling the L1 transaction receipt timed out"
+                    );
+                }
+                Err(err) => return Err(err.into()),
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Returns the receipt of the given transaction if it's included in a block that is
+    /// `confirmations` deep.
+    async fn poll_confirmation(
+        &self,
+        tx_hash: TxHash,
+        confirmations: u64,
+    ) -> TransportResult<Option<TransactionReceipt>> {
+        let Some(receipt) = self.timed(self.provider.get_transaction_receipt(tx_hash)).await?
+        else {
+            return Ok(None)
+        };
+        let Some(included) = receipt.block_number else { return Ok(None) };
+        let latest = self.block_number().await?;
+        let depth = (latest + 1).saturating_sub(included);
+        Ok((depth >= confirmations).then_some(receipt))
+    }
+}
+
+/// A call anchoring state roots in the [`StateRootContract`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateRootCall {
+    /// `changeStateRoot`, anchoring a single state root without its block number, see
+    /// [`ExExConfig::legacy_state_root`].
+    Single(B256),
+    /// `changeStateRootAt`, anchoring the state root of a single block number.
+    At(BlockNumber, B256),
+    /// `changeStateRootBatch`, anchoring the state roots of multiple blocks in order.
+    Batch(Vec<(BlockNumber, B256)>),
+    /// `changeStateRootWithDiff`, anchoring the state root of a single block number along with
+    /// the encoded [`StateDiff`] leading to it, see [`ExExConfig::state_diffs`].
+    WithDiff(BlockNumber, B256, Bytes),
+    /// `changeStateRootAt`, anchoring the state root of a single block number, with the encoded
+    /// [`StateDiff`] leading to it in the blobs of an EIP-4844 transaction, see
+    /// [`ExExConfig::state_diff_blobs`] and [`blob_sidecar`].
+    BlobDiff(BlockNumber, B256, Bytes),
+}
+
+impl StateRootCall {
+    /// Returns the call anchoring the state roots of the given blocks, in order.
+    ///
+    /// A single state root is anchored with `changeStateRootAt`, or with `changeStateRoot` if
+    /// `legacy` is set, multiple with `changeStateRootBatch`.
+    pub fn new(roots: &[(BlockNumber, B256)], legacy: bool) -> eyre::Result<Self> {
+        match roots {
+            [] => eyre::bail!("no state roots to submit"),
+            [(_, state_root)] if legacy => Ok(Self::Single(*state_root)),
+            [(number, state_root)] => Ok(Self::At(*number, *state_root)),
+            roots => Ok(Self::Batch(roots.to_vec())),
+        }
+    }
+
+    /// Returns the state root of the last block of the call.
+    pub fn last_state_root(&self) -> B256 {
+        match self {
+            Self::Single(state_root) |
+            Self::At(_, state_root) |
+            Self::WithDiff(_, state_root, _) |
+            Self::BlobDiff(_, state_root, _) => *state_root,
+            Self::Batch(roots) => roots.last().map_or(B256::ZERO, |(_, state_root)| *state_root),
+        }
+    }
+
+    /// Returns the ABI encoded calldata of the call.
+    pub fn calldata(&self) -> Vec<u8> {
+        match self {
+            Self::Single(state_root) => StateRootAbi::changeStateRootCall {
+                stateRoot_: Bytes::copy_from_slice(state_root.as_slice()),
+            }
+            .abi_encode(),
+            Self::At(number, state_root) | Self::BlobDiff(number, state_root, _) => {
+                StateRootAbi::changeStateRootAtCall {
+                    number_: U256::from(*number),
+                    stateRoot_: *state_root,
+                }
+                .abi_encode()
+            }
+            Self::Batch(roots) => batch_call(roots).abi_encode(),
+            Self::WithDiff(number, state_root, diff) => StateRootAbi::changeStateRootWithDiffCall {
+                number_: U256::from(*number),
+                stateRoot_: *state_root,
+                diff_: diff.clone(),
+            }
+            .abi_encode(),
+        }
+    }
+}
+
+/// Returns the EIP-4844 sidecar of the blobs carrying the given data, e.g. an encoded
+/// [`StateDiff`], see [`StateRootCall::BlobDiff`].
+///
+/// The data is packed into the blobs with the [`SimpleCoder`], and the versioned hashes of the
+/// transaction are derived from the KZG commitments of the sidecar.
+pub fn blob_sidecar(data: &[u8]) -> eyre::Result<BlobTransactionSidecar> {
+    SidecarBuilder::<SimpleCoder>::from_slice(data)
+        .build()
+        .map_err(|err| eyre::eyre!("failed to build the blob sidecar: {err:?}"))
+}
+
+/// Changes of the accounts and storage over a committed chain, which lead from the state root of
+/// its parent to the state root of its tip, see [`ExExConfig::state_diffs`].
+///
+/// Accounts are ordered by address and their storage slots by key. Only the changed storage
+/// slots are included, with their value at the tip.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff(pub Vec<AccountDiff>);
+
+impl StateDiff {
+    /// Returns the diff of the state changes in the execution outcome of the given chain.
+    pub fn from_chain(chain: &Chain) -> Self {
+        let mut accounts = chain
+            .execution_outcome()
+            .bundle
+            .state
+            .iter()
+            .filter(|(_, account)| {
+                account.is_info_changed() || account.storage.values().any(|slot| slot.is_changed())
+            })
+            .map(|(address, account)| {
+                let mut storage = account
+                    .storage
+                    .iter()
+                    .filter(|(_, slot)| slot.is_changed())
+                    .map(|(slot, value)| StorageDiff {
+                        slot: B256::from(*slot),
+                        value: B256::from(value.present_value),
+                    })
+                    .collect::<Vec<_>>();
+                storage.sort_unstable_by_key(|diff| diff.slot);
+                let (nonce, balance, code_hash) =
+                    account.info.as_ref().map_or((0, U256::ZERO, B256::ZERO), |info| {
+                        (info.nonce, info.balance, info.code_hash)
+                    });
+                AccountDiff {
+                    account: *address,
+                    destroyed: account.info.is_none(),
+                    nonce,
+                    balance,
+                    codeHash: code_hash,
+                    storage,
+                }
+            })
+            .collect::<Vec<_>>();
+        accounts.sort_unstable_by_key(|diff| diff.account);
+        Self(accounts)
+    }
+
+    /// Returns the ABI encoding of the diff as an `AccountDiff[]`.
+    pub fn encode(&self) -> Bytes {
+        self.0.abi_encode().into()
+    }
+}
+
+/// Splits the block numbers and state roots of a batch into a `changeStateRootBatch` call.
+fn batch_call(roots: &[(BlockNumber, B256)]) -> StateRootAbi::changeStateRootBatchCall {
+    let (numbers_, state_roots) =
+        roots.iter().map(|(number, state_root)| (U256::from(*number), *state_root)).unzip();
+    StateRootAbi::changeStateRootBatchCall { numbers_, stateRoots_: state_roots }
+}
+
+/// Parameters of a transaction sent to the [`StateRootContract`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxParams {
+    /// Nonce of the transaction.
+    pub nonce: u64,
+    /// Gas limit of the transaction.
+    pub gas: u64,
+    /// EIP-1559 fees of the transaction, or `None` for legacy gas pricing.
+    pub fees: Option<Eip1559Fees>,
+    /// Legacy gas price of the transaction without [`Self::fees`], or `None` for the current gas
+    /// price of the L1.
+    pub gas_price: Option<u128>,
+    /// EIP-2930 access list of the transaction, if any.
+    pub access_list: Option<AccessList>,
+    /// Max fee per blob gas of a blob transaction, see [`StateRootCall::BlobDiff`].
+    pub max_fee_per_blob_gas: Option<u128>,
+}
+
+impl TxParams {
+    /// Returns the maximum fee per gas the transaction pays, if known.
+    pub fn max_fee_per_gas(&self) -> Option<u128> {
+        self.fees.map(|fees| fees.max_fee_per_gas).or(self.gas_price)
+    }
+
+    /// Returns the params of a transaction replacing this one in the L1 mempool, with the same
+    /// nonce and the fees raised by `percent`, at least by [`MIN_FEE_BUMP_PERCENT`].
+    pub fn bumped(&self, percent: f64) -> Self {
+        let factor = 1.0 + percent.max(MIN_FEE_BUMP_PERCENT) / 100.0;
+        let bump = |fee: u128| ((fee as f64 * factor).ceil() as u128).max(fee + 1);
+        Self {
+            fees: self.fees.map(|fees| Eip1559Fees {
+                max_fee_per_gas: bump(fees.max_fee_per_gas),
+                max_priority_fee_per_gas: bump(fees.max_priority_fee_per_gas),
+            }),
+            gas_price: self.gas_price.map(bump),
+            max_fee_per_blob_gas: self.max_fee_per_blob_gas.map(bump),
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        client::L1Client,
+        config::L1_CONFIRM_POLL_INTERVAL_MS,
+        test_utils::{test_env, MockTransport, STATE_ROOT_CONTRACT_ADDRESS},
+        StateRootAbi::{changeStateRootAtCall, changeStateRootBatchCall, changeStateRootCall},
+    };
+    use alloy_rpc_client::RpcClient;
+
+    #[tokio::test]
+    async fn test_wait_for_confirmation() -> eyre::Result<()> {
+        let provider = RootProvider::new(RpcClient::new(MockTransport::default(), false));
+        let config = ExExConfig::from_env_with(test_env)?;
+        let (abi, source) = config.load_state_root_abi()?;
+        let contract = StateRootContract::new(provider, &config, &abi, &source)?;
+
+        // The mocked receipt is included in the latest block
+        let interval = Duration::from_millis(L1_CONFIRM_POLL_INTERVAL_MS);
+        let receipt = contract.wait_for_confirmation(B256::repeat_byte(0x11), 1, interval).await?;
+        assert!(receipt.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_current_state_root() -> eyre::Result<()> {
+        let config = ExExConfig::from_env_with(test_env)?;
+        let (abi, source) = config.load_state_root_abi()?;
+        let contract = |anchored: &[u8]| {
+            let anchored = Bytes::copy_from_slice(anchored);
+            let output = StateRootAbi::stateRootCall::abi_encode_returns(&(anchored,));
+            let provider =
+                RootProvider::new(RpcClient::new(MockTransport::with_call_output(output), false));
+            StateRootContract::new(provider, &config, &abi, &source)
+        };
+
+        let state_root = B256::random();
+        assert_eq!(contract(state_root.as_slice())?.current_state_root().await?, Some(state_root));
+        assert_eq!(contract(&[])?.current_state_root().await?, None);
+
+        // A state root that isn't 32 bytes long is an error instead of a mismatch
+        let err = contract(&state_root[..31])?.current_state_root().await.unwrap_err();
+        assert!(err.to_string().contains("has 31 bytes, expected 32"), "{err}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_state_root_contract_bad_address() -> eyre::Result<()> {
+        let config = ExExConfig {
+            state_root_contract: Address::ZERO,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let (abi, source) = config.load_state_root_abi()?;
+        let provider = RootProvider::new(RpcClient::new(MockTransport::default(), false));
+        let err = StateRootContract::new(provider, &config, &abi, &source).unwrap_err();
+        assert!(err.to_string().contains(&Address::ZERO.to_string()), "{err}");
+
+        // The client is created with the contract
+        let err = L1Client::with_transport(config, MockTransport::default()).await.unwrap_err();
+        assert!(err.to_string().contains("invalid state root contract address"), "{err}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_state_root_contract_bad_abi() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("abi.json");
+        let config = ExExConfig {
+            state_root_abi: Some(path.clone()),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let address = STATE_ROOT_CONTRACT_ADDRESS.to_string();
+        let file = path.display().to_string();
+
+        // A missing ABI file
+        let err =
+            L1Client::with_transport(config.clone(), MockTransport::default()).await.unwrap_err();
+        assert!(err.to_string().contains(&address), "{err}");
+        assert!(err.to_string().contains(&file), "{err}");
+
+        // A malformed one
+        std::fs::write(&path, "not an ABI")?;
+        let err =
+            L1Client::with_transport(config.clone(), MockTransport::default()).await.unwrap_err();
+        assert!(err.to_string().contains(&address), "{err}");
+        assert!(err.to_string().contains(&file), "{err}");
+
+        // One without the methods the client calls
+        std::fs::write(&path, "[]")?;
+        let (abi, source) = config.load_state_root_abi()?;
+        let provider = RootProvider::new(RpcClient::new(MockTransport::default(), false));
+        let err = StateRootContract::new(provider, &config, &abi, &source).unwrap_err();
+        assert!(err.to_string().contains(&address), "{err}");
+        assert!(err.to_string().contains(&file), "{err}");
+        assert!(err.to_string().contains("changeStateRootAt(uint256,bytes32) not found"), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tx_params_bumped() {
+        let params = TxParams {
+            nonce: 3,
+            gas: 21_000,
+            fees: Some(Eip1559Fees { max_fee_per_gas: 100, max_priority_fee_per_gas: 10 }),
+            gas_price: None,
+            access_list: None,
+            max_fee_per_blob_gas: None,
+        };
+
+        // The bump is at least the minimum accepted by the L1 mempool
+        let bumped = params.bumped(5.0);
+        assert_eq!(
+            bumped.fees,
+            Some(Eip1559Fees { max_fee_per_gas: 113, max_priority_fee_per_gas: 12 })
+        );
+        assert_eq!((bumped.nonce, bumped.gas), (3, 21_000));
+
+        let params = TxParams { fees: None, gas_price: Some(1), ..params };
+        assert_eq!(params.bumped(50.0).gas_price, Some(2));
+
+        // The blob fee of a blob transaction is bumped along with the other fees
+        let params = TxParams { max_fee_per_blob_gas: Some(10), ..params };
+        assert_eq!(params.bumped(50.0).max_fee_per_blob_gas, Some(15));
+    }
+
+    #[test]
+    fn test_state_root_call_calldata() -> eyre::Result<()> {
+        let roots = vec![(1, B256::random()), (2, B256::random())];
+
+        let calldata = StateRootCall::Single(roots[0].1).calldata();
+        let call = changeStateRootCall::abi_decode(&calldata, true)?;
+        assert_eq!(call.stateRoot_.as_ref(), roots[0].1.as_slice());
+
+        let calldata = StateRootCall::At(roots[0].0, roots[0].1).calldata();
+        let call = changeStateRootAtCall::abi_decode(&calldata, true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(1), roots[0].1));
+
+        let calldata = StateRootCall::Batch(roots.clone()).calldata();
+        let call = changeStateRootBatchCall::abi_decode(&calldata, true)?;
+        assert_eq!(call.numbers_, vec![U256::from(1), U256::from(2)]);
+        assert_eq!(call.stateRoots_, vec![roots[0].1, roots[1].1]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+//! Decoding of the [`CounterContractEvents`] emitted in a [`Chain`] of blocks.
+
+use super::{CounterContract::CounterContractEvents, L1Metrics};
+use alloy_primitives::Address;
+use alloy_sol_types::SolEventInterface;
+use reth_execution_types::Chain;
+use reth_primitives::{Receipt, SealedBlockWithSenders, TransactionSigned};
+use std::cell::Cell;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// A [`CounterContractEvents`] with the block and transaction it was emitted in, and the address of
+/// the Counter contract that emitted it.
+pub type RollupEvent<'a> =
+    (&'a SealedBlockWithSenders, &'a TransactionSigned, Address, CounterContractEvents);
+
+/// Decodes the [`CounterContractEvents`] emitted in the given chain by any of the Counter contracts
+/// at `contracts`, in the order they were emitted, tagged with the address of the contract that
+/// emitted them.
+///
+/// Logs of other contracts are ignored. A transaction without a receipt, e.g. if receipts are
+/// pruned, is skipped with a warning.
+///
+/// Returns an error if any log of the Counter contracts can't be decoded, after logging every
+/// failure.
+///
+/// # Example
+///
+/// Decode the events of the committed chains in another ExEx, with the `l1-bridge` feature.
+///
+/// ```
+/// use alloy_primitives::Address;
+/// use reth::bridge::decode_rollup_events;
+/// use reth_execution_types::Chain;
+///
+/// let contracts = [Address::with_last_byte(1)];
+/// let events = decode_rollup_events(&Chain::default(), &contracts)?;
+/// assert!(events.is_empty());
+/// # Ok::<_, eyre::Report>(())
+/// ```
+pub fn decode_rollup_events<'a>(
+    chain: &'a Chain,
+    contracts: &[Address],
+) -> eyre::Result<Vec<RollupEvent<'a>>> {
+    decode_blocks(chain.blocks_and_receipts(), contracts).into_result()
+}
+
+/// Decode chain of blocks into a flattened list of receipt logs, filter only transactions to the
+/// Counter contracts at `counter_contracts` and extract [`CounterContractEvents`], tagged with the
+/// address of the contract that emitted them.
+///
+/// The same as [`decode_rollup_events`], but for the metrics.
+///
+/// The time it took and the number of decoded blocks, scanned logs and decoded events are
+/// recorded in the [`L1Metrics`] and logged. A chain without any Counter events is logged
+/// explicitly, to tell a missing event apart from a failed submission.
+pub(super) fn decode_chain_into_rollup_events<'a>(
+    chain: &'a Chain,
+    counter_contracts: &[Address],
+    metrics: &L1Metrics,
+) -> eyre::Result<Vec<RollupEvent<'a>>> {
+    let started_at = Instant::now();
+
+    let decoded = decode_blocks(chain.blocks_and_receipts(), counter_contracts);
+
+    let (blocks, scanned, elapsed) = (decoded.blocks, decoded.scanned, started_at.elapsed());
+    let events = decoded.events.len();
+    metrics.decode_duration_seconds.record(elapsed);
+    metrics.decoded_blocks_total.increment(blocks);
+    metrics.scanned_logs_total.increment(scanned);
+    metrics.decoded_events_total.increment(events as u64);
+    debug!(
+        target: "t1::bridge",
+        blocks,
+        logs = scanned,
+        events,
+        ?elapsed,
+        "Decoded committed chain"
+    );
+    if events == 0 && decoded.failed == 0 {
+        debug!(
+            target: "t1::bridge",
+            blocks,
+            logs = scanned,
+            contracts = ?counter_contracts,
+            "No Counter events in the committed chain"
+        );
+    }
+
+    decoded.into_result()
+}
+
+/// The [`RollupEvent`]s decoded from blocks, with the number of blocks and logs scanned for them.
+struct DecodedBlocks<'a> {
+    events: Vec<RollupEvent<'a>>,
+    blocks: u64,
+    scanned: u64,
+    /// Number of logs of the Counter contracts that couldn't be decoded.
+    failed: usize,
+}
+
+impl<'a> DecodedBlocks<'a> {
+    /// Returns the decoded events, or an error if any log couldn't be decoded.
+    fn into_result(self) -> eyre::Result<Vec<RollupEvent<'a>>> {
+        if self.failed > 0 {
+            eyre::bail!("failed to decode {} Counter events", self.failed)
+        }
+        Ok(self.events)
+    }
+}
+
+/// Decodes the [`CounterContractEvents`] of the Counter contracts at `contracts` from the given
+/// blocks and their receipts.
+///
+/// Transactions are paired with the receipt at the same index of their block. A transaction
+/// without a receipt is skipped instead of being paired with the receipt of a later transaction.
+fn decode_blocks<'a>(
+    blocks: impl Iterator<Item = (&'a SealedBlockWithSenders, &'a Vec<Option<Receipt>>)>,
+    contracts: &[Address],
+) -> DecodedBlocks<'a> {
+    let (count, scanned, missing) = (&Cell::new(0), &Cell::new(0), &Cell::new(0));
+    let logs = blocks
+        .inspect(|_| count.set(count.get() + 1))
+        // Pair every transaction with its receipt, skipping those without one
+        .flat_map(|(block, receipts)| {
+            block.body.transactions.iter().enumerate().filter_map(move |(index, tx)| {
+                let Some(receipt) = receipts.get(index).and_then(Option::as_ref) else {
+                    let (number, tx_hash) = (block.number, tx.hash());
+                    debug!(
+                        target: "t1::bridge",
+                        number,
+                        index,
+                        %tx_hash,
+                        "Transaction without a receipt, skipping its logs"
+                    );
+                    missing.set(missing.get() + 1);
+                    return None
+                };
+                Some((block, tx, receipt))
+            })
+        })
+        // Get all logs from counter contract
+        .flat_map(|(block, tx, receipt)| {
+            receipt
+                .logs
+                .iter()
+                .inspect(|_| scanned.set(scanned.get() + 1))
+                .filter(|log| contracts.contains(&log.address))
+                .map(move |log| (block, tx, log))
+        });
+
+    // Decode counter events
+    let mut events = Vec::new();
+    let mut failed = 0;
+    for (block, tx, log) in logs {
+        match CounterContractEvents::decode_raw_log(log.topics(), &log.data.data, true) {
+            Ok(event) => events.push((block, tx, log.address, event)),
+            Err(err) => {
+                let topic0 = log.topics().first();
+                let (tx_hash, contract) = (tx.hash(), log.address);
+                warn!(
+                    target: "t1::bridge",
+                    %tx_hash,
+                    %contract,
+                    ?topic0,
+                    %err,
+                    "Failed to decode Counter event"
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    let missing = missing.get();
+    if missing > 0 {
+        warn!(target: "t1::bridge", missing, "Skipped transactions without a receipt");
+    }
+
+    DecodedBlocks { events, blocks: count.get(), scanned: scanned.get(), failed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::test_utils::{chain_with_log, incremented_log, metric_values};
+    use alloy_primitives::{Log, LogData, B256};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use reth_testing_utils::generators;
+
+    #[test]
+    fn test_decode_rollup_events() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let contracts = [Address::random(), Address::random()];
+
+        // Every transaction of block 1 emits an event of the first contract, of block 2 of the
+        // second one
+        let chain = chain_with_log(&mut rng, 1..=2, 2, |number| {
+            incremented_log(contracts[number as usize - 1])
+        })?;
+        let expected = chain
+            .blocks_iter()
+            .flat_map(|block| block.body.transactions.iter().map(|tx| (block.number, tx.hash())))
+            .zip([contracts[0], contracts[0], contracts[1], contracts[1]])
+            .collect::<Vec<_>>();
+
+        let events = decode_rollup_events(&chain, &contracts)?;
+        let decoded = events
+            .iter()
+            .map(|(block, tx, contract, event)| {
+                assert!(matches!(event, CounterContractEvents::Incremented(_)));
+                ((block.number, tx.hash()), *contract)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_rollup_events_other_contracts() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let (counter, other) = (Address::random(), Address::random());
+
+        // Only the events of the given contracts are decoded
+        let chain = chain_with_log(&mut rng, 1..=3, 1, |_| incremented_log(other))?;
+        assert!(decode_rollup_events(&chain, &[counter])?.is_empty());
+        assert!(decode_rollup_events(&chain, &[])?.is_empty());
+        assert_eq!(decode_rollup_events(&chain, &[counter, other])?.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_rollup_events_undecodable() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let (counter, other) = (Address::random(), Address::random());
+
+        // A log of an unknown event fails the decoding, with the number of undecodable logs
+        let data = LogData::new_unchecked(vec![B256::random()], Default::default());
+        let chain =
+            chain_with_log(&mut rng, 1..=2, 2, |_| Log { address: counter, data: data.clone() })?;
+        let err = decode_rollup_events(&chain, &[counter]).unwrap_err();
+        assert!(err.to_string().contains("failed to decode 4 Counter events"), "{err}");
+
+        // Unless it's emitted by another contract
+        let chain =
+            chain_with_log(&mut rng, 1..=2, 2, |_| Log { address: other, data: data.clone() })?;
+        assert!(decode_rollup_events(&chain, &[counter])?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_rollup_events_multiple_per_transaction() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let contracts = [Address::random(), Address::random()];
+        let unrelated = Address::random();
+        let mut chain = chain_with_log(&mut rng, 1..=1, 1, |_| incremented_log(contracts[0]))?;
+        let tx_hash = chain.tip().body.transactions[0].hash();
+
+        // The transaction emits events of both contracts and of an unrelated one, in between
+        if let Some(receipt) = chain.execution_outcome_mut().receipts.receipt_vec[0][0].as_mut() {
+            receipt.logs = vec![
+                incremented_log(contracts[1]),
+                incremented_log(unrelated),
+                incremented_log(contracts[0]),
+                incremented_log(contracts[1]),
+            ];
+        }
+
+        // Every event of the contracts is decoded in order, all tagged with the same transaction
+        let events = decode_rollup_events(&chain, &contracts)?;
+        let decoded = events.iter().map(|(_, tx, contract, _)| (tx.hash(), *contract));
+        assert_eq!(
+            decoded.collect::<Vec<_>>(),
+            vec![(tx_hash, contracts[1]), (tx_hash, contracts[0]), (tx_hash, contracts[1])]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_metrics() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let metrics = reth_metrics::metrics::with_local_recorder(&recorder, L1Metrics::default);
+
+        // Both blocks have 3 logs, only those of block 1 are emitted by the Counter contract
+        let (counter, other) = (Address::random(), Address::random());
+        let chain = chain_with_log(&mut rng, 1..=2, 3, |number| {
+            incremented_log(if number == 1 { counter } else { other })
+        })?;
+        let events = decode_chain_into_rollup_events(&chain, &[counter], &metrics)?;
+        assert_eq!(events.len(), 3);
+
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.decoded_blocks_total"], DebugValue::Counter(2));
+        assert_eq!(values["exex.l1.scanned_logs_total"], DebugValue::Counter(6));
+        assert_eq!(values["exex.l1.decoded_events_total"], DebugValue::Counter(3));
+        let DebugValue::Histogram(durations) = &values["exex.l1.decode_duration_seconds"] else {
+            panic!("decode duration is not a histogram")
+        };
+        assert_eq!(durations.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_missing_receipt() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let (first, last) = (Address::random(), Address::random());
+        let mut chain = chain_with_log(&mut rng, 1..=1, 3, |_| incremented_log(first))?;
+        let hashes = chain.tip().body.transactions.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+
+        // The receipt of the second transaction is missing, the third one is emitted by another
+        // contract to tell it apart
+        let receipts = &mut chain.execution_outcome_mut().receipts.receipt_vec[0];
+        receipts[1] = None;
+        if let Some(receipt) = receipts[2].as_mut() {
+            receipt.logs = vec![incremented_log(last)];
+        }
+
+        let events =
+            decode_chain_into_rollup_events(&chain, &[first, last], &L1Metrics::default())?;
+        let decoded = events.iter().map(|(_, tx, contract, _)| (tx.hash(), *contract));
+        assert_eq!(decoded.collect::<Vec<_>>(), vec![(hashes[0], first), (hashes[2], last)]);
+
+        // A block with fewer receipts than transactions only decodes those it has
+        chain.execution_outcome_mut().receipts.receipt_vec[0].truncate(1);
+        let events =
+            decode_chain_into_rollup_events(&chain, &[first, last], &L1Metrics::default())?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].1.hash(), hashes[0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_chain_malformed_log() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let address = Address::random();
+
+        // A log of an unknown event is surfaced as an error instead of being dropped
+        let data = LogData::new_unchecked(vec![B256::random()], Default::default());
+        let chain = chain_with_log(&mut rng, 1..=1, 1, |_| Log { address, data: data.clone() })?;
+        let metrics = L1Metrics::default();
+        let err = decode_chain_into_rollup_events(&chain, &[address], &metrics).unwrap_err();
+        assert!(err.to_string().contains("failed to decode 1 Counter events"), "{err}");
+
+        // Logs of other contracts are not decoded
+        let events = decode_chain_into_rollup_events(&chain, &[Address::random()], &metrics)?;
+        assert!(events.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_chain_multiple_contracts() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let contracts = [Address::random(), Address::random()];
+        let unrelated = Address::random();
+
+        // Blocks 1 and 2 have events of the two contracts, block 3 of an unrelated one
+        let emitters = [contracts[0], contracts[1], unrelated];
+        let chain = chain_with_log(&mut rng, 1..=3, 1, |number| {
+            incremented_log(emitters[number as usize - 1])
+        })?;
+
+        // The events of both contracts are decoded and tagged with their contract, the events of
+        // the unrelated contract are ignored
+        let events = decode_chain_into_rollup_events(&chain, &contracts, &L1Metrics::default())?;
+        let tagged = events
+            .iter()
+            .map(|(block, _, contract, _)| (block.number, *contract))
+            .collect::<Vec<_>>();
+        assert_eq!(tagged, vec![(1, contracts[0]), (2, contracts[1])]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1626 @@
+//! The ExEx of the bridge, and its installation on a node with the [`StateRootBridge`].
+
+use super::{
+    client::{L1Client, L1Metrics, StateRootSink},
+    config::{ExExArgs, ExExConfig},
+    handler::{RollupEventHandler, StateRootAnchor},
+    history::{AnchorEvents, SubmissionHistory, L1_HISTORY_FILE},
+    rpc::{BridgeStatus, BridgeStatusHandle},
+    signer::L1Signer,
+    watermark::{Watermark, L1_WATERMARK_FILE},
+};
+use alloy_primitives::{Address, BlockNumber, B256};
+use alloy_transport::{BoxTransport, Transport};
+use reth_execution_types::Chain;
+use reth_exex::{ExExContext, ExExEvent, ExExNotification};
+use reth_node_api::FullNodeComponents;
+use reth_provider::{HeaderProvider, StateProviderFactory};
+use reth_tasks::shutdown::GracefulShutdown;
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::time::Instant;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+/// The L1 bridge ExEx, anchoring the state roots of the committed blocks on L1.
+///
+/// Built with [`Self::builder`] from an [`ExExConfig`], and installed on a node by launching it
+/// in the `install_exex` closure, see [`Self::launch`] and [`exex_init`].
+///
+/// # Example
+///
+/// Install the bridge on the node of another binary, with the `l1-bridge` feature.
+///
+/// ```no_run
+/// use reth::{
+///     bridge::{ExExConfig, StateRootBridge},
+///     cli::Cli,
+/// };
+/// use reth_node_ethereum::EthereumNode;
+///
+/// Cli::parse_args()
+///     .run(|builder, _| async move {
+///         let bridge = StateRootBridge::builder().config(ExExConfig::from_env()?).build()?;
+///         let handle = builder
+///             .node(EthereumNode::default())
+///             .install_exex("t1", move |ctx| bridge.launch(ctx))
+///             .launch()
+///             .await?;
+///
+///         handle.wait_for_node_exit().await
+///     })
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct StateRootBridge {
+    config: ExExConfig,
+    transport: Option<BoxTransport>,
+    signer: Option<Arc<dyn L1Signer>>,
+    sink: Option<Arc<dyn StateRootSink>>,
+    status: BridgeStatusHandle,
+    anchor_events: AnchorEvents,
+    disabled: bool,
+}
+
+impl StateRootBridge {
+    /// Returns a [`StateRootBridgeBuilder`] of the bridge.
+    pub fn builder() -> StateRootBridgeBuilder {
+        StateRootBridgeBuilder::default()
+    }
+
+    /// Returns the configuration of the bridge.
+    pub const fn config(&self) -> &ExExConfig {
+        &self.config
+    }
+
+    /// Launches the ExEx on the node of the given context, returning the future the node runs
+    /// it as.
+    ///
+    /// The contract ABIs are checked and the [`L1Client`] is constructed here, so a misconfigured
+    /// bridge fails the node at startup. The [`Watermark`] of the previous run is read from the
+    /// datadir and reconciled with L1, see [`reconcile_l1`], and the Counter contracts are looked
+    /// up in the node's state, see [`check_counter_contracts`]. The ExEx itself runs in a task
+    /// that outlives the node's shutdown signal, see [`spawn_exex`]. Its progress is reported to
+    /// the [`BridgeStatusHandle`], which is watched by the [`health_check`] task, and the state
+    /// roots it anchors are published to the [`AnchorEvents`]. Unless in dry run mode or anchoring
+    /// in the sink of the builder, the balance of the L1 account is watched by the
+    /// [`balance_check`] task, sharing the client of the primary destination.
+    ///
+    /// Every [`ExExConfig::l1_backup_destinations`] gets a [`StateRootAnchor`] of its own, with
+    /// its own watermark, history and [`L1Metrics`] labeled by destination, composed with the
+    /// primary one. A failing destination keeps its state roots queued by its own
+    /// [`CircuitBreaker`](super::client::CircuitBreaker) without blocking the others, but the
+    /// height of the queued blocks isn't finished until every destination anchored them. The
+    /// signer and the sink of the builder only apply to the primary destination.
+    ///
+    /// A disabled bridge is installed without any handler, finishing every committed block
+    /// without contacting the L1.
+    pub async fn launch<Node: FullNodeComponents>(
+        self,
+        ctx: ExExContext<Node>,
+    ) -> eyre::Result<impl Future<Output = eyre::Result<()>>> {
+        let config = &self.config;
+        let mut handlers: Vec<Box<dyn RollupEventHandler>> = Vec::new();
+        if self.disabled {
+            warn!(target: "t1::bridge", "L1 bridge disabled, committed blocks are not anchored");
+            return Ok(spawn_exex(ctx, handlers, config))
+        }
+        config.check_counter_abi()?;
+        let data_dir = ctx.config.datadir();
+        let mut client = self
+            .client(config.clone())
+            .await?
+            .with_status(self.status.clone())
+            .with_history(SubmissionHistory::new(data_dir.data_dir().join(L1_HISTORY_FILE)))
+            .with_anchor_events(self.anchor_events.clone());
+        if let Some(signer) = &self.signer {
+            client = client.with_signer(signer.clone());
+        }
+        if let Some(sink) = &self.sink {
+            client = client.with_sink(sink.clone());
+        }
+        let health_check = health_check(self.status.clone(), L1Metrics::default(), config.clone())
+            .instrument(exex_span(config));
+        ctx.components.task_executor().spawn(health_check);
+        let watermark = Watermark::load(data_dir.data_dir().join(L1_WATERMARK_FILE))?;
+        info!(target: "t1::bridge", height = ?watermark.height(), "Loaded L1 watermark");
+        check_counter_contracts(ctx.provider(), &config.counter_contracts)?;
+        reconcile_l1(&client, ctx.provider(), &watermark).await?;
+        let anchor = StateRootAnchor::new(client, watermark, ctx.provider().clone());
+        if !config.dry_run && self.sink.is_none() {
+            let balance_check = balance_check(anchor.client.clone()).instrument(exex_span(config));
+            ctx.components.task_executor().spawn(balance_check);
+        }
+        handlers.push(Box::new(anchor));
+        for (i, destination) in config.l1_backup_destinations.iter().enumerate() {
+            let label = format!("backup-{}", i + 1);
+            let history = data_dir.data_dir().join(format!("{L1_HISTORY_FILE}-{label}"));
+            let metrics = L1Metrics::new_with_labels(&[("destination", label.clone())]);
+            let client = self
+                .client(config.for_destination(destination))
+                .await?
+                .with_history(SubmissionHistory::new(history))
+                .with_metrics(metrics);
+            let watermark =
+                Watermark::load(data_dir.data_dir().join(format!("{L1_WATERMARK_FILE}-{label}")))?;
+            info!(
+                target: "t1::bridge",
+                destination = %label,
+                height = ?watermark.height(),
+                "Loaded L1 watermark"
+            );
+            reconcile_l1(&client, ctx.provider(), &watermark).await?;
+            let anchor = StateRootAnchor::new(client, watermark, ctx.provider().clone());
+            handlers.push(Box::new(anchor));
+        }
+        Ok(spawn_exex(ctx, handlers, config))
+    }
+
+    /// Returns an [`L1Client`] of the given configuration, over the transport of the builder if
+    /// set.
+    async fn client(&self, config: ExExConfig) -> eyre::Result<L1Client> {
+        match &self.transport {
+            Some(transport) => L1Client::with_transport(config, transport.clone()).await,
+            None => L1Client::new(config).await,
+        }
+    }
+}
+
+/// Builder of a [`StateRootBridge`].
+#[derive(Debug, Default)]
+pub struct StateRootBridgeBuilder {
+    config: Option<ExExConfig>,
+    transport: Option<BoxTransport>,
+    signer: Option<Arc<dyn L1Signer>>,
+    sink: Option<Arc<dyn StateRootSink>>,
+    status: BridgeStatusHandle,
+    anchor_events: AnchorEvents,
+    disabled: bool,
+}
+
+impl StateRootBridgeBuilder {
+    /// Sets the configuration of the bridge, which is required.
+    pub fn config(mut self, config: ExExConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Sets the transport of the L1 RPC calls, instead of connecting to [`ExExConfig::l1_rpc`],
+    /// e.g. a mock L1 in tests.
+    pub fn transport<T: Transport + Clone>(mut self, transport: T) -> Self {
+        self.transport = Some(BoxTransport::new(transport));
+        self
+    }
+
+    /// Sets the [`L1Signer`] of the submissions instead of [`ExExConfig::signer`], see
+    /// [`L1Client::with_signer`].
+    pub fn signer(mut self, signer: Arc<dyn L1Signer>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Sets the [`StateRootSink`] the state roots are anchored in instead of the
+    /// [`StateRootContract`](super::contract::StateRootContract), see [`L1Client::with_sink`].
+    pub fn sink(mut self, sink: Arc<dyn StateRootSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Sets the [`BridgeStatusHandle`] the bridge reports its progress to, e.g. one shared with
+    /// the [`T1Rpc`](super::rpc::T1Rpc).
+    pub fn status(mut self, status: BridgeStatusHandle) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Sets the [`AnchorEvents`] the anchored state roots are published to.
+    pub fn anchor_events(mut self, anchor_events: AnchorEvents) -> Self {
+        self.anchor_events = anchor_events;
+        self
+    }
+
+    /// Sets whether the bridge is disabled, see [`ExExArgs::disable`].
+    pub const fn disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+
+    /// Builds the bridge, failing without a configuration.
+    pub fn build(self) -> eyre::Result<StateRootBridge> {
+        let config =
+            self.config.ok_or_else(|| eyre::eyre!("L1 bridge built without a configuration"))?;
+        Ok(StateRootBridge {
+            config,
+            transport: self.transport,
+            signer: self.signer,
+            sink: self.sink,
+            status: self.status,
+            anchor_events: self.anchor_events,
+            disabled: self.disabled,
+        })
+    }
+}
+
+/// The initialization logic of the ExEx is just an async function.
+///
+/// During initialization you can wait for resources you need to be up for the ExEx to function,
+/// like a database connection.
+///
+/// The [`ExExConfig`] is resolved from the [`ExExArgs`], see [`ExExArgs::resolve_config`], and
+/// the [`StateRootBridge`] built from it is launched, see [`StateRootBridge::launch`]. Its
+/// progress is reported to the given `status`, and the state roots it anchors are published to
+/// the given `anchor_events`.
+///
+/// With [`ExExArgs::print_config`], the resolved configuration is logged. Exiting instead of
+/// starting the node is handled before the node is launched, not here.
+///
+/// With [`ExExArgs::disable`], the ExEx is installed without any handler, finishing every
+/// committed block without contacting the L1.
+pub async fn exex_init<Node: FullNodeComponents>(
+    ctx: ExExContext<Node>,
+    args: ExExArgs,
+    status: BridgeStatusHandle,
+    anchor_events: AnchorEvents,
+) -> eyre::Result<impl Future<Output = eyre::Result<()>>> {
+    let config = args.resolve_config(&ctx.config)?;
+    if args.print_config.is_some() {
+        let account = config.account();
+        info!(
+            target: "t1::bridge",
+            %account,
+            ?config,
+            "Resolved L1 bridge configuration"
+        );
+    }
+    StateRootBridge::builder()
+        .config(config)
+        .status(status)
+        .anchor_events(anchor_events)
+        .disabled(args.disable)
+        .build()?
+        .launch(ctx)
+        .await
+}
+
+/// Spawns the [`exex`] as a critical task with a graceful shutdown signal, returning a future that
+/// resolves to its result.
+///
+/// The future returned to the node is dropped as soon as the node shuts down, while the spawned
+/// task delays the shutdown until the `handler` is done with the blocks still pending in it.
+/// Everything the ExEx logs is in its [`exex_span`].
+fn spawn_exex<Node: FullNodeComponents, H: RollupEventHandler + 'static>(
+    ctx: ExExContext<Node>,
+    handler: H,
+    config: &ExExConfig,
+) -> impl Future<Output = eyre::Result<()>> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let executor = ctx.components.task_executor().clone();
+    let span = exex_span(config);
+    let timeouts = ExExTimeouts::new(config);
+    let reorgs = ReorgMonitor::new(L1Metrics::default(), config.reorg_warn_depth);
+    executor.spawn_critical_with_graceful_shutdown_signal("l1 bridge exex", |shutdown| {
+        async move {
+            let _ = tx.send(exex(ctx, handler, timeouts, reorgs, shutdown).await);
+        }
+        .instrument(span)
+    });
+    async move { rx.await? }
+}
+
+/// Timeouts of the [`exex`], taken from the [`ExExConfig`].
+#[derive(Debug, Clone, Copy)]
+struct ExExTimeouts {
+    /// See [`ExExConfig::shutdown_timeout`].
+    shutdown: Duration,
+    /// See [`ExExConfig::notification_timeout`].
+    notification: Option<Duration>,
+    /// See [`ExExConfig::abort_on_notification_timeout`].
+    abort_on_notification: bool,
+}
+
+impl ExExTimeouts {
+    const fn new(config: &ExExConfig) -> Self {
+        Self {
+            shutdown: config.shutdown_timeout,
+            notification: config.notification_timeout,
+            abort_on_notification: config.abort_on_notification_timeout,
+        }
+    }
+}
+
+/// Records the depth of the reorgs of the local chain the [`exex`] receives, see [`reorg_depth`].
+#[derive(Debug, Clone)]
+struct ReorgMonitor {
+    metrics: L1Metrics,
+    /// See [`ExExConfig::reorg_warn_depth`].
+    warn_depth: u64,
+}
+
+impl ReorgMonitor {
+    const fn new(metrics: L1Metrics, warn_depth: u64) -> Self {
+        Self { metrics, warn_depth }
+    }
+
+    /// Records the depth of the reorg from the `old` to the `new` chain in
+    /// [`L1Metrics::reorg_depth`], warning and incrementing [`L1Metrics::deep_reorgs_total`] if
+    /// it's deeper than the warn depth. Returns the depth.
+    fn observe(&self, old: &Chain, new: &Chain) -> u64 {
+        let depth = reorg_depth(old, new);
+        self.metrics.reorg_depth.record(depth as f64);
+        if depth > self.warn_depth {
+            let (from_chain, to_chain) = (old.range(), new.range());
+            warn!(
+                target: "t1::bridge",
+                depth,
+                warn_depth = self.warn_depth,
+                ?from_chain,
+                ?to_chain,
+                "Deep reorg of the local chain"
+            );
+            self.metrics.deep_reorgs_total.increment(1);
+        }
+        depth
+    }
+}
+
+/// Returns the number of blocks of the `old` chain rolled back by a reorg to the `new` chain,
+/// i.e. the blocks of the `old` chain above the fork point both chains descend from.
+///
+/// The fork point is the parent of the lowest first block of the two chains. An empty `old` chain
+/// rolls back no blocks.
+pub fn reorg_depth(old: &Chain, new: &Chain) -> u64 {
+    let Some(old_tip) = old.blocks().keys().next_back() else { return 0 };
+    let first = old.first().number;
+    let fork = new.blocks().keys().next().map_or(first, |&number| number.min(first));
+    (old_tip + 1).saturating_sub(fork)
+}
+
+/// Returns `true` if the committed or the reverted chain of the given notification has no blocks,
+/// and thus no tip.
+fn has_empty_chain(notification: &ExExNotification) -> bool {
+    let committed = notification.committed_chain();
+    let reverted = notification.reverted_chain();
+    committed.iter().chain(reverted.iter()).any(|chain| chain.is_empty())
+}
+
+/// Returns the span the given notification is processed in.
+///
+/// The span records the kind of the notification, and the number and hash of the tip of its new
+/// chain, or of its reverted chain if there's no new one.
+fn notification_span(notification: &ExExNotification) -> tracing::Span {
+    let (kind, chain) = notification_chain(notification);
+    let tip = chain.tip();
+    info_span!(
+        target: "t1::bridge",
+        "notification",
+        notification_kind = %kind,
+        block_number = tip.number,
+        block_hash = %tip.hash()
+    )
+}
+
+/// Returns the kind of the given notification, and its committed chain, or the reverted chain of
+/// a revert.
+fn notification_chain(notification: &ExExNotification) -> (&'static str, &Arc<Chain>) {
+    match notification {
+        ExExNotification::ChainCommitted { new } => ("commit", new),
+        ExExNotification::ChainReorged { new, .. } => ("reorg", new),
+        ExExNotification::ChainReverted { old } => ("revert", old),
+    }
+}
+
+/// Returns the span of the tasks of the ExEx, named after [`ExExConfig::name`].
+fn exex_span(config: &ExExConfig) -> tracing::Span {
+    info_span!(target: "t1::bridge", "exex", name = %config.name)
+}
+
+/// An ExEx is just a future, which means you can implement all of it in an async function!
+///
+/// This ExEx just prints out whenever either a new chain of blocks being added, or a chain of
+/// blocks being re-orged, and passes the notification on to the [`RollupEventHandler`]. After
+/// processing a committed chain, emits an [`ExExEvent::FinishedHeight`] event with the height the
+/// handler acknowledged, or the tip of the new chain of a reorg, unless the handler is still
+/// [`RollupEventHandler::is_pending`]. A handler error stops the ExEx before the height is
+/// finished. While the handler is [`RollupEventHandler::is_saturated`], no notifications are
+/// consumed, applying backpressure to the node.
+///
+/// A notification the handler doesn't handle within [`ExExTimeouts::notification`] is logged
+/// with the range of its chain and skipped without finishing its height, or stops the ExEx with
+/// an error if [`ExExTimeouts::abort_on_notification`] is set.
+///
+/// On the `shutdown` signal, or once the notifications end, the handler is shut down for at most
+/// [`ExExTimeouts::shutdown`]. The height is only finished if it's no longer pending by then.
+///
+/// The depth of every reorg is logged and recorded by the [`ReorgMonitor`].
+async fn exex<Node: FullNodeComponents, H: RollupEventHandler>(
+    mut ctx: ExExContext<Node>,
+    mut handler: H,
+    timeouts: ExExTimeouts,
+    reorgs: ReorgMonitor,
+    mut shutdown: GracefulShutdown,
+) -> eyre::Result<()> {
+    // Tip of the latest committed chain, finished once the handler isn't pending anymore
+    let mut finished = None;
+    // Held until the handler is shut down, delaying the node's shutdown
+    let mut shutdown_guard = None;
+
+    loop {
+        let deadline = handler.deadline();
+        let due = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now));
+        let saturated = handler.is_saturated();
+        if saturated {
+            debug!(
+                target: "t1::bridge",
+                ?deadline,
+                "Rollup event handler saturated, pausing notifications"
+            );
+        }
+        tokio::select! {
+            notification = ctx.notifications.next(), if !saturated => {
+                let Some(notification) = notification else { break };
+                if has_empty_chain(&notification) {
+                    debug!(
+                        target: "t1::bridge",
+                        ?notification,
+                        "Skipping notification with an empty chain"
+                    );
+                    continue
+                }
+
+                let span = notification_span(&notification);
+                let handled = async {
+                    match &notification {
+                        ExExNotification::ChainCommitted { new } => {
+                            info!(
+                                target: "t1::bridge",
+                                committed_chain = ?new.range(),
+                                "Received commit"
+                            );
+                            handler.on_chain_committed(new).await.map(Some)
+                        }
+                        ExExNotification::ChainReorged { old, new } => {
+                            let (from_chain, to_chain) = (old.range(), new.range());
+                            let depth = reorgs.observe(old, new);
+                            info!(
+                                target: "t1::bridge",
+                                ?from_chain,
+                                ?to_chain,
+                                depth,
+                                "Received reorg"
+                            );
+                            handler.on_reorg(old, new).await.map(|()| Some(new.tip().num_hash()))
+                        }
+                        ExExNotification::ChainReverted { old } => {
+                            info!(
+                                target: "t1::bridge",
+                                reverted_chain = ?old.range(),
+                                "Received revert"
+                            );
+                            handler.on_revert(old).await.map(|()| None)
+                        }
+                    }
+                }
+                .instrument(span);
+                let acknowledged = match timeouts.notification {
+                    Some(timeout) => match tokio::time::timeout(timeout, handled).await {
+                        Ok(acknowledged) => acknowledged?,
+                        Err(_) => {
+                            let (kind, chain) = notification_chain(&notification);
+                            error!(
+                                target: "t1::bridge",
+                                notification_kind = %kind,
+                                stuck_chain = ?chain.range(),
+                                ?timeout,
+                                "Timed out handling notification"
+                            );
+                            if timeouts.abort_on_notification {
+                                let range = chain.range();
+                                eyre::bail!("timed out handling the {kind} of blocks {range:?}")
+                            }
+                            continue
+                        }
+                    },
+                    None => handled.await?,
+                };
+
+                if acknowledged.is_some() {
+                    finished = acknowledged;
+                }
+            }
+            _ = due, if deadline.is_some() => {
+                handler.on_deadline().await?;
+            }
+            guard = &mut shutdown => {
+                info!(target: "t1::bridge", "Received shutdown signal");
+                shutdown_guard = Some(guard);
+                break
+            }
+        }
+
+        if !handler.is_pending() {
+            if let Some(tip) = finished.take() {
+                ctx.events.send(ExExEvent::FinishedHeight(tip))?;
+            }
+        }
+    }
+
+    match tokio::time::timeout(timeouts.shutdown, handler.on_shutdown()).await {
+        Ok(result) => {
+            result?;
+            if let Some(tip) = finished.filter(|_| !handler.is_pending()) {
+                ctx.events.send(ExExEvent::FinishedHeight(tip))?;
+            }
+        }
+        Err(_) => {
+            let timeout = timeouts.shutdown;
+            warn!(
+                target: "t1::bridge",
+                ?timeout,
+                "Timed out shutting down the rollup event handler"
+            );
+        }
+    }
+    drop(shutdown_guard);
+
+    Ok(())
+}
+
+/// Periodically runs [`check_anchor_gap`] on the given status, every
+/// [`ExExConfig::health_check_interval`].
+///
+/// Runs independently of the notifications, so a stalled anchor is reported even if no blocks are
+/// committed anymore.
+async fn health_check(status: BridgeStatusHandle, metrics: L1Metrics, config: ExExConfig) {
+    // A zero period isn't supported by the interval
+    let period = config.health_check_interval.max(Duration::from_millis(1));
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        check_anchor_gap(&status, &metrics, config.max_anchor_gap);
+    }
+}
+
+/// Periodically runs [`L1Client::check_balance`], every [`ExExConfig::health_check_interval`].
+///
+/// A failed check is only logged, the next one is attempted at the next interval.
+async fn balance_check<T: Transport + Clone>(client: Arc<L1Client<T>>) {
+    // A zero period isn't supported by the interval
+    let period = client.config().health_check_interval.max(Duration::from_millis(1));
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if let Err(err) = client.check_balance().await {
+            warn!(target: "t1::bridge", %err, "Failed to check the L1 account balance");
+        }
+    }
+}
+
+/// Warns and increments [`L1Metrics::anchor_lag_warnings_total`] if the L1 anchor is more than
+/// `max_gap` blocks behind the local tip, returning `true` in that case.
+fn check_anchor_gap(status: &BridgeStatusHandle, metrics: &L1Metrics, max_gap: u64) -> bool {
+    let BridgeStatus { local_tip, anchored_height, .. } = status.get();
+    let Some(local_tip) = local_tip else { return false };
+
+    let gap = local_tip.saturating_sub(anchored_height.unwrap_or_default());
+    if gap <= max_gap {
+        return false
+    }
+    warn!(
+        target: "t1::bridge",
+        local_tip,
+        ?anchored_height,
+        gap,
+        max_gap,
+        "L1 anchor is falling behind the local tip"
+    );
+    metrics.anchor_lag_warnings_total.increment(1);
+    true
+}
+
+/// Warns about the Counter contracts without code in the latest state of the node, returning
+/// their addresses.
+///
+/// No events are ever decoded for an address without a contract, e.g. because it's mistyped in
+/// [`ExExConfig::counter_contracts`]. The contract may also not be deployed yet while the node is
+/// syncing, so this isn't an error.
+fn check_counter_contracts<P: StateProviderFactory>(
+    provider: &P,
+    contracts: &[Address],
+) -> eyre::Result<Vec<Address>> {
+    let state = provider.latest()?;
+    let mut missing = Vec::new();
+    for &contract in contracts {
+        if !state.account_code(contract)?.is_some_and(|code| !code.is_empty()) {
+            warn!(
+                target: "t1::bridge",
+                %contract,
+                "No Counter contract deployed at the configured address"
+            );
+            missing.push(contract);
+        }
+    }
+    Ok(missing)
+}
+
+/// Disagreement between the state root anchored on L1 and the local chain, see [`reconcile_l1`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Discrepancy {
+    /// Number of the last anchored block.
+    pub number: BlockNumber,
+    /// State root of the block in the local chain.
+    pub local: B256,
+    /// State root held by the contract, or `None` if it holds none.
+    pub anchored: Option<B256>,
+}
+
+/// Compares the state root anchored on L1 to the one of the local chain at the [`Watermark`]
+/// height, returning the [`Discrepancy`], if any.
+///
+/// The two disagree if the L1 rolled back the anchoring transaction, or if the local chain was
+/// reorged while the node was down. A discrepancy is logged, and with
+/// [`ExExConfig::reanchor_on_mismatch`] fixed by anchoring the local state root again. If the
+/// block isn't stored locally or the anchored state root can't be read, reconciliation is skipped
+/// with a warning, as it's no reason to keep the node from starting.
+async fn reconcile_l1<T: Transport + Clone, P: HeaderProvider>(
+    client: &L1Client<T>,
+    provider: &P,
+    watermark: &Watermark,
+) -> eyre::Result<Option<Discrepancy>> {
+    let Some(number) = watermark.height() else { return Ok(None) };
+    let Some(header) = provider.sealed_header(number)? else {
+        warn!(
+            target: "t1::bridge",
+            number,
+            "Last anchored block not found in the local chain, not reconciling"
+        );
+        return Ok(None)
+    };
+    let local = client.state_root(&header)?;
+    let anchored = match client.anchored_state_root(number).await {
+        Ok(anchored) => anchored,
+        Err(err) => {
+            warn!(
+                target: "t1::bridge",
+                %err,
+                number,
+                "Failed to read the state root anchored on L1, not reconciling"
+            );
+            return Ok(None)
+        }
+    };
+    if anchored == Some(local) {
+        info!(
+            target: "t1::bridge",
+            number,
+            %local,
+            "State root anchored on L1 matches the local chain"
+        );
+        return Ok(None)
+    }
+
+    warn!(
+        target: "t1::bridge",
+        number,
+        %local,
+        ?anchored,
+        "State root anchored on L1 disagrees with the local chain"
+    );
+    if client.config().reanchor_on_mismatch {
+        if let Some(tx_id) = client.submit_state_roots(&[(number, local)]).await? {
+            info!(target: "t1::bridge", number, %local, %tx_id, "Re-anchored the local state root");
+        }
+    }
+    Ok(Some(Discrepancy { number, local, anchored }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        batch::RootBatch,
+        client::MockStateRootSink,
+        config::{L1_BATCH_SIZE, L1_CONFIG_FILE},
+        decode::decode_chain_into_rollup_events,
+        handler::notify_l1,
+        signer::LocalSigner,
+        test_utils::{
+            counter_chain, metric_values, set_test_env, test_env, CapturedLogs, MockTransport,
+        },
+        StateRootAbi::{self, changeStateRootAtCall},
+    };
+    use alloy_eips::BlockNumHash;
+    use alloy_primitives::{Bytes, U256};
+    use alloy_provider::Provider;
+    use alloy_signer_local::PrivateKeySigner;
+    use alloy_sol_types::SolCall;
+    use async_trait::async_trait;
+    use eyre::OptionExt;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use reth_exex_test_utils::{test_exex_context, Adapter, TestExExHandle};
+    use reth_node_api::FullNodeTypes;
+    use reth_node_builder::NodeTypesWithEngine;
+    use reth_payload_builder::PayloadBuilderHandle;
+    use reth_primitives::Header;
+    use reth_provider::test_utils::{ExtendedAccount, MockEthProvider};
+    use reth_tasks::TaskExecutor;
+    use reth_testing_utils::generators::{self, random_block};
+    use std::{ops::RangeInclusive, sync::Mutex};
+
+    #[test]
+    fn test_check_counter_contracts() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let provider = MockEthProvider::default();
+        let deployed = Address::random();
+        let account = ExtendedAccount::new(0, U256::ZERO);
+        provider.add_account(deployed, account.with_bytecode(Bytes::from_static(&[0x60, 0x00])));
+        let empty = Address::random();
+        provider.add_account(empty, ExtendedAccount::new(1, U256::from(1)));
+        let missing = Address::random();
+
+        // Accounts without code are reported, whether or not they exist
+        let contracts = [deployed, empty, missing];
+        assert_eq!(check_counter_contracts(&provider, &contracts)?, vec![empty, missing]);
+        let warnings = logs
+            .lines()
+            .into_iter()
+            .filter(|line| line.contains("No Counter contract deployed"))
+            .collect::<Vec<_>>();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains(&empty.to_string()), "{}", warnings[0]);
+        assert!(warnings[1].contains(&missing.to_string()), "{}", warnings[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_anchor_gap() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let metrics = reth_metrics::metrics::with_local_recorder(&recorder, L1Metrics::default);
+        let status = BridgeStatusHandle::default();
+        status.update(|status| status.anchored_height = Some(10));
+
+        // Nothing to compare before the first notification
+        assert!(!check_anchor_gap(&status, &metrics, 5));
+
+        // The local tip advances without anchoring, up to the threshold
+        for tip in 10..=15 {
+            status.update(|status| status.local_tip = Some(tip));
+            assert!(!check_anchor_gap(&status, &metrics, 5));
+        }
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.anchor_lag_warnings_total"], DebugValue::Counter(0));
+
+        // Past the threshold, every check warns
+        status.update(|status| status.local_tip = Some(16));
+        assert!(check_anchor_gap(&status, &metrics, 5));
+        assert!(check_anchor_gap(&status, &metrics, 5));
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.anchor_lag_warnings_total"], DebugValue::Counter(2));
+
+        // Once anchored again, the checks stop warning
+        status.update(|status| status.anchored_height = Some(16));
+        assert!(!check_anchor_gap(&status, &metrics, 5));
+    }
+
+    #[test]
+    fn test_reorg_depth() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let metrics = reth_metrics::metrics::with_local_recorder(&recorder, L1Metrics::default);
+        let reorgs = ReorgMonitor::new(metrics, 3);
+        let address = Address::random();
+
+        // Blocks 5 to 7 are replaced by a longer chain forked off block 4
+        let old = counter_chain(&mut rng, 5..=7, 0, address)?;
+        let new = counter_chain(&mut rng, 5..=9, 0, address)?;
+        assert_eq!(reorg_depth(&old, &new), 3);
+        assert_eq!(reorgs.observe(&old, &new), 3);
+        assert!(!logs.lines().iter().any(|line| line.contains("Deep reorg")));
+
+        // A new chain forked off a lower block rolls back the blocks below the old chain too
+        let new = counter_chain(&mut rng, 3..=8, 0, address)?;
+        assert_eq!(reorg_depth(&old, &new), 5);
+        assert_eq!(reorgs.observe(&old, &new), 5);
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("Deep reorg of the local chain"))
+            .ok_or_eyre("deep reorg not warned about")?;
+        assert!(line.contains("WARN"), "{line}");
+        assert!(line.contains("depth=5"), "{line}");
+        assert!(line.contains("warn_depth=3"), "{line}");
+
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.deep_reorgs_total"], DebugValue::Counter(1));
+        assert_eq!(
+            values["exex.l1.reorg_depth"],
+            DebugValue::Histogram(vec![3.0.into(), 5.0.into()])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_l1() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut rng = generators::rng();
+        let provider = MockEthProvider::default();
+        let block = random_block(&mut rng, 5, Default::default());
+        let local = B256::random();
+        let header = Header { state_root: local, ..block.header.header().clone() };
+        provider.add_header(block.hash(), header);
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        watermark.update(5)?;
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport_anchoring = |state_root: B256| {
+            let anchored = Bytes::copy_from_slice(state_root.as_slice());
+            let output = StateRootAbi::stateRootCall::abi_encode_returns(&(anchored,));
+            MockTransport::with_call_output(output)
+        };
+
+        // A state root rolled back on L1 is reported, but not fixed by default
+        let anchored = B256::repeat_byte(0xaa);
+        let transport = transport_anchoring(anchored);
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let discrepancy = reconcile_l1(&client, &provider, &watermark).await?;
+        assert_eq!(discrepancy, Some(Discrepancy { number: 5, local, anchored: Some(anchored) }));
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("disagrees with the local chain"))
+            .ok_or_eyre("discrepancy not reported")?;
+        assert!(line.contains("number=5"), "{line}");
+        assert!(line.contains(&format!("local={local}")), "{line}");
+        assert!(line.contains(&format!("anchored=Some({anchored})")), "{line}");
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        // With the flag, the local state root is anchored again
+        let config = ExExConfig { reanchor_on_mismatch: true, ..config };
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        assert!(reconcile_l1(&client, &provider, &watermark).await?.is_some());
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 1);
+        let call = changeStateRootAtCall::abi_decode(txs[0].input(), true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(5), local));
+
+        // Matching state roots are no discrepancy
+        let transport = transport_anchoring(local);
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        assert_eq!(reconcile_l1(&client, &provider, &watermark).await?, None);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exex_flushes_on_shutdown() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let (ctx, mut handle) = test_exex_context().await?;
+        let config = ExExConfig {
+            batch_flush_interval: Duration::from_secs(3600),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let anchor = StateRootAnchor::new(client, watermark, ctx.provider().clone());
+        let exex = spawn_exex(ctx, anchor, &config);
+
+        // The state root is queued until the batch is due, so the height isn't finished
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        let tip = chain.tip().num_hash();
+        handle.send_notification_chain_committed(chain).await?;
+        while handle.notifications_tx.capacity() < handle.notifications_tx.max_capacity() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        handle.assert_events_empty();
+
+        // The queued state root is anchored before the node shuts down
+        let tasks = handle.tasks;
+        let shutdown = tokio::task::spawn_blocking(move || {
+            tasks.graceful_shutdown_with_timeout(Duration::from_secs(5))
+        });
+        assert!(shutdown.await?);
+        exex.await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+        assert_eq!(handle.events_rx.try_recv()?, ExExEvent::FinishedHeight(tip));
+        assert_eq!(Watermark::load(dir.path().join(L1_WATERMARK_FILE))?.height(), Some(1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exex_finishes_acknowledged_height() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig { start_height: 10, ..ExExConfig::from_env_with(test_env)? };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        // An empty chain acknowledges nothing
+        let empty = Chain::default();
+        let acknowledged =
+            notify_l1(&client, client.sink(), &mut watermark, &mut batch, &empty).await?;
+        assert_eq!(acknowledged, None);
+
+        // Blocks below the start height aren't anchored, but still acknowledged
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        let acknowledged = notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain)
+            .await?
+            .ok_or_eyre("chain not acknowledged")?;
+        assert_eq!(acknowledged, chain.tip().num_hash());
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        // The ExEx finishes the height acknowledged by the handler
+        let (ctx, mut handle) = test_exex_context().await?;
+        let anchor = StateRootAnchor::new(client, watermark, ctx.provider().clone());
+        let _exex = spawn_exex(ctx, anchor, &config);
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(acknowledged)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exex_notification_span() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut rng = generators::rng();
+        let (ctx, mut handle) = test_exex_context().await?;
+        let config = ExExConfig::from_env_with(test_env)?;
+        let client = L1Client::with_transport(config.clone(), MockTransport::default()).await?;
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let anchor = StateRootAnchor::new(client, watermark, ctx.provider().clone());
+        let _exex = spawn_exex(ctx, anchor, &config);
+
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        let tip = chain.tip().num_hash();
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+
+        // The logs of the submission carry the block the notification is about
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("I notifed L1"))
+            .ok_or_eyre("submission not logged")?;
+        assert!(line.contains("exex{name=t1}"), "{line}");
+        assert!(line.contains("notification_kind=commit"), "{line}");
+        assert!(line.contains("block_number=1"), "{line}");
+        assert!(line.contains(&format!("block_hash={}", tip.hash)), "{line}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_empty_chain() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        let chain = Chain::default();
+        let contracts = &config.counter_contracts;
+        let events = decode_chain_into_rollup_events(&chain, contracts, client.metrics())?;
+        assert!(events.is_empty());
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert_eq!(watermark.height(), None);
+
+        // The ExEx skips the empty chain and keeps processing notifications
+        let (ctx, mut handle) = test_exex_context().await?;
+        let anchor = StateRootAnchor::new(client, watermark, ctx.provider().clone());
+        let _exex = spawn_exex(ctx, anchor, &config);
+        handle.send_notification_chain_committed(Chain::default()).await?;
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        let tip = chain.tip().num_hash();
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        Ok(())
+    }
+
+    /// Spawns the ExEx anchoring state roots over a [`MockTransport`], returning the ExEx, the
+    /// handle driving it with notifications, the transport recording its L1 calls and the
+    /// directory of its [`Watermark`].
+    async fn spawn_anchor_exex(
+        config: &ExExConfig,
+    ) -> eyre::Result<(
+        impl Future<Output = eyre::Result<()>>,
+        TestExExHandle,
+        MockTransport,
+        tempfile::TempDir,
+    )> {
+        let (ctx, handle) = test_exex_context().await?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let anchor = StateRootAnchor::new(client, watermark, ctx.provider().clone());
+        Ok((spawn_exex(ctx, anchor, config), handle, transport, dir))
+    }
+
+    #[tokio::test]
+    async fn test_exex_commit_reorg_revert() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config =
+            ExExConfig { dedup_state_roots: false, ..ExExConfig::from_env_with(test_env)? };
+        let (_exex, mut handle, transport, dir) = spawn_anchor_exex(&config).await?;
+        let address = config.counter_contracts[0];
+
+        // The committed blocks are anchored in a batch before their height is finished
+        let committed = counter_chain(&mut rng, 1..=2, 1, address)?;
+        let tip = committed.tip().num_hash();
+        let committed_roots = committed
+            .blocks_iter()
+            .map(|block| (block.number, block.header.state_root))
+            .collect::<Vec<_>>();
+        handle.send_notification_chain_committed(committed).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.anchored_state_roots()?, vec![committed_roots.clone()]);
+
+        // A reorg corrects L1 to the tip of the new chain, whose height is finished
+        let old = counter_chain(&mut rng, 2..=2, 1, address)?;
+        let new = counter_chain(&mut rng, 2..=3, 1, address)?;
+        let tip = new.tip().num_hash();
+        let new_root = (tip.number, new.tip().header.state_root);
+        handle.send_notification_chain_reorged(old, new).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.anchored_state_roots()?[1..], [vec![new_root]]);
+
+        // A revert corrects L1 to the parent of the reverted chain without finishing a height,
+        // the chain committed after it is anchored again
+        let reverted = counter_chain(&mut rng, 1..=3, 1, address)?;
+        handle.send_notification_chain_reverted(reverted).await?;
+        let committed = counter_chain(&mut rng, 1..=1, 1, address)?;
+        let tip = committed.tip().num_hash();
+        let recommitted_root = (tip.number, committed.tip().header.state_root);
+        handle.send_notification_chain_committed(committed).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        let genesis_root = (0, handle.genesis.header.state_root);
+        assert_eq!(
+            transport.anchored_state_roots()?[2..],
+            [vec![genesis_root], vec![recommitted_root]]
+        );
+        handle.assert_events_empty();
+        assert_eq!(Watermark::load(dir.path().join(L1_WATERMARK_FILE))?.height(), Some(1));
+
+        Ok(())
+    }
+
+    /// A node other than the [`Adapter`] of [`test_exex_context`], standing in for e.g. an
+    /// OP-stack node, with the components of the test node.
+    #[derive(Clone)]
+    struct OtherNode(Adapter);
+
+    impl FullNodeTypes for OtherNode {
+        type Types = <Adapter as FullNodeTypes>::Types;
+        type Provider = <Adapter as FullNodeTypes>::Provider;
+    }
+
+    impl FullNodeComponents for OtherNode {
+        type Pool = <Adapter as FullNodeComponents>::Pool;
+        type Evm = <Adapter as FullNodeComponents>::Evm;
+        type Executor = <Adapter as FullNodeComponents>::Executor;
+        type Network = <Adapter as FullNodeComponents>::Network;
+
+        fn pool(&self) -> &Self::Pool {
+            self.0.pool()
+        }
+
+        fn evm_config(&self) -> &Self::Evm {
+            self.0.evm_config()
+        }
+
+        fn block_executor(&self) -> &Self::Executor {
+            self.0.block_executor()
+        }
+
+        fn provider(&self) -> &Self::Provider {
+            self.0.provider()
+        }
+
+        fn network(&self) -> &Self::Network {
+            self.0.network()
+        }
+
+        fn payload_builder(
+            &self,
+        ) -> &PayloadBuilderHandle<<Self::Types as NodeTypesWithEngine>::Engine> {
+            self.0.payload_builder()
+        }
+
+        fn task_executor(&self) -> &TaskExecutor {
+            self.0.task_executor()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exex_other_node() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config =
+            ExExConfig { dedup_state_roots: false, ..ExExConfig::from_env_with(test_env)? };
+        let (ctx, mut handle) = test_exex_context().await?;
+        let ctx = ExExContext {
+            head: ctx.head,
+            config: ctx.config,
+            reth_config: ctx.reth_config,
+            events: ctx.events,
+            notifications: ctx.notifications,
+            components: OtherNode(ctx.components),
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let anchor = StateRootAnchor::new(client, watermark, ctx.provider().clone());
+        let _exex = spawn_exex(ctx, anchor, &config);
+
+        // The ExEx anchors the committed blocks of the other node like those of the test node
+        let committed = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        let tip = committed.tip().num_hash();
+        let committed_roots = committed
+            .blocks_iter()
+            .map(|block| (block.number, block.header.state_root))
+            .collect::<Vec<_>>();
+        handle.send_notification_chain_committed(committed).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.anchored_state_roots()?, vec![committed_roots]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exex_disable() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let (mut ctx, mut handle) = test_exex_context().await?;
+
+        // The L1 is a listener that would see any connection of the ExEx
+        let l1 = std::net::TcpListener::bind("127.0.0.1:0")?;
+        l1.set_nonblocking(true)?;
+        let dir = tempfile::tempdir()?;
+        let config_path = dir.path().join(L1_CONFIG_FILE);
+        let l1_rpc = format!("http://{}", l1.local_addr()?);
+        std::fs::write(&config_path, format!("l1_rpc = \"{l1_rpc}\"\n"))?;
+
+        // Even a disabled bridge is configured with the required settings
+        set_test_env();
+
+        let status = BridgeStatusHandle::default();
+        let anchor_events = AnchorEvents::default();
+        let mut anchored = anchor_events.subscribe();
+        let args = ExExArgs { disable: true, config: Some(config_path), ..Default::default() };
+        let _exex = exex_init(ctx, args, status.clone(), anchor_events).await?;
+
+        // The committed chain is finished without anchoring its state roots
+        let address = ExExConfig::from_env_with(test_env)?.counter_contracts[0];
+        let committed = counter_chain(&mut rng, 1..=2, 1, address)?;
+        let tip = committed.tip().num_hash();
+        handle.send_notification_chain_committed(committed).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(status.get(), BridgeStatus::default());
+        assert!(anchored.try_recv().is_err());
+        let err = l1.accept().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock, "{err}");
+
+        Ok(())
+    }
+
+    /// Returns a test ExEx context whose datadir is in a temporary directory.
+    async fn datadir_exex_context(
+    ) -> eyre::Result<(ExExContext<Adapter>, TestExExHandle, tempfile::TempDir)> {
+        let (mut ctx, handle) = test_exex_context().await?;
+        let dir = tempfile::tempdir()?;
+        ctx.config.datadir.datadir = dir.path().to_path_buf().into();
+        std::fs::create_dir_all(ctx.config.datadir().data_dir())?;
+        Ok((ctx, handle, dir))
+    }
+
+    #[tokio::test]
+    async fn test_state_root_bridge_builder() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config =
+            ExExConfig { dedup_state_roots: false, ..ExExConfig::from_env_with(test_env)? };
+        let address = config.counter_contracts[0];
+
+        // A bridge can't be built without a configuration
+        assert!(StateRootBridge::builder().build().is_err());
+
+        // The state roots are submitted over the mock L1, signed by the signer of the builder
+        let (ctx, mut handle, _dir) = datadir_exex_context().await?;
+        let key = PrivateKeySigner::random();
+        let account = key.address();
+        let transport = MockTransport::default();
+        let status = BridgeStatusHandle::default();
+        let _exex = StateRootBridge::builder()
+            .config(config.clone())
+            .transport(transport.clone())
+            .signer(Arc::new(LocalSigner::new(key)))
+            .status(status.clone())
+            .build()?
+            .launch(ctx)
+            .await?;
+        let chain = counter_chain(&mut rng, 1..=2, 1, address)?;
+        let tip = chain.tip().num_hash();
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].recover_signer(), Some(account));
+        assert_eq!(status.get().anchored_height, Some(2));
+        // A single client is connected, shared by the anchor and the balance check
+        assert_eq!(transport.calls("eth_chainId"), 1);
+
+        // With a sink, the state roots are anchored in it instead of the contract
+        let (ctx, mut handle, _dir) = datadir_exex_context().await?;
+        let transport = MockTransport::default();
+        let sink = Arc::new(MockStateRootSink::default());
+        let _exex = StateRootBridge::builder()
+            .config(config)
+            .transport(transport.clone())
+            .sink(sink.clone())
+            .build()?
+            .launch(ctx)
+            .await?;
+        let chain = counter_chain(&mut rng, 1..=2, 1, address)?;
+        let tip = chain.tip().num_hash();
+        let roots = chain
+            .blocks_iter()
+            .map(|block| (block.number, block.header.state_root))
+            .collect::<Vec<_>>();
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(sink.submissions(), roots);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        // Nothing is paid from the L1 account, so its balance isn't checked
+        assert_eq!(transport.calls("eth_chainId"), 1);
+        assert_eq!(transport.calls("eth_getBalance"), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exex_start_height() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            start_height: 3,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let (_exex, mut handle, transport, _dir) = spawn_anchor_exex(&config).await?;
+        let address = config.counter_contracts[0];
+
+        // Blocks below the start height are finished without being anchored
+        let chain = counter_chain(&mut rng, 1..=2, 1, address)?;
+        let tip = chain.tip().num_hash();
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        // Only the blocks from the start height on are anchored
+        let chain = counter_chain(&mut rng, 2..=4, 1, address)?;
+        let tip = chain.tip().num_hash();
+        let anchored = chain
+            .blocks_iter()
+            .filter(|block| block.number >= 3)
+            .map(|block| (block.number, block.header.state_root))
+            .collect::<Vec<_>>();
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.anchored_state_roots()?, vec![anchored]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exex_anchor_every() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            anchor_every: 3,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let (_exex, mut handle, transport, _dir) = spawn_anchor_exex(&config).await?;
+        let address = config.counter_contracts[0];
+
+        // Only every third block is anchored
+        let chain = counter_chain(&mut rng, 1..=7, 1, address)?;
+        let tip = chain.tip().num_hash();
+        let anchored = chain
+            .blocks_iter()
+            .filter(|block| block.number % 3 == 0)
+            .map(|block| (block.number, block.header.state_root))
+            .collect::<Vec<_>>();
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.anchored_state_roots()?, vec![anchored]);
+
+        // A reorg of the blocks skipped since the last anchored one is corrected to the new tip,
+        // even though it isn't scheduled
+        let old = counter_chain(&mut rng, 7..=7, 1, address)?;
+        let new = counter_chain(&mut rng, 7..=8, 1, address)?;
+        let tip = new.tip().num_hash();
+        let new_root = (tip.number, new.tip().header.state_root);
+        handle.send_notification_chain_reorged(old, new).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.anchored_state_roots()?[1..], [vec![new_root]]);
+
+        // Committed blocks are only anchored again at the next multiple
+        let chain = counter_chain(&mut rng, 9..=10, 1, address)?;
+        let tip = chain.tip().num_hash();
+        let scheduled = (9, chain.first().header.state_root);
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.anchored_state_roots()?[2..], [vec![scheduled]]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exex_reorg_only() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            reorg_only: true,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let (_exex, mut handle, transport, _dir) = spawn_anchor_exex(&config).await?;
+        let address = config.counter_contracts[0];
+
+        // Committed chains are finished without being anchored
+        let chain = counter_chain(&mut rng, 1..=3, 1, address)?;
+        let tip = chain.tip().num_hash();
+        handle.send_notification_chain_committed(chain).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        // Every reorg anchors the state root of its new tip
+        let mut anchored = Vec::new();
+        for number in [3, 5] {
+            let old = counter_chain(&mut rng, number..=number, 1, address)?;
+            let new = counter_chain(&mut rng, number..=number + 1, 1, address)?;
+            let tip = new.tip().num_hash();
+            anchored.push(vec![(tip.number, new.tip().header.state_root)]);
+            handle.send_notification_chain_reorged(old, new).await?;
+            assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+            assert_eq!(transport.anchored_state_roots()?, anchored);
+
+            let chain = counter_chain(&mut rng, number + 2..=number + 2, 1, address)?;
+            let tip = chain.tip().num_hash();
+            handle.send_notification_chain_committed(chain).await?;
+            assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+            assert_eq!(transport.anchored_state_roots()?, anchored);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_exex_backpressure() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            batch_flush_interval: Duration::from_secs(3600),
+            max_queued_roots: 2,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let (_exex, mut handle, transport, _dir) = spawn_anchor_exex(&config).await?;
+        let address = config.counter_contracts[0];
+        let max_capacity = handle.notifications_tx.max_capacity();
+
+        // The first chain saturates the queue, so the second one isn't consumed while its state
+        // roots wait for the flush interval
+        let first = counter_chain(&mut rng, 1..=2, 1, address)?;
+        let first_tip = first.tip().num_hash();
+        let second = counter_chain(&mut rng, 3..=3, 1, address)?;
+        let second_tip = second.tip().num_hash();
+        handle.send_notification_chain_committed(first).await?;
+        handle.send_notification_chain_committed(second).await?;
+        while handle.notifications_tx.capacity() < max_capacity - 1 {
+            tokio::task::yield_now().await;
+        }
+        for _ in 0..100 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(handle.notifications_tx.capacity(), max_capacity - 1);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        handle.assert_events_empty();
+
+        // Once the queued state roots are anchored, the second chain is consumed
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(first_tip)));
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(second_tip)));
+        let anchored = transport.anchored_state_roots()?;
+        let numbers = anchored
+            .iter()
+            .map(|roots| roots.iter().map(|(number, _)| *number).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![vec![1, 2], vec![3]]);
+
+        Ok(())
+    }
+
+    /// [`RollupEventHandler`] recording the kind and block range of the notifications it handles.
+    #[derive(Debug, Clone, Default)]
+    struct RecordingHandler {
+        received: Arc<Mutex<Vec<(&'static str, RangeInclusive<BlockNumber>)>>>,
+    }
+
+    impl RecordingHandler {
+        fn received(&self) -> Vec<(&'static str, RangeInclusive<BlockNumber>)> {
+            self.received.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl RollupEventHandler for RecordingHandler {
+        async fn on_chain_committed(&mut self, chain: &Arc<Chain>) -> eyre::Result<BlockNumHash> {
+            self.received.lock().unwrap().push(("commit", chain.range()));
+            Ok(chain.tip().num_hash())
+        }
+
+        async fn on_reorg(&mut self, old: &Arc<Chain>, new: &Arc<Chain>) -> eyre::Result<()> {
+            let mut received = self.received.lock().unwrap();
+            received.push(("reorg_old", old.range()));
+            received.push(("reorg_new", new.range()));
+            Ok(())
+        }
+
+        async fn on_revert(&mut self, old: &Arc<Chain>) -> eyre::Result<()> {
+            self.received.lock().unwrap().push(("revert", old.range()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exex_rollup_event_handlers() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let (ctx, mut handle) = test_exex_context().await?;
+        let config = ExExConfig::from_env_with(test_env)?;
+        let (first, second) = (RecordingHandler::default(), RecordingHandler::default());
+        let handlers: Vec<Box<dyn RollupEventHandler>> =
+            vec![Box::new(first.clone()), Box::new(second.clone())];
+        let _exex = spawn_exex(ctx, handlers, &config);
+
+        let address = config.counter_contracts[0];
+        let committed = counter_chain(&mut rng, 1..=2, 1, address)?;
+        let tip = committed.tip().num_hash();
+        handle.send_notification_chain_committed(committed).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+
+        let old = counter_chain(&mut rng, 2..=2, 1, address)?;
+        let new = counter_chain(&mut rng, 2..=3, 1, address)?;
+        let tip = new.tip().num_hash();
+        handle.send_notification_chain_reorged(old, new).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+
+        // The revert has no committed chain to finish, the commit after it does
+        handle
+            .send_notification_chain_reverted(counter_chain(&mut rng, 3..=3, 1, address)?)
+            .await?;
+        let committed = counter_chain(&mut rng, 3..=3, 1, address)?;
+        let tip = committed.tip().num_hash();
+        handle.send_notification_chain_committed(committed).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+
+        // Every handler received every notification, in order
+        let expected = vec![
+            ("commit", 1..=2),
+            ("reorg_old", 2..=2),
+            ("reorg_new", 2..=3),
+            ("revert", 3..=3),
+            ("commit", 3..=3),
+        ];
+        assert_eq!(first.received(), expected);
+        assert_eq!(second.received(), expected);
+
+        Ok(())
+    }
+
+    /// [`RollupEventHandler`] that never finishes handling a committed chain with the block
+    /// `stall_at`.
+    #[derive(Debug)]
+    struct StallingHandler {
+        stall_at: BlockNumber,
+    }
+
+    #[async_trait]
+    impl RollupEventHandler for StallingHandler {
+        async fn on_chain_committed(&mut self, chain: &Arc<Chain>) -> eyre::Result<BlockNumHash> {
+            if chain.range().contains(&self.stall_at) {
+                std::future::pending::<()>().await;
+            }
+            Ok(chain.tip().num_hash())
+        }
+
+        async fn on_reorg(&mut self, _old: &Arc<Chain>, _new: &Arc<Chain>) -> eyre::Result<()> {
+            Ok(())
+        }
+
+        async fn on_revert(&mut self, _old: &Arc<Chain>) -> eyre::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_exex_notification_timeout() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            notification_timeout: Some(Duration::from_secs(10)),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let address = config.counter_contracts[0];
+
+        // The stalled chain is skipped once the timeout passes, and the next one is finished
+        let (ctx, mut handle) = test_exex_context().await?;
+        let _exex = spawn_exex(ctx, StallingHandler { stall_at: 2 }, &config);
+        let stalled = counter_chain(&mut rng, 1..=2, 1, address)?;
+        handle.send_notification_chain_committed(stalled).await?;
+        let committed = counter_chain(&mut rng, 3..=3, 1, address)?;
+        let tip = committed.tip().num_hash();
+        handle.send_notification_chain_committed(committed).await?;
+        assert_eq!(handle.events_rx.recv().await, Some(ExExEvent::FinishedHeight(tip)));
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("Timed out handling notification"))
+            .ok_or_eyre("timeout not logged")?;
+        assert!(line.contains("ERROR"), "{line}");
+        assert!(line.contains("notification_kind=commit"), "{line}");
+        assert!(line.contains("stuck_chain=1..=2"), "{line}");
+        assert!(line.contains("timeout=10s"), "{line}");
+
+        // With the abort flag, the stalled chain stops the ExEx
+        let config = ExExConfig { abort_on_notification_timeout: true, ..config };
+        let (ctx, mut handle) = test_exex_context().await?;
+        let exex = spawn_exex(ctx, StallingHandler { stall_at: 2 }, &config);
+        let stalled = counter_chain(&mut rng, 2..=2, 1, address)?;
+        handle.send_notification_chain_committed(stalled).await?;
+        let err = exex.await.unwrap_err();
+        assert!(err.to_string().contains("timed out handling the commit of blocks 2..=2"), "{err}");
+        handle.assert_events_empty();
+
+        Ok(())
+    }
+}
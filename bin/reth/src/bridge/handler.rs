@@ -0,0 +1,2290 @@
+//! Handlers of the rollup events of the ExEx, e.g. the [`StateRootAnchor`] anchoring state roots
+//! on L1.
+
+use super::{
+    batch::RootBatch,
+    client::{BreakerState, L1Client, L1Metrics, L1SubmitError, StateRootSink},
+    config::{root_for, StateRootProvider},
+    contract::StateDiff,
+    decode::{decode_chain_into_rollup_events, RollupEvent},
+    rpc::BridgeStatusHandle,
+    watermark::Watermark,
+    CounterContract::CounterContractEvents,
+};
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{BlockNumber, B256};
+use alloy_transport::Transport;
+use async_trait::async_trait;
+use rand::Rng;
+use reth_execution_types::Chain;
+use reth_exex::ExExNotification;
+use reth_primitives::SealedBlockWithSenders;
+use reth_provider::{BlockIdReader, HeaderProvider, StateProviderFactory, StateRootProvider as _};
+use reth_trie::HashedPostState;
+use std::{sync::Arc, time::Duration};
+use tokio::time::Instant;
+use tracing::{debug, error, info, warn};
+
+/// Interval the finalized block is checked at while only unfinalized state roots are queued,
+/// see [`ExExArgs::anchor_finalized`](super::config::ExExArgs::anchor_finalized).
+const FINALITY_POLL_INTERVAL: Duration = Duration::from_secs(12);
+
+/// Handles the chain notifications the [`exex`](super::exex::exex) receives.
+///
+/// The [`StateRootAnchor`] anchors the state roots of the blocks with Counter events on L1. Other
+/// rollup logic plugs into the ExEx by implementing this trait, and multiple handlers are composed
+/// in a `Vec`, which passes every notification to each of them in order.
+///
+/// Handlers may hold back blocks, e.g. to batch them, in which case they report a
+/// [`Self::deadline`] to be woken up at and are [`Self::is_pending`] until the blocks are done.
+#[async_trait]
+pub trait RollupEventHandler: Send {
+    /// Handles a newly committed chain, returning the height the ExEx may finish once the
+    /// handler isn't [`Self::is_pending`], usually the tip of the chain.
+    async fn on_chain_committed(&mut self, chain: &Arc<Chain>) -> eyre::Result<BlockNumHash>;
+
+    /// Handles the reorg of the `old` chain to the `new` one.
+    async fn on_reorg(&mut self, old: &Arc<Chain>, new: &Arc<Chain>) -> eyre::Result<()>;
+
+    /// Handles the revert of the `old` chain.
+    async fn on_revert(&mut self, old: &Arc<Chain>) -> eyre::Result<()>;
+
+    /// Returns when [`Self::on_deadline`] is due, if the handler holds back any blocks.
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Called once the [`Self::deadline`] has passed.
+    async fn on_deadline(&mut self) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    /// Returns `true` while the handler holds back committed blocks, which keeps the ExEx from
+    /// finishing their height.
+    fn is_pending(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` while the handler can't take more notifications, which the ExEx stops
+    /// consuming until the handler catches up on a [`Self::deadline`].
+    fn is_saturated(&self) -> bool {
+        false
+    }
+
+    /// Called once the ExEx shuts down, for at most
+    /// [`ExExConfig::shutdown_timeout`](super::config::ExExConfig::shutdown_timeout).
+    async fn on_shutdown(&mut self) -> eyre::Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RollupEventHandler for Vec<Box<dyn RollupEventHandler>> {
+    /// Returns the lowest height acknowledged by any of the handlers.
+    async fn on_chain_committed(&mut self, chain: &Arc<Chain>) -> eyre::Result<BlockNumHash> {
+        let mut acknowledged = chain.tip().num_hash();
+        for handler in self.iter_mut() {
+            let height = handler.on_chain_committed(chain).await?;
+            if height.number < acknowledged.number {
+                acknowledged = height;
+            }
+        }
+        Ok(acknowledged)
+    }
+
+    async fn on_reorg(&mut self, old: &Arc<Chain>, new: &Arc<Chain>) -> eyre::Result<()> {
+        for handler in self.iter_mut() {
+            handler.on_reorg(old, new).await?;
+        }
+        Ok(())
+    }
+
+    async fn on_revert(&mut self, old: &Arc<Chain>) -> eyre::Result<()> {
+        for handler in self.iter_mut() {
+            handler.on_revert(old).await?;
+        }
+        Ok(())
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.iter().filter_map(|handler| handler.deadline()).min()
+    }
+
+    async fn on_deadline(&mut self) -> eyre::Result<()> {
+        let now = Instant::now();
+        for handler in self.iter_mut() {
+            if handler.deadline().is_some_and(|deadline| deadline <= now) {
+                handler.on_deadline().await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_pending(&self) -> bool {
+        self.iter().any(|handler| handler.is_pending())
+    }
+
+    fn is_saturated(&self) -> bool {
+        self.iter().any(|handler| handler.is_saturated())
+    }
+
+    async fn on_shutdown(&mut self) -> eyre::Result<()> {
+        for handler in self.iter_mut() {
+            handler.on_shutdown().await?;
+        }
+        Ok(())
+    }
+}
+
+/// [`RollupEventHandler`] anchoring the state roots of the blocks with Counter events on L1.
+///
+/// State roots are only considered processed once their L1 transaction is confirmed, so a failed
+/// or reverted submission stops the ExEx before the height is finished. While state roots are
+/// queued in the [`RootBatch`], the handler is pending. Queued state roots are anchored before a
+/// reorg or revert is corrected, except those of the blocks it orphaned, and on shutdown.
+///
+/// With [`ExExConfig::anchor_finalized`](super::config::ExExConfig::anchor_finalized), only the
+/// state roots of the blocks finalized according to the provider are anchored. The finalized block
+/// is checked on every notification, and every [`FINALITY_POLL_INTERVAL`] while state roots are
+/// held back. Finalized blocks are never reorged, so a reorg or revert only drops the orphaned
+/// state roots and queues the new chain.
+///
+/// With [`ExExConfig::reorg_only`](super::config::ExExConfig::reorg_only), committed chains are
+/// acknowledged without being queued, so only the corrections of reorgs and reverts are anchored.
+///
+/// With [`ExExConfig::verify_root_locally`](super::config::ExExConfig::verify_root_locally), a
+/// committed chain whose tip doesn't match the state root recomputed from the provider's state is
+/// an error, before any of it is queued.
+///
+/// The [`BridgeStatus`](super::rpc::BridgeStatus) of the client is updated after every notification
+/// and flush.
+#[derive(Debug)]
+pub struct StateRootAnchor<T: Transport + Clone, P> {
+    /// Client anchoring the state roots, shared with the
+    /// [`balance_check`](super::exex::balance_check).
+    pub(super) client: Arc<L1Client<T>>,
+    watermark: Watermark,
+    batch: RootBatch,
+    provider: P,
+}
+
+impl<T, P> StateRootAnchor<T, P>
+where
+    T: Transport + Clone,
+    P: HeaderProvider + BlockIdReader + StateProviderFactory,
+{
+    /// Returns a handler anchoring the state roots with the given client, resuming after the
+    /// given [`Watermark`] and reading the headers of reverted blocks from the `provider`.
+    pub fn new(client: impl Into<Arc<L1Client<T>>>, watermark: Watermark, provider: P) -> Self {
+        let client = client.into();
+        let config = client.config();
+        let batch = RootBatch::new(client.max_batch_len(), config.batch_flush_interval)
+            .with_submit_interval(config.submit_interval)
+            .with_max_queued(config.max_queued_roots)
+            .with_finalized_only(config.anchor_finalized)
+            .with_min_depth(config.min_depth);
+        let anchor = Self { client, watermark, batch, provider };
+        anchor.record_status();
+        anchor
+    }
+
+    /// Anchors the roots of the given [`StateRootProvider`] instead of the state roots of the
+    /// blocks, see [`L1Client::with_state_root_provider`].
+    ///
+    /// # Panics
+    ///
+    /// If the client is shared already, i.e. the anchor was passed a shared client.
+    pub fn with_state_root_provider(mut self, state_roots: Arc<dyn StateRootProvider>) -> Self {
+        Arc::get_mut(&mut self.client).expect("client of the anchor is shared").state_roots =
+            state_roots;
+        self
+    }
+
+    /// Reads the finalized block from the provider into the batch, with
+    /// [`ExExConfig::anchor_finalized`](super::config::ExExConfig::anchor_finalized).
+    fn update_finalized(&mut self) -> eyre::Result<()> {
+        if self.client.config().anchor_finalized {
+            self.batch.set_finalized(self.provider.finalized_block_number()?);
+        }
+        Ok(())
+    }
+
+    /// Recomputes the state root of the given block from the node's state, with
+    /// [`ExExConfig::verify_root_locally`](super::config::ExExConfig::verify_root_locally), and
+    /// fails if it differs from the one in its header.
+    ///
+    /// Only the tip of a committed chain is verified, as its state root covers the state of every
+    /// block before it.
+    fn verify_state_root(&self, block: &SealedBlockWithSenders) -> eyre::Result<()> {
+        if !self.client.config().verify_root_locally {
+            return Ok(())
+        }
+        let computed = self
+            .provider
+            .state_by_block_hash(block.hash())?
+            .state_root(HashedPostState::default())?;
+        if computed != block.header.state_root {
+            error!(
+                target: "t1::bridge",
+                number = block.number,
+                hash = %block.hash(),
+                header = %block.header.state_root,
+                %computed,
+                "Local state root mismatch, refusing to anchor"
+            );
+            eyre::bail!("state root of block {} doesn't match the local state", block.number)
+        }
+        Ok(())
+    }
+
+    /// Reports the progress of the handler to the [`BridgeStatus`](super::rpc::BridgeStatus) of the
+    /// client.
+    fn record_status(&self) {
+        record_status(self.client.status(), &self.watermark, &self.batch);
+    }
+
+    /// Anchors the queued state roots, then corrects the state root anchored on L1 after the
+    /// given reorg or revert, reporting `tip` as the new local tip.
+    ///
+    /// The queued state roots of the orphaned blocks are dropped instead of being anchored, as
+    /// they are superseded by the correction. A transaction that was already sent for them but
+    /// never confirmed, e.g. after the confirmation timed out, can't be recalled and may still be
+    /// mined. It's superseded as well, since the correction is sent with a later nonce.
+    ///
+    /// With [`ExExConfig::anchor_finalized`](super::config::ExExConfig::anchor_finalized) nothing
+    /// anchored can be orphaned, so instead of a correction the new chain of a reorg is queued.
+    async fn correct(
+        &mut self,
+        notification: &ExExNotification,
+        tip: BlockNumber,
+    ) -> eyre::Result<()> {
+        if let Some(orphaned) = notification.reverted_chain() {
+            let first = orphaned.first().number;
+            let dropped = self.batch.invalidate(first);
+            if dropped > 0 {
+                info!(
+                    target: "t1::bridge",
+                    dropped,
+                    first,
+                    "Dropped the queued state roots of orphaned blocks"
+                );
+            }
+        }
+        self.update_finalized()?;
+        if self.client.config().anchor_finalized {
+            if let Some(new) = notification.committed_chain() {
+                let sink = self.client.sink();
+                notify_l1(&self.client, sink, &mut self.watermark, &mut self.batch, &new).await?;
+            }
+        } else {
+            flush_l1(&self.client, self.client.sink(), &mut self.watermark, &mut self.batch)
+                .await?;
+            revert_l1(&self.client, &mut self.watermark, &self.provider, notification).await?;
+        }
+        self.client.status().update(|status| status.local_tip = Some(tip));
+        self.record_status();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T, P> RollupEventHandler for StateRootAnchor<T, P>
+where
+    T: Transport + Clone,
+    P: HeaderProvider + BlockIdReader + StateProviderFactory + Send + Sync,
+{
+    async fn on_chain_committed(&mut self, chain: &Arc<Chain>) -> eyre::Result<BlockNumHash> {
+        if let Some(state_root) = self.client.state_roots.root_for(&chain.tip().header) {
+            info!(target: "t1::bridge", "Current stateRoot is [{}]", state_root);
+        }
+        self.verify_state_root(chain.tip())?;
+        self.client.recheck_anchored_if_due().await?;
+        if self.client.config().reorg_only {
+            let tip = chain.tip().num_hash();
+            debug!(target: "t1::bridge", number = tip.number, "Reorg-only mode, not anchoring");
+            self.client.status().update(|status| status.local_tip = Some(tip.number));
+            self.record_status();
+            return Ok(tip)
+        }
+        self.update_finalized()?;
+        let sink = self.client.sink();
+        let acknowledged =
+            notify_l1(&self.client, sink, &mut self.watermark, &mut self.batch, chain)
+                .await?
+                .unwrap_or_else(|| chain.tip().num_hash());
+        self.client.status().update(|status| status.local_tip = Some(acknowledged.number));
+        self.record_status();
+        Ok(acknowledged)
+    }
+
+    async fn on_reorg(&mut self, old: &Arc<Chain>, new: &Arc<Chain>) -> eyre::Result<()> {
+        let tip = new.tip().number;
+        let notification =
+            ExExNotification::ChainReorged { old: Arc::clone(old), new: Arc::clone(new) };
+        self.correct(&notification, tip).await
+    }
+
+    async fn on_revert(&mut self, old: &Arc<Chain>) -> eyre::Result<()> {
+        let tip = old.first().number.saturating_sub(1);
+        let notification = ExExNotification::ChainReverted { old: Arc::clone(old) };
+        self.correct(&notification, tip).await
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.batch.deadline().or_else(|| {
+            (self.client.config().anchor_finalized && !self.batch.is_empty())
+                .then(|| Instant::now() + FINALITY_POLL_INTERVAL)
+        })
+    }
+
+    async fn on_deadline(&mut self) -> eyre::Result<()> {
+        self.update_finalized()?;
+        if self.batch.is_full() || self.batch.is_due() {
+            flush_l1(&self.client, self.client.sink(), &mut self.watermark, &mut self.batch)
+                .await?;
+        }
+        self.record_status();
+        Ok(())
+    }
+
+    fn is_pending(&self) -> bool {
+        !self.batch.is_empty()
+    }
+
+    fn is_saturated(&self) -> bool {
+        self.batch.is_saturated()
+    }
+
+    async fn on_shutdown(&mut self) -> eyre::Result<()> {
+        self.update_finalized()?;
+        let sink = self.client.sink();
+        let result = flush_l1(&self.client, sink, &mut self.watermark, &mut self.batch).await;
+        self.record_status();
+        result?;
+        if !self.batch.is_empty() {
+            let queued = self.batch.len();
+            warn!(
+                target: "t1::bridge",
+                queued,
+                "Shutting down with state roots queued by the L1 circuit breaker or finality"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Logs the given decoded [`RollupEvent`]s, see
+/// [`ExExConfig::log_events`](super::config::ExExConfig::log_events).
+fn log_rollup_events(events: &[RollupEvent<'_>]) {
+    for (block, tx, contract, event) in events {
+        let name = match event {
+            CounterContractEvents::Incremented(_) => "Incremented",
+        };
+        let (number, tx_hash) = (block.number, tx.hash());
+        info!(
+            target: "t1::bridge",
+            number,
+            %tx_hash,
+            %contract,
+            event = name,
+            "Decoded Counter event"
+        );
+    }
+}
+
+/// Queues the state roots of the committed chain's blocks that contain at least one
+/// [`CounterContractEvents`] emitted by any of the Counter contracts, and anchors the queued state
+/// roots in the given [`StateRootSink`] once the [`RootBatch`] is full or due, see [`flush_l1`].
+///
+/// Every block is queued once, regardless of its number of events and the contracts that emitted
+/// them, as its state root covers the state of all contracts. State roots already held by the
+/// contract aren't submitted again, see [`flush_l1`]. Blocks at or below the [`Watermark`] have
+/// already been anchored and are skipped, as are blocks below the
+/// [`ExExConfig::start_height`](super::config::ExExConfig::start_height) and blocks whose number
+/// isn't a multiple of [`ExExConfig::anchor_every`](super::config::ExExConfig::anchor_every). The
+/// state roots of blocks less than [`ExExConfig::min_depth`](super::config::ExExConfig::min_depth)
+/// below the committed tip stay queued, until a later commit buries them deep enough, see
+/// [`RootBatch::with_min_depth`]. The [`L1Metrics::anchor_gap`] is updated to the committed tip.
+/// With [`ExExConfig::log_events`](super::config::ExExConfig::log_events) the decoded events are
+/// logged first.
+///
+/// With [`ExExConfig::state_diffs`](super::config::ExExConfig::state_diffs), the state root of the
+/// tip is queued with the [`StateDiff`] of the whole chain as well, regardless of its events and of
+/// [`ExExConfig::anchor_every`](super::config::ExExConfig::anchor_every), so the
+/// diffs of consecutive commits chain up.
+///
+/// Returns the height the chain is acknowledged up to, which is its tip, including skipped blocks,
+/// or `None` for an empty chain. State roots still queued in the batch are acknowledged as well,
+/// their height isn't finished while the [`StateRootAnchor`] is pending.
+pub(super) async fn notify_l1<T: Transport + Clone>(
+    client: &L1Client<T>,
+    sink: &dyn StateRootSink,
+    watermark: &mut Watermark,
+    batch: &mut RootBatch,
+    chain: &Chain,
+) -> Result<Option<BlockNumHash>, L1SubmitError> {
+    if chain.is_empty() {
+        debug!(target: "t1::bridge", "Committed chain is empty, nothing to anchor");
+        return Ok(None)
+    }
+
+    let config = client.config();
+    let events =
+        decode_chain_into_rollup_events(chain, &config.counter_contracts, client.metrics())?;
+    if config.log_events {
+        log_rollup_events(&events);
+    }
+
+    let mut blocks = events
+        .iter()
+        .filter(|(_, _, _, event)| matches!(event, CounterContractEvents::Incremented(..)))
+        .map(|(block, _, _, _)| *block)
+        .collect::<Vec<_>>();
+    blocks.dedup_by_key(|block| block.number);
+
+    for block in blocks {
+        if block.number < config.start_height {
+            let number = block.number;
+            debug!(
+                target: "t1::bridge",
+                number,
+                start_height = config.start_height,
+                "Block below the start height"
+            );
+            continue
+        }
+        if block.number % config.anchor_every.max(1) != 0 {
+            let number = block.number;
+            debug!(
+                target: "t1::bridge",
+                number,
+                anchor_every = config.anchor_every,
+                "Block not scheduled for anchoring"
+            );
+            continue
+        }
+        if watermark.is_anchored(block.number) {
+            let number = block.number;
+            info!(
+                target: "t1::bridge",
+                number,
+                watermark = ?watermark.height(),
+                "State root already anchored"
+            );
+            continue
+        }
+        if let Some(state_root) = client.root_to_anchor(&block.header)? {
+            batch.push(block.number, state_root);
+        }
+    }
+    if config.state_diffs {
+        let tip = chain.tip();
+        if tip.number >= config.start_height && !watermark.is_anchored(tip.number) {
+            if let Some(state_root) = client.root_to_anchor(&tip.header)? {
+                batch.push_diff(tip.number, state_root, StateDiff::from_chain(chain));
+            }
+        }
+    }
+
+    batch.set_tip(chain.tip().number);
+    if batch.is_full() || batch.is_due() {
+        flush_l1(client, sink, watermark, batch).await?;
+    }
+    record_anchor_gap(client.metrics(), chain.tip().number, watermark);
+
+    Ok(Some(chain.tip().num_hash()))
+}
+
+/// Anchors all state roots ready in the [`RootBatch`] in the given [`StateRootSink`], usually the
+/// [`L1Client::sink`], in transactions of at most the batch size, advancing the [`Watermark`] after
+/// every confirmed transaction, see [`L1Client::submit_state_roots_to`].
+///
+/// A transaction is skipped if the contract already holds the state root of its last block, see
+/// [`L1Client::is_anchored_on_chain`], or with
+/// [`ExExConfig::dedup_state_roots`](super::config::ExExConfig::dedup_state_roots) if all of its
+/// state roots are identical to the last submitted one, e.g. of empty blocks. The contract is
+/// checked after the random [`submit_jitter`], so of multiple nodes anchoring the same chain only
+/// the first one submits.
+///
+/// The latency of every anchored state root is recorded from the time it was queued at.
+///
+/// With the [`CircuitBreaker`](super::client::CircuitBreaker) enabled, the state roots of a failed
+/// submission stay queued, and nothing is submitted while the breaker is open. Otherwise a failed
+/// submission is an error.
+///
+/// The state roots are anchored in the order of their block numbers, and a transaction is only
+/// sent once the previous one is confirmed, so the heights anchored on L1 increase monotonically
+/// across retries. Queued state roots at or below the [`Watermark`] would be anchored out of
+/// order, e.g. those still queued by the open breaker when a reorg correction anchored a later
+/// block, so they are dropped instead.
+///
+/// The flush is cancellation safe, e.g. when it's cut short by the shutdown timeout. The state
+/// roots of a transaction are only removed from the batch once, in this order, the transaction is
+/// confirmed, they are recorded in the [`SubmissionHistory`](super::history::SubmissionHistory) and
+/// the [`Watermark`] is advanced past them, with no await point in between. Cancelling the flush
+/// thus leaves them queued above the watermark, and their height unfinished. Their transaction may
+/// still be mined: when they are flushed again, or redelivered after a restart, they are skipped if
+/// the contract already holds them, and anchored again otherwise.
+pub(super) async fn flush_l1<T: Transport + Clone>(
+    client: &L1Client<T>,
+    sink: &dyn StateRootSink,
+    watermark: &mut Watermark,
+    batch: &mut RootBatch,
+) -> Result<(), L1SubmitError> {
+    if let Some(height) = watermark.height() {
+        let dropped = batch.remove_through(height);
+        if dropped > 0 {
+            warn!(
+                target: "t1::bridge",
+                dropped,
+                watermark = height,
+                "Dropped queued state roots at or below the watermark, out of order"
+            );
+        }
+    }
+    while batch.ready() > 0 {
+        let state = client.breaker().lock().state();
+        match state {
+            BreakerState::Closed => {}
+            BreakerState::Open => {
+                debug!(
+                    target: "t1::bridge",
+                    queued = batch.len(),
+                    "L1 circuit breaker open, keeping state roots queued"
+                );
+                return Ok(())
+            }
+            BreakerState::HalfOpen => {
+                info!(target: "t1::bridge", "L1 circuit breaker half-open, probing L1")
+            }
+        }
+
+        let queued = batch.next_batch();
+        let roots =
+            queued.iter().map(|(number, state_root, _)| (*number, *state_root)).collect::<Vec<_>>();
+        let (number, state_root) = roots[roots.len() - 1];
+        if client.config().dedup_state_roots &&
+            client
+                .last_state_root()
+                .is_some_and(|last| roots.iter().all(|(_, root)| *root == last))
+        {
+            info!(
+                target: "t1::bridge",
+                number,
+                %state_root,
+                "State root unchanged since the last submission, skipping"
+            );
+            watermark.update(number)?;
+            batch.remove(queued.len());
+            continue
+        }
+        let jitter = submit_jitter(client.config().submit_jitter);
+        if !jitter.is_zero() {
+            debug!(target: "t1::bridge", number, ?jitter, "Delaying L1 submission");
+            tokio::time::sleep(jitter).await;
+        }
+        if client.is_anchored_on_chain(number, state_root).await {
+            info!(
+                target: "t1::bridge",
+                number,
+                %state_root,
+                "State root already anchored on L1, skipping submission"
+            );
+            watermark.update(number)?;
+            batch.remove(queued.len());
+            continue
+        }
+        let submitted = match batch.diff(number).cloned() {
+            Some(diff) => client.submit_state_diff_to(sink, number, state_root, &diff).await,
+            None => client.submit_state_roots_to(sink, &roots).await,
+        };
+        let tx_id = match submitted {
+            Ok(Some(tx_id)) => tx_id,
+            Ok(None) => {
+                batch.remove(queued.len());
+                continue
+            }
+            Err(err) => {
+                let mut breaker = client.breaker().lock();
+                if !breaker.is_enabled() {
+                    return Err(err)
+                }
+
+                let opened = breaker.record_failure();
+                let failures = breaker.failures();
+                if opened {
+                    let cooldown = client.config().breaker_cooldown;
+                    warn!(
+                        target: "t1::bridge",
+                        %err,
+                        rpc = %client.config().l1_rpc,
+                        failures,
+                        ?cooldown,
+                        "L1 circuit breaker opened, pausing submissions"
+                    );
+                    batch.pause_until(breaker.cooldown_end().unwrap_or_else(Instant::now));
+                } else {
+                    warn!(
+                        target: "t1::bridge",
+                        %err,
+                        rpc = %client.config().l1_rpc,
+                        failures,
+                        "Failed to anchor state roots, keeping them queued"
+                    );
+                }
+                return Ok(())
+            }
+        };
+        if state == BreakerState::HalfOpen {
+            info!(target: "t1::bridge", "L1 circuit breaker closed");
+        }
+        client.breaker().lock().record_success();
+
+        let metrics = client.metrics();
+        metrics.state_roots_submitted_total.increment(roots.len() as u64);
+        for (_, _, queued_at) in &queued {
+            metrics.submission_latency_seconds.record(queued_at.elapsed());
+        }
+
+        info!(
+            target: "t1::bridge",
+            roots = roots.len(),
+            number,
+            %state_root,
+            "I notifed L1 with new state root. txId = [{:#x}]",
+            tx_id
+        );
+        watermark.update(number)?;
+        batch.remove(queued.len());
+    }
+
+    Ok(())
+}
+
+/// Corrects the state root anchored on L1 after a chain reorg or revert.
+///
+/// See [`l1_correction`] for the state root that gets submitted, unless its block is below the
+/// [`ExExConfig::start_height`](super::config::ExExConfig::start_height). The correction is
+/// submitted regardless of [`ExExConfig::anchor_every`](super::config::ExExConfig::anchor_every),
+/// as the state root anchored last may have been reorged out, and the blocks skipped since then are
+/// only covered by the state root of the new tip.
+async fn revert_l1<T: Transport + Clone, P: HeaderProvider>(
+    client: &L1Client<T>,
+    watermark: &mut Watermark,
+    provider: &P,
+    notification: &ExExNotification,
+) -> eyre::Result<()> {
+    let state_roots = client.state_roots.as_ref();
+    let Some((number, state_root)) = l1_correction(provider, state_roots, notification)? else {
+        return Ok(())
+    };
+    let start_height = client.config().start_height;
+    if number < start_height {
+        info!(
+            target: "t1::bridge",
+            number,
+            start_height,
+            "Not correcting the L1 state root below the start height"
+        );
+        return Ok(())
+    }
+
+    let Some(tx_id) = client.submit_state_roots(&[(number, state_root)]).await? else {
+        return Ok(())
+    };
+    info!(
+        target: "t1::bridge",
+        number,
+        %state_root,
+        "I corrected the L1 state root. txId = [{:#x}]",
+        tx_id
+    );
+    watermark.update(number)?;
+    client.metrics().state_roots_submitted_total.increment(1);
+    record_anchor_gap(client.metrics(), number, watermark);
+
+    Ok(())
+}
+
+/// Returns a random delay of at most `max`, uniformly distributed, before an L1 submission.
+fn submit_jitter(max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO
+    }
+    rand::thread_rng().gen_range(Duration::ZERO..=max)
+}
+
+/// Reports the [`Watermark`] and the number of state roots queued in the [`RootBatch`] to the
+/// [`BridgeStatus`](super::rpc::BridgeStatus).
+fn record_status(status: &BridgeStatusHandle, watermark: &Watermark, batch: &RootBatch) {
+    status.update(|status| {
+        status.anchored_height = watermark.height();
+        status.pending_submissions = batch.len();
+    });
+}
+
+/// Sets the [`L1Metrics::anchor_gap`] to the number of blocks between the local `tip` and the
+/// [`Watermark`].
+fn record_anchor_gap(metrics: &L1Metrics, tip: BlockNumber, watermark: &Watermark) {
+    let gap = tip.saturating_sub(watermark.height().unwrap_or_default());
+    metrics.anchor_gap.set(gap as f64);
+}
+
+/// Returns the block number and state root L1 has to be corrected to after the given
+/// notification, if any, as returned by the `state_roots` for the header of the block.
+///
+/// - On [`ExExNotification::ChainReorged`] this is the state root of the new chain's tip.
+/// - On [`ExExNotification::ChainReverted`] this is the state root of the parent of the first
+///   reverted block, which is read from the provider.
+/// - Committed chains don't require a correction, they are handled by [`notify_l1`].
+fn l1_correction<P: HeaderProvider>(
+    provider: &P,
+    state_roots: &dyn StateRootProvider,
+    notification: &ExExNotification,
+) -> eyre::Result<Option<(BlockNumber, B256)>> {
+    match notification {
+        ExExNotification::ChainCommitted { .. } => Ok(None),
+        ExExNotification::ChainReorged { new, .. } => {
+            Ok(Some((new.tip().number, root_for(state_roots, &new.tip().header)?)))
+        }
+        ExExNotification::ChainReverted { old } => {
+            let parent = old.first().number.saturating_sub(1);
+            let header = provider
+                .header_by_number(parent)?
+                .ok_or_else(|| eyre::eyre!("header of reverted chain parent {parent} not found"))?;
+            Ok(Some((parent, root_for(state_roots, &header)?)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        client::MockStateRootSink,
+        config::{AnchoredRoot, ExExConfig, HeaderStateRoot, L1_BATCH_SIZE},
+        contract::StateRootCall,
+        test_utils::{
+            access_list, chain_with_log, counter_chain, incremented_log, metric_values, test_env,
+            CapturedLogs, MockTransport,
+        },
+        watermark::L1_WATERMARK_FILE,
+        AccountDiff,
+        StateRootAbi::{
+            self, changeStateRootAtCall, changeStateRootBatchCall, changeStateRootCall,
+            changeStateRootWithDiffCall,
+        },
+        StorageDiff,
+    };
+    use alloy_primitives::{Address, Bytes, Selector, TxHash, U256};
+    use alloy_rpc_types::AccessList;
+    use alloy_sol_types::{SolCall, SolValue};
+    use alloy_transport::{TransportErrorKind, TransportResult};
+    use eyre::OptionExt;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use reth_execution_types::ExecutionOutcome;
+    use reth_exex_test_utils::test_exex_context;
+    use reth_primitives::Header;
+    use reth_provider::{test_utils::MockEthProvider, CanonChainTracker};
+    use reth_revm::{db::BundleState, primitives::AccountInfo};
+    use reth_testing_utils::generators::{self, random_block, BlockParams};
+    use reth_tracing::tracing_subscriber::EnvFilter;
+    use serde_json::json;
+    use std::{path::PathBuf, sync::atomic::Ordering};
+    use url::Url;
+
+    #[test]
+    fn test_l1_correction_on_reorg() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let provider = MockEthProvider::default();
+
+        let block_1 = random_block(&mut rng, 1, Default::default());
+        let old_block_2 = random_block(
+            &mut rng,
+            2,
+            BlockParams { parent: Some(block_1.hash()), ..Default::default() },
+        )
+        .seal_with_senders()
+        .ok_or_eyre("failed to recover senders")?;
+        let new_block_2 = random_block(
+            &mut rng,
+            2,
+            BlockParams { parent: Some(block_1.hash()), ..Default::default() },
+        )
+        .seal_with_senders()
+        .ok_or_eyre("failed to recover senders")?;
+        let new_block_3 = random_block(
+            &mut rng,
+            3,
+            BlockParams { parent: Some(new_block_2.hash()), ..Default::default() },
+        )
+        .seal_with_senders()
+        .ok_or_eyre("failed to recover senders")?;
+
+        let notification = ExExNotification::ChainReorged {
+            old: Arc::new(Chain::new(vec![old_block_2], Default::default(), None)),
+            new: Arc::new(Chain::new(
+                vec![new_block_2, new_block_3.clone()],
+                Default::default(),
+                None,
+            )),
+        };
+
+        assert_eq!(
+            l1_correction(&provider, &HeaderStateRoot, &notification)?,
+            Some((3, new_block_3.header.state_root))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_l1_correction_on_revert() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let provider = MockEthProvider::default();
+
+        let block_1 = random_block(&mut rng, 1, Default::default());
+        provider.add_header(block_1.hash(), block_1.header.header().clone());
+        let block_2 = random_block(
+            &mut rng,
+            2,
+            BlockParams { parent: Some(block_1.hash()), ..Default::default() },
+        )
+        .seal_with_senders()
+        .ok_or_eyre("failed to recover senders")?;
+        let block_3 = random_block(
+            &mut rng,
+            3,
+            BlockParams { parent: Some(block_2.hash()), ..Default::default() },
+        )
+        .seal_with_senders()
+        .ok_or_eyre("failed to recover senders")?;
+
+        let notification = ExExNotification::ChainReverted {
+            old: Arc::new(Chain::new(vec![block_2, block_3], Default::default(), None)),
+        };
+
+        // L1 is rolled back to the parent of the first reverted block
+        assert_eq!(
+            l1_correction(&provider, &HeaderStateRoot, &notification)?,
+            Some((1, block_1.header.state_root))
+        );
+
+        // The parent header is required to compute the correction
+        let provider = MockEthProvider::default();
+        assert!(l1_correction(&provider, &HeaderStateRoot, &notification).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_l1_correction_on_commit() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let provider = MockEthProvider::default();
+
+        let block = random_block(&mut rng, 1, Default::default())
+            .seal_with_senders()
+            .ok_or_eyre("failed to recover senders")?;
+        let notification = ExExNotification::ChainCommitted {
+            new: Arc::new(Chain::new(vec![block], Default::default(), None)),
+        };
+
+        assert_eq!(l1_correction(&provider, &HeaderStateRoot, &notification)?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_reuses_client() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        // Every chain is submitted through the contract constructed with the client
+        for number in 1..=3 {
+            let chain = counter_chain(&mut rng, number..=number, 1, config.counter_contracts[0])?;
+            notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        }
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_once_per_chain() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        let chain = counter_chain(&mut rng, 1..=1, 5, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        // Events of other contracts don't trigger a submission
+        let chain = counter_chain(&mut rng, 2..=2, 5, Address::random())?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_skips_anchored_after_restart() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(L1_WATERMARK_FILE);
+
+        let chain_1 = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        let chain_2 = counter_chain(&mut rng, 2..=2, 1, config.counter_contracts[0])?;
+
+        let mut watermark = Watermark::load(path.clone())?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+        assert_eq!(watermark.height(), None);
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain_1).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        // After a restart the same chain is re-delivered, but not submitted again
+        let mut watermark = Watermark::load(path)?;
+        assert_eq!(watermark.height(), Some(1));
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain_1).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+
+        // New chains are still submitted
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain_2).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 2);
+        assert_eq!(watermark.height(), Some(2));
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_flush_l1_circuit_breaker() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let cooldown = Duration::from_secs(60);
+        let config = ExExConfig {
+            max_retries: 0,
+            breaker_threshold: 2,
+            breaker_cooldown: cooldown,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::failing(3);
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        // A failed submission keeps the state root queued, with the breaker closed
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(client.breaker().lock().state(), BreakerState::Closed);
+        assert_eq!(batch.len(), 1);
+
+        // The second consecutive failure opens the breaker and pauses the queued state roots
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert_eq!(client.breaker().lock().state(), BreakerState::Open);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 2);
+        assert!(!batch.is_due());
+
+        // While open, state roots are queued without being submitted
+        let chain = counter_chain(&mut rng, 2..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 2);
+        assert_eq!(batch.len(), 2);
+
+        // After the cooldown, a failed probe opens the breaker again
+        tokio::time::advance(cooldown).await;
+        assert_eq!(client.breaker().lock().state(), BreakerState::HalfOpen);
+        assert!(batch.is_due());
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert_eq!(client.breaker().lock().state(), BreakerState::Open);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 3);
+
+        // A successful probe closes the breaker and anchors the queued state roots in order
+        tokio::time::advance(cooldown).await;
+        assert_eq!(client.breaker().lock().state(), BreakerState::HalfOpen);
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert_eq!(client.breaker().lock().state(), BreakerState::Closed);
+        assert_eq!(client.breaker().lock().failures(), 0);
+        assert!(batch.is_empty());
+        assert_eq!(watermark.height(), Some(2));
+        let txs = transport.sent_transactions();
+        let call = changeStateRootBatchCall::abi_decode(txs[3].input(), true)?;
+        assert_eq!(call.numbers_, vec![U256::from(1), U256::from(2)]);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_flush_l1_monotonic_order() -> eyre::Result<()> {
+        let cooldown = Duration::from_secs(60);
+        let config = ExExConfig {
+            max_retries: 1,
+            retry_base_delay: Duration::from_millis(1),
+            breaker_threshold: 1,
+            breaker_cooldown: cooldown,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::failing(2);
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(1, Duration::ZERO);
+        let root = |number: u64| B256::with_last_byte(number as u8);
+
+        // The submission of block 5 fails along with its retry, opening the breaker
+        batch.push(5, root(5));
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert_eq!(client.breaker().lock().state(), BreakerState::Open);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 2);
+
+        // Meanwhile block 7 is queued, and a failed submission of block 3 is queued again after it
+        batch.push(7, root(7));
+        batch.push(3, root(3));
+
+        // Once the breaker lets a probe through, the blocks are anchored in order, each one only
+        // after the previous one is confirmed
+        tokio::time::advance(cooldown).await;
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert!(batch.is_empty());
+        assert_eq!(watermark.height(), Some(7));
+        let anchored = transport.anchored_state_roots()?[2..].concat();
+        assert_eq!(anchored, vec![(3, root(3)), (5, root(5)), (7, root(7))]);
+
+        // A state root queued at or below the watermark would be anchored out of order, so it's
+        // dropped without being sent
+        batch.push(6, root(6));
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert!(batch.is_empty());
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 5);
+        assert_eq!(watermark.height(), Some(7));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_batches_chain() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        let chain = counter_chain(&mut rng, 1..=3, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+
+        // The state roots of all blocks are anchored in a single call, in order
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 1);
+        let call = changeStateRootBatchCall::abi_decode(txs[0].input(), true)?;
+        assert_eq!(call.numbers_, vec![U256::from(1), U256::from(2), U256::from(3)]);
+        assert_eq!(
+            call.stateRoots_,
+            chain.blocks().values().map(|block| block.header.state_root).collect::<Vec<_>>()
+        );
+        assert_eq!(watermark.height(), Some(3));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_state_root_at() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        for legacy in [false, true] {
+            let config =
+                ExExConfig { legacy_state_root: legacy, ..ExExConfig::from_env_with(test_env)? };
+            let transport = MockTransport::default();
+            let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+            let dir = tempfile::tempdir()?;
+            let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+            let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+            let chain = counter_chain(&mut rng, 7..=7, 1, config.counter_contracts[0])?;
+            let state_root = chain.tip().header.state_root;
+            notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+
+            // The state root is anchored with its block number, unless the legacy method is
+            // configured
+            let txs = transport.sent_transactions();
+            assert_eq!(txs.len(), 1);
+            if legacy {
+                let call = changeStateRootCall::abi_decode(txs[0].input(), true)?;
+                assert_eq!(call.stateRoot_.as_ref(), state_root.as_slice());
+            } else {
+                let call = changeStateRootAtCall::abi_decode(txs[0].input(), true)?;
+                assert_eq!(call.number_, U256::from(7));
+                assert_eq!(call.stateRoot_, state_root);
+            }
+            assert_eq!(watermark.height(), Some(7));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_flush_l1_cancelled() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport { unmined: usize::MAX, ..Default::default() };
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::from_secs(3600));
+
+        // The flush is cancelled while waiting for its transaction to be mined, which leaves the
+        // state roots queued and the watermark where it was
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        let flush = flush_l1(&client, client.sink(), &mut watermark, &mut batch);
+        assert!(tokio::time::timeout(Duration::from_secs(1), flush).await.is_err());
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(watermark.height(), None);
+        assert_eq!(Watermark::load(dir.path().join(L1_WATERMARK_FILE))?.height(), None);
+
+        // After a restart the chain is redelivered, and once the cancelled transaction was mined
+        // its state roots are skipped instead of anchored again
+        let anchored = Bytes::copy_from_slice(chain.tip().header.state_root.as_slice());
+        let output = StateRootAbi::stateRootCall::abi_encode_returns(&(anchored,));
+        let transport = MockTransport::with_call_output(output);
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert!(batch.is_empty());
+        assert_eq!(watermark.height(), Some(2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flush_l1_batches() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(2, Duration::from_secs(3600));
+
+        // A partial batch is queued until it's due
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert!(batch.deadline().is_some());
+
+        // Once the batch is full, the queued state roots are anchored in calls of at most the batch
+        // size
+        let chain = counter_chain(&mut rng, 2..=3, 1, config.counter_contracts[0])?;
+        let state_root_3 = chain.tip().header.state_root;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 2);
+        assert!(batch.is_empty());
+        assert_eq!(watermark.height(), Some(3));
+
+        // A partial batch is anchored on shutdown with a single state root call
+        let chain = counter_chain(&mut rng, 4..=4, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 2);
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert_eq!(watermark.height(), Some(4));
+
+        let txs = transport.sent_transactions();
+        let call = changeStateRootBatchCall::abi_decode(txs[0].input(), true)?;
+        assert_eq!(call.numbers_, vec![U256::from(1), U256::from(2)]);
+        let call = changeStateRootAtCall::abi_decode(txs[1].input(), true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(3), state_root_3));
+        let call = changeStateRootAtCall::abi_decode(txs[2].input(), true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(4), chain.tip().header.state_root));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reorg_invalidates_queued_state_roots() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            batch_flush_interval: Duration::from_secs(3600),
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut anchor = StateRootAnchor::new(client, watermark, MockEthProvider::default());
+
+        // The state roots of the committed blocks are queued, not submitted yet
+        let chain = counter_chain(&mut rng, 1..=3, 1, config.counter_contracts[0])?;
+        let state_root_1 = chain.blocks()[&1].header.state_root;
+        anchor.on_chain_committed(&Arc::new(chain.clone())).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert_eq!(anchor.batch.len(), 3);
+
+        // The reorg replaces blocks 2 and 3, whose queued state roots are dropped. The state root
+        // below the fork is still anchored, followed by the one of the new tip
+        let old = counter_chain(&mut rng, 2..=3, 1, config.counter_contracts[0])?;
+        let new = counter_chain(&mut rng, 2..=4, 1, config.counter_contracts[0])?;
+        let new_tip = new.tip().header.state_root;
+        anchor.on_reorg(&Arc::new(old), &Arc::new(new)).await?;
+        assert_eq!(
+            transport.anchored_state_roots()?,
+            vec![vec![(1, state_root_1)], vec![(4, new_tip)]]
+        );
+        assert!(!anchor.is_pending());
+        assert_eq!(anchor.watermark.height(), Some(4));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verify_root_locally() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            verify_root_locally: true,
+            batch_flush_interval: Duration::ZERO,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let provider = MockEthProvider::default();
+        let mut anchor = StateRootAnchor::new(client, watermark, provider.clone());
+
+        // The recomputed state root differs from the header, so nothing is submitted
+        provider.add_state_root(B256::repeat_byte(0xba));
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        let err = anchor.on_chain_committed(&Arc::new(chain)).await.unwrap_err();
+        assert!(err.to_string().contains("doesn't match the local state"), "{err}");
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert!(!anchor.is_pending());
+        assert_eq!(anchor.watermark.height(), None);
+
+        // The random blocks have empty state roots, which the mock recomputes without an override
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        anchor.on_chain_committed(&Arc::new(chain)).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+        assert_eq!(anchor.watermark.height(), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_submit_jitter() {
+        assert_eq!(submit_jitter(Duration::ZERO), Duration::ZERO);
+
+        let max = Duration::from_millis(250);
+        let delays = (0..1000).map(|_| submit_jitter(max)).collect::<Vec<_>>();
+        assert!(delays.iter().all(|delay| *delay <= max));
+        // The delays are spread over the range rather than fixed
+        assert!(delays.iter().any(|delay| *delay < max / 2));
+        assert!(delays.iter().any(|delay| *delay > max / 2));
+    }
+
+    #[tokio::test]
+    async fn test_anchor_finalized() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            anchor_finalized: true,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let (ctx, _handle) = test_exex_context().await?;
+        let provider = ctx.provider().clone();
+        let mut anchor = StateRootAnchor::new(client, watermark, provider.clone());
+
+        // Nothing is finalized yet, so the state roots are held back
+        let chain = counter_chain(&mut rng, 1..=3, 1, config.counter_contracts[0])?;
+        let state_roots = chain
+            .blocks()
+            .values()
+            .map(|block| (block.number, block.header.state_root))
+            .collect::<Vec<_>>();
+        anchor.on_chain_committed(&Arc::new(chain.clone())).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert!(anchor.is_pending());
+        assert!(anchor.deadline().is_some());
+
+        // Once block 2 is finalized, the next notification anchors the state roots up to it
+        provider.set_finalized(chain.blocks()[&2].header.clone());
+        let next = counter_chain(&mut rng, 4..=4, 1, config.counter_contracts[0])?;
+        anchor.on_chain_committed(&Arc::new(next.clone())).await?;
+        assert_eq!(transport.anchored_state_roots()?, vec![state_roots[..2].to_vec()]);
+        assert_eq!(anchor.watermark.height(), Some(2));
+        assert_eq!(anchor.batch.len(), 2);
+
+        // Unfinalized blocks are reorged without a correction, the new chain is queued instead
+        let new = counter_chain(&mut rng, 4..=5, 1, config.counter_contracts[0])?;
+        anchor.on_reorg(&Arc::new(next), &Arc::new(new.clone())).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+        assert_eq!(anchor.batch.len(), 3);
+
+        // Finality advancing without a notification is picked up at the deadline
+        provider.set_finalized(new.tip().header.clone());
+        anchor.on_deadline().await?;
+        let mut finalized = vec![state_roots[2]];
+        let roots = new.blocks().values().map(|block| (block.number, block.header.state_root));
+        finalized.extend(roots);
+        assert_eq!(transport.anchored_state_roots()?[1], finalized);
+        assert_eq!(anchor.watermark.height(), Some(5));
+        assert!(!anchor.is_pending());
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_notify_l1_submit_interval() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let interval = Duration::from_secs(1);
+        let mut batch =
+            RootBatch::new(L1_BATCH_SIZE, Duration::ZERO).with_submit_interval(interval);
+
+        // The first commit is anchored right away and starts the interval
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+        let submitted_at = Instant::now();
+
+        // A burst of commits within the interval is coalesced into the latest state root
+        let mut state_root = B256::ZERO;
+        for number in 2..=4 {
+            let chain = counter_chain(&mut rng, number..=number, 1, config.counter_contracts[0])?;
+            state_root = chain.tip().header.state_root;
+            notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+            tokio::time::advance(Duration::from_millis(100)).await;
+        }
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch.deadline(), Some(submitted_at + interval));
+        assert!(!batch.is_due());
+
+        // After the burst, the latest state root is anchored once the interval has passed
+        tokio::time::sleep_until(batch.deadline().unwrap()).await;
+        assert!(batch.is_due());
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 2);
+        assert_eq!(watermark.height(), Some(4));
+        let txs = transport.sent_transactions();
+        let call = changeStateRootAtCall::abi_decode(txs[1].input(), true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(4), state_root));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_access_list() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let transport = MockTransport::default();
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        // Without the toggle, no access list is created
+        let config = ExExConfig::from_env_with(test_env)?;
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_createAccessList"), 0);
+
+        let config = ExExConfig { access_list: true, ..config };
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let chain = counter_chain(&mut rng, 2..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_createAccessList"), 1);
+
+        let txs = transport.sent_transactions();
+        assert_eq!(txs[0].access_list(), Some(&AccessList::default()));
+        assert_eq!(txs[1].access_list(), Some(&access_list()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flush_l1_dedup_state_roots() -> eyre::Result<()> {
+        let state_root = B256::random();
+        for (dedup, sends) in [(true, 1), (false, 2)] {
+            let config =
+                ExExConfig { dedup_state_roots: dedup, ..ExExConfig::from_env_with(test_env)? };
+            let transport = MockTransport::default();
+            let client = L1Client::with_transport(config, transport.clone()).await?;
+            let dir = tempfile::tempdir()?;
+            let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+            let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+            // Two consecutive blocks with the same state root, e.g. empty ones
+            for number in 1..=2 {
+                batch.push(number, state_root);
+                flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+            }
+            assert_eq!(transport.calls("eth_sendRawTransaction"), sends, "dedup: {dedup}");
+            assert_eq!(watermark.height(), Some(2));
+            assert_eq!(client.last_state_root(), Some(state_root));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_dry_run() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig { dry_run: true, ..ExExConfig::from_env_with(test_env)? };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        let chain = counter_chain(&mut rng, 1..=3, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+
+        // Nothing is sent to L1, and nothing is considered anchored
+        assert!(transport.requests.lock().unwrap().is_empty());
+        assert!(batch.is_empty());
+        assert_eq!(watermark.height(), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_custom_state_root_method() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let dir = tempfile::tempdir()?;
+        let abi = dir.path().join("abi.json");
+        std::fs::write(
+            &abi,
+            json!([{
+                "type": "function",
+                "name": "submitStateRoot",
+                "inputs": [{ "name": "root", "type": "bytes", "internalType": "bytes" }],
+                "outputs": [],
+                "stateMutability": "nonpayable",
+            }])
+            .to_string(),
+        )?;
+        let config = ExExConfig {
+            state_root_method: "submitStateRoot".to_string(),
+            state_root_abi: Some(abi),
+            legacy_state_root: true,
+            batch_size: 1,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+
+        // The state root is anchored with the configured method, encoded like `changeStateRoot`
+        let txs = transport.sent_transactions();
+        let input = txs[0].input();
+        assert_eq!(input[..4], alloy_primitives::keccak256("submitStateRoot(bytes)")[..4]);
+        let call = changeStateRootCall::abi_decode_raw(&input[4..], true)?;
+        assert_eq!(call.stateRoot_.as_ref(), chain.tip().header.state_root.as_slice());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_state_diff() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig { state_diffs: true, ..ExExConfig::from_env_with(test_env)? };
+        assert_eq!(config.state_root_selector()?, Selector::new(changeStateRootCall::SELECTOR));
+
+        // The first account is created with a storage slot, the second one is destroyed
+        let (created, destroyed) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let info = AccountInfo {
+            balance: U256::from(100),
+            nonce: 1,
+            code_hash: B256::repeat_byte(0xc0),
+            code: None,
+        };
+        let storage = [
+            (U256::from(1), (U256::ZERO, U256::from(5))),
+            (U256::from(2), (U256::from(7), U256::from(7))),
+        ];
+        let bundle = BundleState::new(
+            [
+                (destroyed, Some(info.clone()), None, Default::default()),
+                (created, None, Some(info.clone()), storage.into_iter().collect()),
+            ],
+            [[(destroyed, Some(Some(info)), vec![]), (created, Some(None), vec![])]],
+            vec![],
+        );
+        let block =
+            random_block(&mut rng, 1, BlockParams { tx_count: Some(0), ..Default::default() })
+                .seal_with_senders()
+                .ok_or_eyre("failed to recover senders")?;
+        let outcome = ExecutionOutcome::new(bundle, vec![vec![]].into(), 1, vec![]);
+        let chain = Chain::new([block], outcome, None);
+
+        // Accounts are ordered by address, and only the changed slot is included
+        let diff = StateDiff::from_chain(&chain);
+        let expected = vec![
+            AccountDiff {
+                account: created,
+                destroyed: false,
+                nonce: 1,
+                balance: U256::from(100),
+                codeHash: B256::repeat_byte(0xc0),
+                storage: vec![StorageDiff {
+                    slot: B256::with_last_byte(1),
+                    value: B256::with_last_byte(5),
+                }],
+            },
+            AccountDiff {
+                account: destroyed,
+                destroyed: true,
+                nonce: 0,
+                balance: U256::ZERO,
+                codeHash: B256::ZERO,
+                storage: vec![],
+            },
+        ];
+        assert_eq!(diff.0, expected);
+        assert_eq!(Vec::<AccountDiff>::abi_decode(&diff.encode(), true)?, expected);
+
+        // The state root of the tip is anchored with the diff, although it has no Counter events
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 1);
+        let call = changeStateRootWithDiffCall::abi_decode(txs[0].input(), true)?;
+        assert_eq!(call.number_, U256::from(1));
+        assert_eq!(call.stateRoot_, chain.tip().header.state_root);
+        assert_eq!(call.diff_, diff.encode());
+        assert_eq!(watermark.height(), Some(1));
+        assert!(batch.diff(1).is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_selector_override() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let selector = Selector::new([0xde, 0xad, 0xbe, 0xef]);
+        let prefix = Bytes::from_static(&[0x01, 0x02]);
+        // The ABI isn't read with the override
+        let config = ExExConfig {
+            selector_override: Some(selector),
+            calldata_prefix: prefix.clone(),
+            state_root_abi: Some(PathBuf::from("/nonexistent/abi.json")),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+
+        // The calldata starts with the override selector and prefix, followed by the parameters
+        // of `changeStateRootAt`
+        let txs = transport.sent_transactions();
+        let input = txs[0].input();
+        assert_eq!(input[..4], selector[..]);
+        assert_eq!(input[4..6], prefix[..]);
+        let call = changeStateRootAtCall::abi_decode_raw(&input[6..], true)?;
+        assert_eq!((call.number_, call.stateRoot_), (U256::from(1), chain.tip().header.state_root));
+
+        // The override has to be exactly 4 bytes, and the prefix requires it
+        let env = |key: &str, value: &str| {
+            let entry = (key.to_string(), value.to_string());
+            ExExConfig::from_env_with(move |k| {
+                if k == entry.0 {
+                    Some(entry.1.clone())
+                } else {
+                    test_env(k)
+                }
+            })
+        };
+        assert!(env("STATE_ROOT_SELECTOR", "0x123456").is_err());
+        assert!(env("STATE_ROOT_SELECTOR", "0x1234567890").is_err());
+        assert!(env("STATE_ROOT_SELECTOR", "0xdeadbeef")?.selector_override.is_some());
+        let err = env("STATE_ROOT_CALLDATA_PREFIX", "0x0102").unwrap_err();
+        assert!(err.to_string().contains("requires STATE_ROOT_SELECTOR"), "{err}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_metrics() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            retry_base_delay: Duration::from_millis(1),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::failing(1);
+        let mut client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        client.metrics = reth_metrics::metrics::with_local_recorder(&recorder, L1Metrics::default);
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::from_secs(3600));
+
+        // Queued state roots aren't anchored yet
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.state_roots_submitted_total"], DebugValue::Counter(0));
+        assert_eq!(values["exex.l1.anchor_gap"], DebugValue::Gauge(2.0.into()));
+
+        // The first attempt fails, the retry anchors both state roots
+        flush_l1(&client, client.sink(), &mut watermark, &mut batch).await?;
+        let chain = counter_chain(&mut rng, 3..=3, 0, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        let values = metric_values(&snapshotter);
+        assert_eq!(values["exex.l1.state_roots_submitted_total"], DebugValue::Counter(2));
+        assert_eq!(values["exex.l1.submission_failures_total"], DebugValue::Counter(1));
+        assert_eq!(values["exex.l1.anchor_gap"], DebugValue::Gauge(1.0.into()));
+        let DebugValue::Histogram(latencies) = &values["exex.l1.submission_latency_seconds"] else {
+            panic!("submission latency is not a histogram")
+        };
+        assert_eq!(latencies.len(), 2);
+
+        Ok(())
+    }
+
+    /// [`StateRootProvider`] committing to the state and receipts roots of the header.
+    #[derive(Debug)]
+    struct StateAndReceiptsRoot;
+
+    impl StateRootProvider for StateAndReceiptsRoot {
+        fn root_for(&self, header: &Header) -> Option<B256> {
+            Some(alloy_primitives::keccak256(
+                [header.state_root.as_slice(), header.receipts_root.as_slice()].concat(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_state_root_provider() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone())
+            .await?
+            .with_state_root_provider(Arc::new(StateAndReceiptsRoot));
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        // The root of the provider is anchored instead of the state root of the block
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        let header = chain.tip().header.header().clone();
+        let root = root_for(&StateAndReceiptsRoot, &header)?;
+        assert_ne!(root, header.state_root);
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.anchored_state_roots()?, vec![vec![(1, root)]]);
+
+        // So are the roots L1 is corrected to after a reorg
+        let new = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        let root = root_for(&StateAndReceiptsRoot, &new.tip().header)?;
+        let notification =
+            ExExNotification::ChainReorged { old: Arc::new(chain), new: Arc::new(new) };
+        let provider = MockEthProvider::default();
+        revert_l1(&client, &mut watermark, &provider, &notification).await?;
+        assert_eq!(transport.anchored_state_roots()?[1..], [vec![(2, root)]]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_anchored_root() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config =
+            ExExConfig { dedup_state_roots: false, ..ExExConfig::from_env_with(test_env)? };
+        // Blocks without withdrawals, as before Shanghai
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        let anchor = |config: ExExConfig| {
+            let chain = &chain;
+            async move {
+                let transport = MockTransport::default();
+                let client = L1Client::with_transport(config, transport.clone()).await?;
+                let dir = tempfile::tempdir()?;
+                let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+                let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+                let notified =
+                    notify_l1(&client, client.sink(), &mut watermark, &mut batch, chain).await;
+                assert!(batch.is_empty());
+                eyre::Ok((notified, transport.anchored_state_roots()?))
+            }
+        };
+
+        // The receipts roots are anchored instead of the state roots
+        let receipts = ExExConfig { anchored_root: AnchoredRoot::Receipts, ..config.clone() };
+        let (notified, anchored) = anchor(receipts).await?;
+        notified?;
+        let roots = chain.blocks().values().map(|block| (block.number, block.receipts_root));
+        assert_eq!(anchored, vec![roots.collect::<Vec<_>>()]);
+
+        // Blocks without a withdrawals root fail the anchoring
+        let withdrawals = ExExConfig { anchored_root: AnchoredRoot::Withdrawals, ..config };
+        let (notified, anchored) = anchor(withdrawals.clone()).await?;
+        let err = notified.unwrap_err();
+        assert!(err.to_string().contains("block 1 has no root to anchor"), "{err}");
+        assert!(anchored.is_empty());
+
+        // Unless they are skipped
+        let skipping = ExExConfig { skip_missing_roots: true, ..withdrawals };
+        let (notified, anchored) = anchor(skipping).await?;
+        assert_eq!(notified?, Some(chain.tip().num_hash()));
+        assert!(anchored.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_batch_calldata_bytes() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        // The selector, two offsets and two lengths, followed by a number and state root per
+        // state root of the batch
+        let batch_bytes = |len: usize| 4 + 4 * 32 + len * 2 * 32;
+        let config = ExExConfig {
+            max_batch_calldata_bytes: Some(batch_bytes(2)),
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        assert_eq!(client.max_batch_len(), 2);
+
+        // Three state roots exceed the limit, so they are split into two submissions
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut anchor = StateRootAnchor::new(client, watermark, MockEthProvider::default());
+        let chain = counter_chain(&mut rng, 1..=3, 1, config.counter_contracts[0])?;
+        let roots = chain
+            .blocks_iter()
+            .map(|block| (block.number, block.header.state_root))
+            .collect::<Vec<_>>();
+        anchor.on_chain_committed(&Arc::new(chain)).await?;
+        assert_eq!(
+            transport.anchored_state_roots()?,
+            vec![roots[..2].to_vec(), roots[2..].to_vec()]
+        );
+        let txs = transport.sent_transactions();
+        assert_eq!(txs[0].input().len(), batch_bytes(2));
+
+        // The limit is never below a single state root, and the batch size still applies
+        for (max_bytes, batch_size, expected) in
+            [(batch_bytes(2) - 1, 100, 1), (1, 100, 1), (batch_bytes(4), 3, 3), (usize::MAX, 5, 5)]
+        {
+            let config = ExExConfig {
+                max_batch_calldata_bytes: Some(max_bytes),
+                batch_size,
+                ..config.clone()
+            };
+            let client = L1Client::with_transport(config, MockTransport::default()).await?;
+            assert_eq!(client.max_batch_len(), expected, "{max_bytes} bytes, {batch_size} roots");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_min_depth() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig { min_depth: 3, ..ExExConfig::from_env_with(test_env)? };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch =
+            RootBatch::new(L1_BATCH_SIZE, Duration::ZERO).with_min_depth(config.min_depth);
+        let address = config.counter_contracts[0];
+
+        // A chain shorter than the minimum depth has no block deep enough to be anchored yet, its
+        // state roots stay queued
+        let chain = counter_chain(&mut rng, 1..=3, 1, address)?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert_eq!((batch.len(), watermark.height()), (3, None));
+
+        // Single block commits bury the queued blocks, which are anchored once deep enough
+        for number in 4..=5 {
+            let chain = counter_chain(&mut rng, number..=number, 1, address)?;
+            notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        }
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 2);
+        let call = changeStateRootAtCall::abi_decode(txs[0].input(), true)?;
+        assert_eq!(call.number_, U256::from(1));
+        let call = changeStateRootAtCall::abi_decode(txs[1].input(), true)?;
+        assert_eq!(call.number_, U256::from(2));
+        assert_eq!((batch.len(), watermark.height()), (3, Some(2)));
+
+        // A longer chain anchors all blocks at least the minimum depth below its tip
+        let chain = counter_chain(&mut rng, 6..=8, 1, address)?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        let txs = transport.sent_transactions();
+        assert_eq!(txs.len(), 3);
+        let call = changeStateRootBatchCall::abi_decode(txs[2].input(), true)?;
+        assert_eq!(call.numbers_, (3..=5).map(U256::from).collect::<Vec<_>>());
+        assert_eq!((batch.len(), watermark.height()), (3, Some(5)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exex_reports_status() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let status = BridgeStatusHandle::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone())
+            .await?
+            .with_status(status.clone());
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        record_status(client.status(), &watermark, &batch);
+
+        let status = status.get();
+        assert_eq!(status.anchored_height, Some(2));
+        assert!(status.last_l1_tx_hash.is_some());
+        assert_eq!(status.last_anchored_root, Some(chain.tip().header.state_root));
+        assert_eq!(status.pending_submissions, 0);
+
+        // The anchored root is only updated once a submission is confirmed
+        let reverting = L1Client::with_transport(config, MockTransport::reverting())
+            .await?
+            .with_status(BridgeStatusHandle::default());
+        let call = StateRootCall::new(&[(3, B256::random())], false)?;
+        assert!(reverting.submit(call).await.is_err());
+        assert_eq!(reverting.status().get().last_anchored_root, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_skips_anchored_state_root() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        let state_root = chain.tip().block.header.state_root;
+
+        let anchored = Bytes::copy_from_slice(state_root.as_slice());
+        let output = StateRootAbi::stateRootCall::abi_encode_returns(&(anchored,));
+        let transport = MockTransport::with_call_output(output);
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        // The contract already holds the state root of the tip, so nothing is sent
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_call"), 1);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+        assert_eq!(watermark.height(), Some(1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recheck_anchored() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            l1_reorg_depth: 2,
+            l1_recheck_interval: Duration::ZERO,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut anchor = StateRootAnchor::new(client, watermark, MockEthProvider::default());
+        let address = config.counter_contracts[0];
+        let root_of = |chain: &Chain| (chain.tip().number, chain.tip().header.state_root);
+
+        let first = counter_chain(&mut rng, 1..=1, 1, address)?;
+        let first_root = root_of(&first);
+        anchor.on_chain_committed(&Arc::new(first)).await?;
+        assert_eq!(anchor.client.recheck_anchored().await?, 0);
+
+        // The L1 reorgs out the confirmed transaction, so it's submitted again before the next
+        // committed chain is anchored
+        transport.reorg();
+        let second = counter_chain(&mut rng, 2..=2, 1, address)?;
+        let second_root = root_of(&second);
+        anchor.on_chain_committed(&Arc::new(second)).await?;
+        assert_eq!(
+            transport.anchored_state_roots()?,
+            vec![vec![first_root], vec![first_root], vec![second_root]]
+        );
+
+        // Transactions still included in the L1 aren't submitted again
+        let third = counter_chain(&mut rng, 3..=3, 1, address)?;
+        anchor.on_chain_committed(&Arc::new(third)).await?;
+        assert_eq!(transport.anchored_state_roots()?.len(), 4);
+        assert_eq!(anchor.client.recent.lock().len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recheck_anchored_sink() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            l1_reorg_depth: 2,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let transport = MockTransport::default();
+        let sink = Arc::new(MockStateRootSink::default());
+        let client = L1Client::with_transport(config.clone(), transport.clone())
+            .await?
+            .with_sink(sink.clone());
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut anchor = StateRootAnchor::new(client, watermark, MockEthProvider::default());
+        let address = config.counter_contracts[0];
+        let root_of = |chain: &Chain| (chain.tip().number, chain.tip().header.state_root);
+
+        // The submission is verified against, and tracked in, the sink like in the contract
+        let first = counter_chain(&mut rng, 1..=1, 1, address)?;
+        let first_root = root_of(&first);
+        anchor.on_chain_committed(&Arc::new(first)).await?;
+        assert!(anchor.client.is_anchored_on_chain(first_root.0, first_root.1).await);
+        assert_eq!(anchor.client.recent.lock().len(), 1);
+        assert_eq!(anchor.client.recheck_anchored().await?, 0);
+
+        // The sink reorgs out the submission, so it's submitted to the sink again
+        sink.reorg();
+        assert!(!anchor.client.is_anchored_on_chain(first_root.0, first_root.1).await);
+        assert_eq!(anchor.client.recheck_anchored().await?, 1);
+        assert!(anchor.client.is_anchored_on_chain(first_root.0, first_root.1).await);
+        assert_eq!(sink.submissions(), vec![first_root, first_root]);
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_recheck_anchored_if_due() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            l1_reorg_depth: 2,
+            l1_recheck_interval: Duration::from_secs(12),
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let sink = Arc::new(MockStateRootSink::default());
+        let client = L1Client::with_transport(config.clone(), MockTransport::default())
+            .await?
+            .with_sink(sink.clone());
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut anchor = StateRootAnchor::new(client, watermark, MockEthProvider::default());
+        let address = config.counter_contracts[0];
+
+        // The first committed chain is rechecked, with nothing to recheck yet
+        let first = counter_chain(&mut rng, 1..=1, 1, address)?;
+        anchor.on_chain_committed(&Arc::new(first)).await?;
+        assert_eq!(sink.submissions().len(), 1);
+
+        // The reorged out submission isn't rechecked until the interval passed
+        sink.reorg();
+        let second = counter_chain(&mut rng, 2..=2, 1, address)?;
+        anchor.on_chain_committed(&Arc::new(second)).await?;
+        assert_eq!(sink.submissions().len(), 2);
+        tokio::time::advance(Duration::from_secs(12)).await;
+        assert_eq!(anchor.client.recheck_anchored_if_due().await?, 1);
+        assert_eq!(anchor.client.recheck_anchored_if_due().await?, 0);
+        assert_eq!(sink.submissions().len(), 3);
+
+        Ok(())
+    }
+
+    /// [`MockStateRootSink`] whose transactions can't be checked for being included once
+    /// [`Self::unreadable`] is set.
+    #[derive(Debug, Default)]
+    struct UnreadableSink {
+        inner: MockStateRootSink,
+        unreadable: std::sync::atomic::AtomicBool,
+    }
+
+    #[async_trait]
+    impl StateRootSink for UnreadableSink {
+        async fn submit(&self, number: BlockNumber, root: B256) -> Result<TxHash, L1SubmitError> {
+            self.inner.submit(number, root).await
+        }
+
+        async fn anchored_state_root(&self, number: BlockNumber) -> TransportResult<Option<B256>> {
+            self.inner.anchored_state_root(number).await
+        }
+
+        async fn is_included(&self, tx_hash: TxHash) -> TransportResult<bool> {
+            if self.unreadable.load(Ordering::SeqCst) {
+                return Err(TransportErrorKind::custom_str("receipt unavailable"))
+            }
+            self.inner.is_included(tx_hash).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recheck_anchored_unreadable_receipt() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            l1_reorg_depth: 2,
+            l1_recheck_interval: Duration::ZERO,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let sink = Arc::new(UnreadableSink::default());
+        let client = L1Client::with_transport(config.clone(), MockTransport::default())
+            .await?
+            .with_sink(sink.clone());
+        let dir = tempfile::tempdir()?;
+        let watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut anchor = StateRootAnchor::new(client, watermark, MockEthProvider::default());
+        let address = config.counter_contracts[0];
+        let first = counter_chain(&mut rng, 1..=1, 1, address)?;
+        anchor.on_chain_committed(&Arc::new(first)).await?;
+
+        // A receipt that can't be read doesn't stop the ExEx, and the submission stays tracked
+        sink.unreadable.store(true, Ordering::SeqCst);
+        sink.inner.reorg();
+        let second = counter_chain(&mut rng, 2..=2, 1, address)?;
+        anchor.on_chain_committed(&Arc::new(second)).await?;
+        assert_eq!(sink.inner.submissions().len(), 2);
+        assert_eq!(anchor.client.recent.lock().len(), 2);
+
+        // It's rechecked once the receipt can be read again
+        sink.unreadable.store(false, Ordering::SeqCst);
+        assert_eq!(anchor.client.recheck_anchored().await?, 1);
+        assert_eq!(sink.inner.submissions().len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_per_height_state_roots() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config =
+            ExExConfig { state_roots_per_height: true, ..ExExConfig::from_env_with(test_env)? };
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        let state_root = chain.tip().block.header.state_root;
+
+        let output = StateRootAbi::stateRootsCall::abi_encode_returns(&(state_root,));
+        let transport = MockTransport::with_call_output(output);
+        let client = L1Client::with_transport(config.clone(), transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        // A different state root held for the block number is overwritten
+        let chain = counter_chain(&mut rng, 2..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 1);
+        assert_eq!(watermark.height(), Some(2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bridge_log_target() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .with_env_filter(EnvFilter::new("info,t1::bridge=debug"))
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = ExExConfig::from_env_with(test_env)?;
+        let client = L1Client::with_transport(config, MockTransport::default()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &Chain::default()).await?;
+        debug!("Debug log of another target");
+
+        // Debug logs of the bridge pass the filter, those of other targets don't
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("Committed chain is empty"))
+            .ok_or_eyre("missing bridge debug log")?;
+        assert!(line.contains("DEBUG t1::bridge:"), "{line}");
+        assert!(!lines.iter().any(|line| line.contains("another target")), "{lines:?}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_without_events() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .with_env_filter(EnvFilter::new("info,t1::bridge=debug"))
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config, transport.clone()).await?;
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        // The logs of the chain are all emitted by another contract
+        let chain = chain_with_log(&mut rng, 1..=2, 2, |_| incremented_log(Address::random()))?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 0);
+
+        let lines = logs.lines();
+        let line = lines
+            .iter()
+            .find(|line| line.contains("No Counter events in the committed chain"))
+            .ok_or_eyre("missing zero events log")?;
+        assert!(line.contains("DEBUG t1::bridge:"), "{line}");
+        assert!(line.contains("blocks=2"), "{line}");
+        assert!(line.contains("logs=4"), "{line}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_log_events() -> eyre::Result<()> {
+        let logs = CapturedLogs::default();
+        let subscriber = reth_tracing::tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            start_height: 10,
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+        let decoded = |logs: &CapturedLogs| {
+            logs.lines().into_iter().filter(|line| line.contains("Decoded Counter event")).count()
+        };
+
+        // Silent by default
+        let client = L1Client::with_transport(config.clone(), MockTransport::default()).await?;
+        let chain = counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(decoded(&logs), 0);
+
+        // Every event is logged, even though its block is below the start height
+        let config = ExExConfig { log_events: true, ..config };
+        let client = L1Client::with_transport(config.clone(), MockTransport::default()).await?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        let lines = logs.lines();
+        let lines =
+            lines.iter().filter(|line| line.contains("Decoded Counter event")).collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        for (line, block) in lines.into_iter().zip(chain.blocks_iter()) {
+            assert!(line.contains(&format!("number={}", block.number)), "{line}");
+            let tx_hash = block.body.transactions[0].hash();
+            assert!(line.contains(&format!("tx_hash={tx_hash}")), "{line}");
+            assert!(line.contains("event=\"Incremented\""), "{line}");
+        }
+        assert!(batch.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_anchor_backup_destination() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig {
+            max_retries: 1,
+            retry_base_delay: Duration::from_millis(1),
+            dedup_state_roots: false,
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let backup_contract = Address::random();
+        let backup_config =
+            config.for_destination(&format!("http://localhost:8546,{backup_contract}").parse()?);
+        assert_eq!(backup_config.l1_rpc, Url::parse("http://localhost:8546")?);
+        let dir = tempfile::tempdir()?;
+
+        // The primary destination is failing, the backup one isn't
+        let primary_transport = MockTransport::failing(2);
+        let primary = StateRootAnchor::new(
+            L1Client::with_transport(config.clone(), primary_transport.clone()).await?,
+            Watermark::load(dir.path().join(L1_WATERMARK_FILE))?,
+            MockEthProvider::default(),
+        );
+        let backup_transport = MockTransport::default();
+        let backup = StateRootAnchor::new(
+            L1Client::with_transport(backup_config, backup_transport.clone()).await?,
+            Watermark::load(dir.path().join(format!("{L1_WATERMARK_FILE}-backup-1")))?,
+            MockEthProvider::default(),
+        );
+        let mut handlers: Vec<Box<dyn RollupEventHandler>> =
+            vec![Box::new(primary), Box::new(backup)];
+
+        // The failure of the primary destination keeps its state roots queued, and doesn't keep
+        // them from being anchored to the backup destination
+        let chain = Arc::new(counter_chain(&mut rng, 1..=2, 1, config.counter_contracts[0])?);
+        let roots = chain
+            .blocks_iter()
+            .map(|block| (block.number, block.header.state_root))
+            .collect::<Vec<_>>();
+        handlers.on_chain_committed(&chain).await?;
+        assert_eq!(primary_transport.calls("eth_sendRawTransaction"), 2);
+        assert_eq!(backup_transport.anchored_state_roots()?, vec![roots.clone()]);
+        assert_eq!(backup_transport.sent_transactions()[0].to(), Some(backup_contract));
+        assert!(handlers.is_pending());
+
+        // Once the primary destination recovers, its state roots are anchored as well
+        handlers.on_deadline().await?;
+        assert_eq!(primary_transport.anchored_state_roots()?[2..], [roots]);
+        assert_eq!(backup_transport.calls("eth_sendRawTransaction"), 1);
+        assert!(!handlers.is_pending());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+//! History of the anchoring transactions, persisted and broadcast to subscribers.
+
+use alloy_primitives::{BlockNumber, TxHash, B256};
+use serde::{Deserialize, Serialize};
+use std::{io::Write, path::PathBuf};
+use tokio::sync::broadcast;
+
+/// Number of anchored state roots buffered for the subscribers of the [`AnchorEvents`].
+const ANCHOR_EVENTS_CAPACITY: usize = 1_024;
+
+/// Name of the file in the datadir the [`SubmissionHistory`] is appended to.
+pub const L1_HISTORY_FILE: &str = "t1-l1-history";
+
+/// A state root anchored on L1, as recorded in the [`SubmissionHistory`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubmissionRecord {
+    /// Number of the anchored block.
+    pub number: BlockNumber,
+    /// Anchored state root of the block.
+    pub state_root: B256,
+    /// Hash of the confirmed L1 transaction that anchored the state root.
+    pub l1_tx_hash: TxHash,
+    /// Time the transaction was confirmed at, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// Append-only log of every state root anchored on L1, for auditing.
+///
+/// Every [`SubmissionRecord`] is a line of JSON, synced to disk when it's appended.
+#[derive(Debug, Clone)]
+pub struct SubmissionHistory {
+    path: PathBuf,
+}
+
+impl SubmissionHistory {
+    /// Creates a history appended to the given file, which is created by the first append.
+    pub const fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Appends the given records to the history and syncs them to disk.
+    pub fn append(&self, records: &[SubmissionRecord]) -> eyre::Result<()> {
+        let mut lines = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut lines, record)?;
+            lines.push(b'\n');
+        }
+
+        let mut file =
+            std::fs::OpenOptions::new().create(true).append(true).open(&self.path).map_err(
+                |err| eyre::eyre!("failed to open L1 history {}: {err}", self.path.display()),
+            )?;
+        file.write_all(&lines)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Returns the records of the blocks numbered `from` or above, in the order they were
+    /// appended in.
+    pub fn read(&self, from: BlockNumber) -> eyre::Result<Vec<SubmissionRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new())
+        }
+
+        let contents = reth_fs_util::read_to_string(&self.path)?;
+        let mut records = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let record: SubmissionRecord = serde_json::from_str(line).map_err(|err| {
+                eyre::eyre!("invalid L1 history record in {}: {err}", self.path.display())
+            })?;
+            if record.number >= from {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Broadcast channel of the state roots anchored on L1, as [`SubmissionRecord`]s.
+///
+/// It's created before the node is launched and handed to the ExEx, so other ExExes and tasks of
+/// the node can subscribe to a clone of it instead of polling the L1. Subscribers that fall more
+/// than [`ANCHOR_EVENTS_CAPACITY`] records behind miss the oldest ones.
+#[derive(Debug, Clone)]
+pub struct AnchorEvents(broadcast::Sender<SubmissionRecord>);
+
+impl Default for AnchorEvents {
+    fn default() -> Self {
+        Self(broadcast::channel(ANCHOR_EVENTS_CAPACITY).0)
+    }
+}
+
+impl AnchorEvents {
+    /// Returns a receiver of the state roots anchored from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<SubmissionRecord> {
+        self.0.subscribe()
+    }
+
+    /// Publishes an anchored state root to the current subscribers, if any.
+    pub(super) fn publish(&self, record: SubmissionRecord) {
+        let _ = self.0.send(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        batch::RootBatch,
+        client::L1Client,
+        config::{ExExConfig, L1_BATCH_SIZE},
+        handler::notify_l1,
+        test_utils::{counter_chain, test_env, MockTransport},
+        watermark::{Watermark, L1_WATERMARK_FILE},
+    };
+    use reth_testing_utils::generators;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_submission_history() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let dir = tempfile::tempdir()?;
+        let history = SubmissionHistory::new(dir.path().join(L1_HISTORY_FILE));
+        assert!(history.read(0)?.is_empty());
+
+        let config = ExExConfig::from_env_with(test_env)?;
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone())
+            .await?
+            .with_history(SubmissionHistory::new(dir.path().join(L1_HISTORY_FILE)));
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(2, Duration::ZERO);
+
+        // Every state root is recorded once its transaction is confirmed
+        let chain = counter_chain(&mut rng, 1..=3, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert_eq!(transport.calls("eth_sendRawTransaction"), 2);
+
+        let records = history.read(0)?;
+        assert_eq!(
+            records.iter().map(|record| (record.number, record.state_root)).collect::<Vec<_>>(),
+            chain
+                .blocks()
+                .values()
+                .map(|block| (block.number, block.header.state_root))
+                .collect::<Vec<_>>()
+        );
+        assert!(records.iter().all(|record| record.l1_tx_hash == B256::repeat_byte(0x11)));
+        assert!(records.iter().all(|record| record.timestamp > 0));
+
+        // Later submissions are appended, and the history can be read from a block number on
+        let chain = counter_chain(&mut rng, 4..=4, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        let numbers = history.read(3)?.iter().map(|record| record.number).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![3, 4]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_anchor_events() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let anchor_events = AnchorEvents::default();
+        let client = L1Client::with_transport(config.clone(), MockTransport::default())
+            .await?
+            .with_anchor_events(anchor_events.clone());
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+        let mut first = anchor_events.subscribe();
+        let mut second = anchor_events.subscribe();
+
+        // Every subscriber receives the anchored state root with its L1 transaction
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        for subscriber in [&mut first, &mut second] {
+            let record = subscriber.try_recv()?;
+            assert_eq!(
+                (record.number, record.state_root, record.l1_tx_hash),
+                (1, chain.tip().header.state_root, B256::repeat_byte(0x11))
+            );
+            assert!(subscriber.try_recv().is_err());
+        }
+
+        // Nothing is published in dry run mode
+        let config = ExExConfig { dry_run: true, ..config };
+        let client = L1Client::with_transport(config.clone(), MockTransport::default())
+            .await?
+            .with_anchor_events(anchor_events.clone());
+        let chain = counter_chain(&mut rng, 2..=2, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+        assert!(first.try_recv().is_err());
+
+        Ok(())
+    }
+}
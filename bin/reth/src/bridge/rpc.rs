@@ -0,0 +1,245 @@
+//! The `t1` RPC namespace, reporting the status of the bridge.
+
+use super::history::SubmissionHistory;
+use alloy_primitives::{BlockNumber, TxHash, B256};
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    types::{error::INTERNAL_ERROR_CODE, ErrorObjectOwned},
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Status of the L1 bridge, served by the `t1_bridgeStatus` RPC method.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeStatus {
+    /// Height of the local chain, as of the latest notification.
+    pub local_tip: Option<BlockNumber>,
+    /// Highest block number anchored on L1, see [`Watermark`](super::watermark::Watermark).
+    pub anchored_height: Option<BlockNumber>,
+    /// Hash of the latest confirmed L1 transaction.
+    pub last_l1_tx_hash: Option<TxHash>,
+    /// State root anchored by the latest confirmed L1 transaction, to be cross-checked against
+    /// the state root contract.
+    pub last_anchored_root: Option<B256>,
+    /// Number of state roots queued to be anchored on L1, see
+    /// [`RootBatch`](super::batch::RootBatch).
+    pub pending_submissions: usize,
+}
+
+/// Anchoring status of the state root of a block, served by the `t1_rootStatus` RPC method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootStatus {
+    /// Whether the state root of the block is anchored.
+    pub status: AnchorState,
+    /// Anchored state root of the block, if it's confirmed.
+    pub state_root: Option<B256>,
+    /// Hash of the L1 transaction that anchored the state root, if it's confirmed.
+    pub l1_tx_hash: Option<TxHash>,
+}
+
+/// Whether the state root of a block is anchored on L1, see [`RootStatus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AnchorState {
+    /// The state root was anchored by a confirmed L1 transaction, recorded in the
+    /// [`SubmissionHistory`].
+    Confirmed,
+    /// The block is above the [`BridgeStatus::anchored_height`] and at or below the local tip,
+    /// so its state root is queued or in flight, unless the block has no Counter events.
+    Pending,
+    /// The state root of the block isn't anchored, e.g. because the block isn't committed yet or
+    /// was covered by the state root of a later block.
+    Unknown,
+}
+
+/// [`BridgeStatus`] shared between the ExEx, which updates it, and the RPC, which serves it.
+#[derive(Debug, Clone, Default)]
+pub struct BridgeStatusHandle(Arc<RwLock<BridgeStatus>>);
+
+impl BridgeStatusHandle {
+    /// Returns a snapshot of the current status.
+    pub fn get(&self) -> BridgeStatus {
+        self.0.read().clone()
+    }
+
+    /// Updates the status with the given closure.
+    pub fn update(&self, f: impl FnOnce(&mut BridgeStatus)) {
+        f(&mut self.0.write())
+    }
+}
+
+/// The `t1` RPC namespace, exposing the status of the L1 bridge.
+#[cfg_attr(not(test), rpc(server, namespace = "t1"))]
+#[cfg_attr(test, rpc(server, client, namespace = "t1"))]
+pub trait T1Api {
+    /// Returns the current [`BridgeStatus`].
+    #[method(name = "bridgeStatus")]
+    fn bridge_status(&self) -> RpcResult<BridgeStatus>;
+
+    /// Returns the [`RootStatus`] of the state root of the given block.
+    #[method(name = "rootStatus")]
+    fn root_status(&self, number: BlockNumber) -> RpcResult<RootStatus>;
+}
+
+/// Implementation of the [`T1ApiServer`], reading the [`BridgeStatusHandle`] of the ExEx and its
+/// [`SubmissionHistory`].
+#[derive(Debug, Clone)]
+pub struct T1Rpc {
+    status: BridgeStatusHandle,
+    history: Option<SubmissionHistory>,
+}
+
+impl T1Rpc {
+    /// Creates the RPC serving the given status.
+    pub const fn new(status: BridgeStatusHandle) -> Self {
+        Self { status, history: None }
+    }
+
+    /// Sets the [`SubmissionHistory`] the confirmed state roots are looked up in.
+    pub fn with_history(mut self, history: SubmissionHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+}
+
+impl T1ApiServer for T1Rpc {
+    fn bridge_status(&self) -> RpcResult<BridgeStatus> {
+        Ok(self.status.get())
+    }
+
+    fn root_status(&self, number: BlockNumber) -> RpcResult<RootStatus> {
+        let records = match &self.history {
+            Some(history) => history.read(number).map_err(|err| {
+                ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, err.to_string(), None::<()>)
+            })?,
+            None => Vec::new(),
+        };
+        // A state root re-anchored after a reorg supersedes the earlier one
+        if let Some(record) = records.into_iter().rev().find(|record| record.number == number) {
+            return Ok(RootStatus {
+                status: AnchorState::Confirmed,
+                state_root: Some(record.state_root),
+                l1_tx_hash: Some(record.l1_tx_hash),
+            })
+        }
+
+        let BridgeStatus { local_tip, anchored_height, .. } = self.status.get();
+        let status = if local_tip.is_some_and(|tip| number <= tip) &&
+            !anchored_height.is_some_and(|height| number <= height)
+        {
+            AnchorState::Pending
+        } else {
+            AnchorState::Unknown
+        };
+        Ok(RootStatus { status, state_root: None, l1_tx_hash: None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::history::{SubmissionRecord, L1_HISTORY_FILE};
+    use jsonrpsee::{
+        core::client::ClientT, http_client::HttpClientBuilder, rpc_params, server::ServerBuilder,
+    };
+    use serde_json::{json, Value};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_bridge_status_rpc() -> eyre::Result<()> {
+        let status = BridgeStatusHandle::default();
+        let server = ServerBuilder::default().build("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let handle = server.start(T1Rpc::new(status.clone()).into_rpc());
+        let client = HttpClientBuilder::default().build(format!("http://{addr}"))?;
+
+        // Nothing is known before the first notification
+        let json: Value = client.request("t1_bridgeStatus", rpc_params![]).await?;
+        assert_eq!(
+            json,
+            json!({
+                "localTip": null,
+                "anchoredHeight": null,
+                "lastL1TxHash": null,
+                "lastAnchoredRoot": null,
+                "pendingSubmissions": 0,
+            })
+        );
+
+        let (tx_hash, state_root) = (TxHash::random(), B256::random());
+        status.update(|status| {
+            status.local_tip = Some(12);
+            status.anchored_height = Some(10);
+            status.last_l1_tx_hash = Some(tx_hash);
+            status.last_anchored_root = Some(state_root);
+            status.pending_submissions = 2;
+        });
+        let json: Value = client.request("t1_bridgeStatus", rpc_params![]).await?;
+        assert_eq!(
+            json,
+            json!({
+                "localTip": 12,
+                "anchoredHeight": 10,
+                "lastL1TxHash": tx_hash,
+                "lastAnchoredRoot": state_root,
+                "pendingSubmissions": 2,
+            })
+        );
+        assert_eq!(T1ApiClient::bridge_status(&client).await?, status.get());
+
+        handle.stop()?;
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_root_status_rpc() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let history = SubmissionHistory::new(dir.path().join(L1_HISTORY_FILE));
+        let record = SubmissionRecord {
+            number: 10,
+            state_root: B256::random(),
+            l1_tx_hash: TxHash::random(),
+            timestamp: 1_700_000_000,
+        };
+        history.append(&[record.clone()])?;
+        let status = BridgeStatusHandle::default();
+        status.update(|status| {
+            status.local_tip = Some(12);
+            status.anchored_height = Some(10);
+        });
+        let server = ServerBuilder::default().build("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        let handle = server.start(T1Rpc::new(status).with_history(history).into_rpc());
+        let client = HttpClientBuilder::default().build(format!("http://{addr}"))?;
+
+        // The anchored block is confirmed by the transaction in the history
+        let json: Value = client.request("t1_rootStatus", rpc_params![10]).await?;
+        assert_eq!(
+            json,
+            json!({
+                "status": "confirmed",
+                "stateRoot": record.state_root,
+                "l1TxHash": record.l1_tx_hash,
+            })
+        );
+
+        // A committed block above the anchored height is pending
+        let json: Value = client.request("t1_rootStatus", rpc_params![12]).await?;
+        assert_eq!(json, json!({ "status": "pending", "stateRoot": null, "l1TxHash": null }));
+
+        // Nothing is known about a block that isn't committed yet, or wasn't anchored itself
+        for number in [13, 9] {
+            let root_status = T1ApiClient::root_status(&client, number).await?;
+            assert_eq!(
+                root_status,
+                RootStatus { status: AnchorState::Unknown, state_root: None, l1_tx_hash: None }
+            );
+        }
+
+        handle.stop()?;
+        Ok(())
+    }
+}
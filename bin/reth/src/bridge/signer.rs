@@ -0,0 +1,189 @@
+//! Signers of the L1 transactions of the bridge.
+
+use alloy_network::{eip2718::Encodable2718, EthereumWallet};
+use alloy_primitives::{Address, Bytes};
+use alloy_rpc_client::RpcClient;
+use alloy_rpc_types::TransactionRequest;
+use alloy_signer_local::PrivateKeySigner;
+use alloy_transport::{Transport, TransportErrorKind, TransportResult};
+use alloy_transport_http::ReqwestTransport;
+use async_trait::async_trait;
+use std::fmt;
+
+/// Signer of the transactions sent to the
+/// [`StateRootContract`](super::contract::StateRootContract).
+#[async_trait]
+pub trait L1Signer: fmt::Debug + Send + Sync {
+    /// Returns the address of the account the signer signs for.
+    fn address(&self) -> Address;
+
+    /// Signs the given transaction, returning it EIP-2718 encoded.
+    async fn sign_transaction(&self, request: TransactionRequest) -> TransportResult<Bytes>;
+}
+
+/// [`L1Signer`] holding the key of the account in the process.
+#[derive(Debug, Clone)]
+pub struct LocalSigner {
+    address: Address,
+    wallet: EthereumWallet,
+}
+
+impl LocalSigner {
+    /// Creates a signer signing with the given key.
+    pub fn new(key: PrivateKeySigner) -> Self {
+        Self { address: key.address(), wallet: EthereumWallet::from(key) }
+    }
+}
+
+#[async_trait]
+impl L1Signer for LocalSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(&self, request: TransactionRequest) -> TransportResult<Bytes> {
+        let envelope = request.build(&self.wallet).await.map_err(TransportErrorKind::custom)?;
+        Ok(envelope.encoded_2718().into())
+    }
+}
+
+/// [`L1Signer`] delegating to a remote signer with `eth_signTransaction`, keeping the key of the
+/// account out of the process.
+///
+/// The remote signer, e.g. a signing service backed by a KMS, has to return the raw signed
+/// transaction.
+#[derive(Debug)]
+pub struct RemoteSigner<T: Transport + Clone = ReqwestTransport> {
+    address: Address,
+    client: RpcClient<T>,
+}
+
+impl<T: Transport + Clone> RemoteSigner<T> {
+    /// Creates a signer signing for the given account over the given transport.
+    pub fn new(transport: T, address: Address) -> Self {
+        Self { address, client: RpcClient::new(transport, false) }
+    }
+}
+
+#[async_trait]
+impl<T: Transport + Clone> L1Signer for RemoteSigner<T> {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(&self, request: TransactionRequest) -> TransportResult<Bytes> {
+        self.client.request("eth_signTransaction", (request,)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::{
+        batch::RootBatch,
+        client::L1Client,
+        config::{ExExConfig, L1_BATCH_SIZE},
+        contract::StateRootCall,
+        handler::notify_l1,
+        test_utils::{counter_chain, test_env, MockTransport, TEST_SECRET},
+        watermark::{Watermark, L1_WATERMARK_FILE},
+    };
+    use reth_testing_utils::generators;
+    use serde_json::json;
+    use std::{
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use url::Url;
+
+    /// [`L1Signer`] recording the transactions it signs, signing them with a [`LocalSigner`].
+    #[derive(Debug)]
+    struct RecordingSigner {
+        inner: LocalSigner,
+        requests: Mutex<Vec<TransactionRequest>>,
+    }
+
+    #[async_trait]
+    impl L1Signer for RecordingSigner {
+        fn address(&self) -> Address {
+            self.inner.address()
+        }
+
+        async fn sign_transaction(&self, request: TransactionRequest) -> TransportResult<Bytes> {
+            self.requests.lock().unwrap().push(request.clone());
+            self.inner.sign_transaction(request).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notify_l1_signer() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let config = ExExConfig::from_env_with(test_env)?;
+        let signer = Arc::new(RecordingSigner {
+            inner: LocalSigner::new(PrivateKeySigner::from_str(TEST_SECRET)?),
+            requests: Mutex::default(),
+        });
+        let transport = MockTransport::default();
+        let client = L1Client::with_transport(config.clone(), transport.clone())
+            .await?
+            .with_signer(signer.clone());
+        let dir = tempfile::tempdir()?;
+        let mut watermark = Watermark::load(dir.path().join(L1_WATERMARK_FILE))?;
+        let mut batch = RootBatch::new(L1_BATCH_SIZE, Duration::ZERO);
+
+        let chain = counter_chain(&mut rng, 1..=1, 1, config.counter_contracts[0])?;
+        notify_l1(&client, client.sink(), &mut watermark, &mut batch, &chain).await?;
+
+        // The signer is asked to sign the state root call, which is then sent as signed
+        let requests = signer.requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.from, Some(config.account()));
+        assert_eq!(request.to, Some(config.state_root_contract.into()));
+        assert_eq!(request.nonce, Some(0));
+        let calldata = StateRootCall::At(1, chain.tip().header.state_root).calldata();
+        assert_eq!(request.input.input(), Some(&Bytes::from(calldata)));
+        assert_eq!(transport.sent_transactions().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer() -> eyre::Result<()> {
+        let transport = MockTransport::default();
+        let address = Address::random();
+        let signer = RemoteSigner::new(transport.clone(), address);
+        assert_eq!(signer.address(), address);
+
+        let request = TransactionRequest::default().with_from(address).with_nonce(7);
+        let raw = signer.sign_transaction(request).await?;
+        assert_eq!(raw, Bytes::from_static(&[0x02, 0xc0]));
+
+        // The transaction is sent to the remote signer as is
+        let requests = transport.requests.lock().unwrap().clone();
+        let (method, params) = &requests[0];
+        assert_eq!(method, "eth_signTransaction");
+        assert_eq!(params[0]["from"], json!(address));
+        assert_eq!(params[0]["nonce"], json!("0x7"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_signer_requires_address() -> eyre::Result<()> {
+        let config = ExExConfig {
+            remote_signer: Some(Url::parse("http://localhost:9000")?),
+            ..ExExConfig::from_env_with(test_env)?
+        };
+        let err = config.signer().unwrap_err();
+        assert!(err.to_string().contains("L1_REMOTE_SIGNER_ADDRESS"), "{err}");
+
+        let address = Address::random();
+        let config = ExExConfig { remote_signer_address: Some(address), ..config };
+        assert_eq!(config.signer()?.address(), address);
+        assert_eq!(config.account(), address);
+
+        Ok(())
+    }
+}
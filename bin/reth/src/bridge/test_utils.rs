@@ -0,0 +1,416 @@
+//! Test utilities of the bridge: a mock L1 transport, captured logs and chains of Counter events.
+
+use super::{
+    CounterContract,
+    StateRootAbi::{changeStateRootAtCall, changeStateRootBatchCall},
+};
+use alloy_eips::eip2718::Decodable2718;
+use alloy_json_rpc::{ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload};
+use alloy_primitives::{address, Address, BlockNumber, Bloom, Bytes, Log, LogData, B256};
+use alloy_rpc_types::{AccessList, AccessListItem};
+use alloy_sol_types::{SolCall, SolEvent};
+use alloy_transport::{TransportError, TransportErrorKind, TransportFut};
+use eyre::OptionExt;
+use metrics_util::debugging::{DebugValue, Snapshotter};
+use reth_execution_types::{Chain, ExecutionOutcome};
+use reth_primitives::{Receipt, Receipts, TransactionSigned};
+use reth_testing_utils::generators::{random_block, BlockParams, Rng};
+use reth_tracing::tracing_subscriber::fmt::MakeWriter;
+use serde_json::{json, value::RawValue, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::Service;
+
+/// Key of the prefunded L1 account of the tests.
+pub(super) const TEST_SECRET: &str =
+    "bcdf20249abf0ed6d944c0288fad489e33f66b3960d9e6229c1cd214ed3bbe31";
+
+/// L1 endpoint of the tests, never contacted.
+pub(super) const L1_RPC_ADDRESS: &str = "http://localhost:8545";
+/// Address of the state root contract of the tests.
+pub(super) const STATE_ROOT_CONTRACT_ADDRESS: Address =
+    address!("b4B46bdAA835F8E4b4d8e208B6559cD267851051");
+
+/// Environment of the tests, only setting the required variables: the [`L1_RPC_ADDRESS`],
+/// the [`STATE_ROOT_CONTRACT_ADDRESS`] and the [`TEST_SECRET`] as `PREFUNDED_SECRET`, see
+/// [`ExExConfig::from_env_with`](super::ExExConfig::from_env_with).
+pub(super) fn test_env(key: &str) -> Option<String> {
+    match key {
+        "L1_RPC_ADDRESS" => Some(L1_RPC_ADDRESS.to_string()),
+        "STATE_ROOT_CONTRACT_ADDRESS" => Some(STATE_ROOT_CONTRACT_ADDRESS.to_string()),
+        "PREFUNDED_SECRET" => Some(TEST_SECRET.to_string()),
+        _ => None,
+    }
+}
+
+/// Sets the [`test_env`] in the process environment, which the node reads its configuration
+/// from, see [`ExExConfig::from_env`](super::ExExConfig::from_env).
+pub(super) fn set_test_env() {
+    for key in ["L1_RPC_ADDRESS", "STATE_ROOT_CONTRACT_ADDRESS", "PREFUNDED_SECRET"] {
+        std::env::set_var(key, test_env(key).unwrap());
+    }
+}
+
+/// A [`Transport`](alloy_transport::Transport) answering the requests needed to sign, send and
+/// confirm a transaction, recording the requests.
+#[derive(Debug, Clone, Default)]
+pub(super) struct MockTransport {
+    pub(super) requests: Arc<Mutex<Vec<(String, Vec<Value>)>>>,
+    /// Errors of the `eth_sendRawTransaction` requests that fail before one succeeds.
+    pub(super) failures: Arc<Mutex<VecDeque<ErrorPayload>>>,
+    /// Pending nonce of the sending account.
+    pub(super) nonce: Arc<AtomicU64>,
+    /// Whether the sent transactions revert.
+    pub(super) reverted: bool,
+    /// Whether the L1 doesn't support EIP-1559 and has no fee history.
+    pub(super) legacy: bool,
+    /// Gas estimated for transactions, failing the estimation if unset.
+    pub(super) gas_estimate: Option<u64>,
+    /// Output of contract calls, failing the calls if unset.
+    pub(super) call_output: Option<Bytes>,
+    /// Number of sent transactions, from the first one, that are never mined.
+    pub(super) unmined: usize,
+    /// Balance of every account in wei.
+    pub(super) balance: u128,
+    /// Method whose requests are never answered.
+    pub(super) hanging: Option<&'static str>,
+    /// Code of every account but the implementation.
+    pub(super) code: Bytes,
+    /// EIP-1967 implementation of every proxy, with its code.
+    pub(super) implementation: Option<(Address, Bytes)>,
+    /// Number of sent transactions when the L1 reorged them out, see [`Self::reorg`].
+    pub(super) reorged_at: Arc<Mutex<Option<usize>>>,
+    /// Number of L1 blocks mined after the one transactions are included in, see
+    /// [`Self::mine`].
+    pub(super) mined_blocks: Arc<AtomicU64>,
+    /// Time every request takes to be answered.
+    pub(super) latency: Duration,
+}
+
+impl MockTransport {
+    /// Creates a transport failing the first `failures` sent transactions.
+    pub(super) fn failing(failures: usize) -> Self {
+        Self::failing_with((0..failures).map(|_| rpc_error("unreachable")).collect())
+    }
+
+    /// Creates a transport failing the first sent transactions with the given errors.
+    pub(super) fn failing_with(failures: VecDeque<ErrorPayload>) -> Self {
+        Self { failures: Arc::new(Mutex::new(failures)), ..Default::default() }
+    }
+
+    /// Creates a transport whose sent transactions revert.
+    pub(super) fn reverting() -> Self {
+        Self { reverted: true, ..Default::default() }
+    }
+
+    /// Creates a transport of an L1 that doesn't support EIP-1559.
+    pub(super) fn legacy() -> Self {
+        Self { legacy: true, ..Default::default() }
+    }
+
+    /// Creates a transport never answering the requests of the given method.
+    pub(super) fn hanging(method: &'static str) -> Self {
+        Self { hanging: Some(method), ..Default::default() }
+    }
+
+    /// Creates a transport estimating the given gas for transactions.
+    pub(super) fn with_gas_estimate(gas_estimate: u64) -> Self {
+        Self { gas_estimate: Some(gas_estimate), ..Default::default() }
+    }
+
+    /// Creates a transport returning the given output for contract calls.
+    pub(super) fn with_call_output(output: Vec<u8>) -> Self {
+        Self { call_output: Some(output.into()), ..Default::default() }
+    }
+
+    /// Creates a transport answering every request after the given latency.
+    pub(super) fn with_latency(latency: Duration) -> Self {
+        Self { latency, ..Default::default() }
+    }
+
+    /// Reorgs the transactions sent so far out of the L1, so they have no receipt until
+    /// another transaction is sent.
+    pub(super) fn reorg(&self) {
+        *self.reorged_at.lock().unwrap() = Some(self.calls("eth_sendRawTransaction"));
+    }
+
+    /// Mines the given number of L1 blocks.
+    pub(super) fn mine(&self, blocks: u64) {
+        self.mined_blocks.fetch_add(blocks, Ordering::SeqCst);
+    }
+
+    pub(super) fn calls(&self, method: &str) -> usize {
+        self.requests.lock().unwrap().iter().filter(|(m, _)| m == method).count()
+    }
+
+    /// Returns the transactions of all `eth_sendRawTransaction` requests, including failed
+    /// ones.
+    pub(super) fn sent_transactions(&self) -> Vec<TransactionSigned> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(method, _)| method == "eth_sendRawTransaction")
+            .map(|(_, params)| {
+                let raw = alloy_primitives::hex::decode(params[0].as_str().unwrap()).unwrap();
+                TransactionSigned::decode_2718(&mut raw.as_slice()).unwrap()
+            })
+            .collect()
+    }
+
+    /// Returns the block numbers and state roots anchored by each sent transaction, decoding
+    /// its `changeStateRootAt` or `changeStateRootBatch` call.
+    pub(super) fn anchored_state_roots(&self) -> eyre::Result<Vec<Vec<(BlockNumber, B256)>>> {
+        self.sent_transactions()
+            .iter()
+            .map(|tx| {
+                if let Ok(call) = changeStateRootAtCall::abi_decode(tx.input(), true) {
+                    return Ok(vec![(call.number_.to(), call.stateRoot_)])
+                }
+                let call = changeStateRootBatchCall::abi_decode(tx.input(), true)?;
+                let numbers = call.numbers_.iter().map(|number| number.to());
+                Ok(numbers.zip(call.stateRoots_).collect())
+            })
+            .collect()
+    }
+}
+
+/// Fee history with the base fees `[10, 20]` and the rewards `[[1], [3], [2]]`.
+pub(super) fn fee_history_json() -> Value {
+    json!({
+        "oldestBlock": "0xe",
+        "baseFeePerGas": ["0xa", "0x14"],
+        "gasUsedRatio": [0.5, 0.5, 0.5],
+        "reward": [["0x1"], ["0x3"], ["0x2"]],
+    })
+}
+
+impl MockTransport {
+    /// Returns the result of a request of the given method with the given params.
+    pub(super) fn respond(&self, method: &str, params: &[Value]) -> Result<Value, ErrorPayload> {
+        let sent = self.calls("eth_sendRawTransaction");
+        let mined = sent > self.unmined && *self.reorged_at.lock().unwrap() != Some(sent);
+        let response = match method {
+            "eth_chainId" | "eth_gasPrice" | "eth_blobBaseFee" => json!("0x1"),
+            "eth_getTransactionCount" => {
+                json!(format!("{:#x}", self.nonce.load(Ordering::SeqCst)))
+            }
+            "eth_sendRawTransaction" => {
+                if let Some(err) = self.failures.lock().unwrap().pop_front() {
+                    return Err(err)
+                }
+                json!(B256::repeat_byte(0x11))
+            }
+            "eth_blockNumber" => {
+                json!(format!("{:#x}", 0x10 + self.mined_blocks.load(Ordering::SeqCst)))
+            }
+            "eth_getBalance" => json!(format!("{:#x}", self.balance)),
+            "eth_getCode" => match &self.implementation {
+                Some((address, code)) if params[0] == json!(address) => json!(code),
+                _ => json!(self.code),
+            },
+            "eth_getStorageAt" => {
+                let implementation = self.implementation.as_ref().map(|(address, _)| *address);
+                json!(implementation.unwrap_or_default().into_word())
+            }
+            "eth_feeHistory" if !self.legacy => fee_history_json(),
+            "eth_estimateGas" => match self.gas_estimate {
+                Some(gas) => json!(format!("{gas:#x}")),
+                None => return Err(rpc_error("execution reverted")),
+            },
+            "eth_signTransaction" => json!(Bytes::from_static(&[0x02, 0xc0])),
+            "eth_createAccessList" => json!({
+                "accessList": access_list(),
+                "gasUsed": "0x5208",
+            }),
+            "eth_call" => match &self.call_output {
+                Some(output) => json!(output),
+                None => return Err(rpc_error("execution reverted")),
+            },
+            "eth_getTransactionReceipt" if !mined => Value::Null,
+            "eth_getTransactionReceipt" => json!({
+                "type": "0x2",
+                "transactionHash": B256::repeat_byte(0x11),
+                "transactionIndex": "0x0",
+                "blockHash": B256::repeat_byte(0x22),
+                "blockNumber": "0x10",
+                "from": Address::ZERO,
+                "to": STATE_ROOT_CONTRACT_ADDRESS,
+                "contractAddress": null,
+                "gasUsed": format!("{:#x}", self.gas_estimate.unwrap_or_default()),
+                "effectiveGasPrice": "0x1",
+                "cumulativeGasUsed": "0x0",
+                "logs": [],
+                "logsBloom": Bloom::ZERO,
+                "status": if self.reverted { "0x0" } else { "0x1" },
+            }),
+            _ => return Err(rpc_error(&format!("method {method} not supported"))),
+        };
+        Ok(response)
+    }
+}
+
+impl Service<RequestPacket> for MockTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let RequestPacket::Single(request) = request else {
+            return Box::pin(async {
+                Err(TransportErrorKind::custom_str("batch requests are not supported"))
+            })
+        };
+        let params = request
+            .params()
+            .map(|params| serde_json::from_str(params.get()).unwrap())
+            .unwrap_or_default();
+        self.requests.lock().unwrap().push((request.method().to_string(), params));
+        if self.hanging == Some(request.method()) {
+            return Box::pin(futures::future::pending())
+        }
+
+        let payload = match self.respond(request.method(), &params) {
+            Ok(value) => {
+                ResponsePayload::Success(RawValue::from_string(value.to_string()).unwrap())
+            }
+            Err(err) => ResponsePayload::Failure(err),
+        };
+        let response = Response { id: request.id().clone(), payload };
+        let latency = self.latency;
+        Box::pin(async move {
+            tokio::time::sleep(latency).await;
+            Ok(ResponsePacket::Single(response))
+        })
+    }
+}
+
+/// Access list created for transactions, accessing the first slot of the contract.
+pub(super) fn access_list() -> AccessList {
+    AccessList(vec![AccessListItem {
+        address: STATE_ROOT_CONTRACT_ADDRESS,
+        storage_keys: vec![B256::ZERO],
+    }])
+}
+
+/// Log output captured by a test subscriber.
+#[derive(Debug, Clone, Default)]
+pub(super) struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl CapturedLogs {
+    /// Returns the captured log lines.
+    pub(super) fn lines(&self) -> Vec<String> {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).lines().map(String::from).collect()
+    }
+}
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for CapturedLogs {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Returns a JSON-RPC server error with the given message.
+pub(super) fn rpc_error(message: &str) -> ErrorPayload {
+    ErrorPayload { code: -32000, message: message.into(), data: None }
+}
+
+/// Returns the values of all metrics recorded since the last snapshot by their name.
+pub(super) fn metric_values(snapshotter: &Snapshotter) -> HashMap<String, DebugValue> {
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| (key.key().name().to_string(), value))
+        .collect()
+}
+
+/// Creates a chain of the given blocks, every transaction of which emits a
+/// [`CounterContract::Incremented`] event from the given address.
+pub(super) fn counter_chain(
+    rng: &mut impl Rng,
+    numbers: RangeInclusive<u64>,
+    tx_count: u8,
+    address: Address,
+) -> eyre::Result<Chain> {
+    chain_with_log(rng, numbers, tx_count, |_| incremented_log(address))
+}
+
+/// Returns a [`CounterContract::Incremented`] log emitted from the given address.
+pub(super) fn incremented_log(address: Address) -> Log {
+    let data = LogData::new_unchecked(
+        vec![CounterContract::Incremented::SIGNATURE_HASH],
+        Default::default(),
+    );
+    Log { address, data }
+}
+
+/// Creates a chain of the given blocks, every transaction of which emits the log returned for
+/// its block number.
+pub(super) fn chain_with_log(
+    rng: &mut impl Rng,
+    numbers: RangeInclusive<u64>,
+    tx_count: u8,
+    log: impl Fn(BlockNumber) -> Log,
+) -> eyre::Result<Chain> {
+    let first = *numbers.start();
+    let mut blocks = Vec::new();
+    let mut receipts = Vec::new();
+    for number in numbers {
+        let block = random_block(
+            rng,
+            number,
+            BlockParams { tx_count: Some(tx_count), ..Default::default() },
+        )
+        .seal_with_senders()
+        .ok_or_eyre("failed to recover senders")?;
+        receipts.push(
+            block
+                .body
+                .transactions
+                .iter()
+                .map(|tx| {
+                    Some(Receipt {
+                        tx_type: tx.tx_type(),
+                        success: true,
+                        logs: vec![log(number)],
+                        ..Default::default()
+                    })
+                })
+                .collect(),
+        );
+        blocks.push(block);
+    }
+    let outcome = ExecutionOutcome::new(
+        Default::default(),
+        Receipts { receipt_vec: receipts },
+        first,
+        vec![],
+    );
+    Ok(Chain::new(blocks, outcome, None))
+}
@@ -0,0 +1,52 @@
+//! The persisted height of the last state root anchored on L1.
+
+use alloy_primitives::BlockNumber;
+use std::path::PathBuf;
+
+/// Name of the file in the datadir the [`Watermark`] is persisted to.
+pub(super) const L1_WATERMARK_FILE: &str = "t1-l1-watermark";
+
+/// Highest block number whose state root was anchored on L1.
+///
+/// The watermark is persisted to a file, so that state roots anchored before a restart are not
+/// submitted again when the notifications are re-delivered.
+#[derive(Debug)]
+pub struct Watermark {
+    path: PathBuf,
+    height: Option<BlockNumber>,
+}
+
+impl Watermark {
+    /// Loads the watermark from the given file, starting without one if the file doesn't exist.
+    pub fn load(path: PathBuf) -> eyre::Result<Self> {
+        let height =
+            if path.exists() {
+                let contents = reth_fs_util::read_to_string(&path)?;
+                Some(contents.trim().parse().map_err(|err| {
+                    eyre::eyre!("invalid L1 watermark in {}: {err}", path.display())
+                })?)
+            } else {
+                None
+            };
+        Ok(Self { path, height })
+    }
+
+    /// Returns the highest block number anchored on L1, if any.
+    pub const fn height(&self) -> Option<BlockNumber> {
+        self.height
+    }
+
+    /// Returns `true` if the state root of the given block number has already been anchored.
+    pub fn is_anchored(&self, number: BlockNumber) -> bool {
+        self.height.is_some_and(|height| number <= height)
+    }
+
+    /// Atomically persists the given block number as the new watermark.
+    ///
+    /// The watermark may move backwards, when L1 is corrected after a reorg or revert.
+    pub fn update(&mut self, number: BlockNumber) -> eyre::Result<()> {
+        reth_fs_util::atomic_write_file(&self.path, |file| write!(file, "{number}"))?;
+        self.height = Some(number);
+        Ok(())
+    }
+}
@@ -18,6 +18,11 @@
 //!   calls to the logging component is made.
 //! - `min-debug-logs`: Disables all logs below `debug` level.
 //! - `min-trace-logs`: Disables all logs below `trace` level.
+//! - `l1-bridge`: Enabled by default. Includes the L1 bridge ExEx, which anchors the state roots of
+//!   the committed blocks on L1, along with its CLI arguments, `t1` commands and `t1` RPC
+//!   namespace, see the `bridge` module. Without it the binary is a plain Ethereum node.
+//! - `test-utils`: Enables `l1-bridge` and exports its test utilities, e.g. the
+//!   `MockStateRootSink` recording the state roots instead of anchoring them on L1.
 
 #![doc(
     html_logo_url = "https://raw.githubusercontent.com/paradigmxyz/reth/main/assets/reth-docs.png",
@@ -27,6 +32,8 @@
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+#[cfg(feature = "l1-bridge")]
+pub mod bridge;
 pub mod cli;
 pub mod commands;
 
@@ -3,171 +3,28 @@
 #[global_allocator]
 static ALLOC: reth_cli_util::allocator::Allocator = reth_cli_util::allocator::new_allocator();
 
-use std::future::Future;
 use clap::{Args, Parser};
 use reth::{args::utils::DefaultChainSpecParser, cli::Cli};
 use reth_node_builder::{
     engine_tree_config::{
-        TreeConfig, DEFAULT_MEMORY_BLOCK_BUFFER_TARGET, DEFAULT_PERSISTENCE_THRESHOLD,
+        TreeConfig, DEFAULT_MAX_EXECUTE_BLOCK_BATCH_SIZE, DEFAULT_MEMORY_BLOCK_BUFFER_TARGET,
+        DEFAULT_PERSISTENCE_THRESHOLD,
     },
     EngineNodeLauncher,
 };
 use reth_node_ethereum::{node::EthereumAddOns, EthereumNode};
 use reth_provider::providers::BlockchainProvider2;
 
-use alloy_sol_types::{sol, SolEventInterface};
-use alloy_primitives::{Address, address};
-use futures::StreamExt;
-use tracing::info;
-use reth_execution_types::Chain;
-use reth_primitives::{SealedBlockWithSenders, TransactionSigned};
-
-use reth_exex::{ExExContext, ExExEvent, ExExNotification};
-use reth_node_api::FullNodeComponents;
-
-sol!(CounterContract, "counter_abi.json");
-use CounterContract::{CounterContractEvents};
-use secp256k1::SecretKey;
-use std::str::FromStr;
-use web3::contract::{Contract, Options};
-use web3::signing::SecretKeyRef;
-use web3::transports::Http;
-use web3::types::{H256, Bytes};
-
-const COUNTER_CONTRACT_ADDRESS: Address = address!("b4B46bdAA835F8E4b4d8e208B6559cD267851051");
-const STATE_ROOT_CONTRACT_ADDRESS: &str = "0xb4B46bdAA835F8E4b4d8e208B6559cD267851051";
-const L1_RPC_ADDREESS: &str = "https://possible-spider-driven.ngrok-free.app";
-const PREFUNDED_SECRET: &str = "bcdf20249abf0ed6d944c0288fad489e33f66b3960d9e6229c1cd214ed3bbe31";
-
-#[derive(Debug)]
-pub struct StateRootContract(Contract<Http>);
-
-impl StateRootContract {
-    pub async fn new(web3: &web3::Web3<Http>, address: String) -> Self {
-        let address = web3::types::Address::from_str(&address).unwrap();
-        let contract =
-            Contract::from_json(web3.eth(), address, include_bytes!("../state_root_abi.json")).unwrap();
-        StateRootContract(contract)
-    }
-
-    pub async fn update_state_root(&self, account: &SecretKey, state_root: Bytes) -> H256 {
-        self
-            .0
-            .signed_call(
-                "changeStateRoot",
-                state_root,
-                Options {
-                    gas: Some(5_000_000.into()),
-                    ..Default::default()
-                },
-                SecretKeyRef::new(account),
-            )
-            .await
-        .unwrap()
-    }
-}
-
-/// The initialization logic of the ExEx is just an async function.
-///
-/// During initialization you can wait for resources you need to be up for the ExEx to function,
-/// like a database connection.
-async fn exex_init<Node: FullNodeComponents>(
-    ctx: ExExContext<Node>,
-) -> eyre::Result<impl Future<Output = eyre::Result<()>>> {
-    Ok(exex(ctx))
-}
-
-/// An ExEx is just a future, which means you can implement all of it in an async function!
-///
-/// This ExEx just prints out whenever either a new chain of blocks being added, or a chain of
-/// blocks being re-orged. After processing the chain, emits an [ExExEvent::FinishedHeight] event.
-async fn exex<Node: FullNodeComponents>(mut ctx: ExExContext<Node>) -> eyre::Result<()> {
-    while let Some(notification) = ctx.notifications.next().await {
-        match &notification {
-            ExExNotification::ChainCommitted { new } => {
-                info!(committed_chain = ?new.range(), "Received commit");
-                info!("Current stateRoot is [{}]", new.tip().block.header.state_root);
-                notify_l1(&new).await;
-            }
-            ExExNotification::ChainReorged { old, new } => {
-                info!(from_chain = ?old.range(), to_chain = ?new.range(), "Received reorg");
-            }
-            ExExNotification::ChainReverted { old } => {
-                info!(reverted_chain = ?old.range(), "Received revert");
-            }
-        };
-
-        if let Some(committed_chain) = notification.committed_chain() {
-            ctx.events.send(ExExEvent::FinishedHeight(committed_chain.tip().num_hash()))?;
-        }
-    }
-
-    Ok(())
-}
-
-/// Decode chain of blocks into a flattened list of receipt logs, filter only transactions to the
-/// Counter contract [COUNTER_CONTRACT_ADDRESS] and extract [CounterContractEvents].
-fn decode_chain_into_rollup_events(
-    chain: &Chain,
-) -> Vec<(&SealedBlockWithSenders, &TransactionSigned, CounterContractEvents)> {
-    chain
-        // Get all blocks and receipts
-        .blocks_and_receipts()
-        // Get all receipts
-        .flat_map(|(block, receipts)| {
-            block
-                .body
-                .transactions
-                .iter()
-                .zip(receipts.iter().flatten())
-                .map(move |(tx, receipt)| (block, tx, receipt))
-        })
-        // Get all logs from counter contract
-        .flat_map(|(block, tx, receipt)| {
-            receipt
-                .logs
-                .iter()
-                .filter(|log| log.address == COUNTER_CONTRACT_ADDRESS)
-                .map(move |log| (block, tx, log))
-        })
-        // Decode and filter counter events
-        .filter_map(|(block, tx, log)| {
-            CounterContractEvents::decode_raw_log(log.topics(), &log.data.data, true)
-                .ok()
-                .map(|event| (block, tx, event))
-        })
-        .collect()
-}
-
-
-async fn notify_l1(chain: &Chain) {
-    let events = decode_chain_into_rollup_events(chain);
-
-    for (_, _tx, event) in events {
-        match event {
-            CounterContractEvents::Incremented(..) => {
-                let transport = Http::new(L1_RPC_ADDREESS).unwrap();
-                let web3 = web3::Web3::new(transport);
-                let state_root_contract = StateRootContract::new(
-                    &web3,
-                    STATE_ROOT_CONTRACT_ADDRESS.to_string(),
-                ).await;
-
-                let wallet = SecretKey::from_str(PREFUNDED_SECRET).unwrap();
-
-                let tx_id = state_root_contract
-                    .update_state_root(
-                        &wallet, Bytes::from(chain.tip().block.header.state_root.to_vec())
-                    );
-                info!("I notifed L1 with new state root. txId = [{:#x}]", tx_id.await);
-
-                ()
-            }
-            _ => (),
-        }
-    }
-}
+#[cfg(feature = "l1-bridge")]
+use reth::bridge::{
+    exex_init, AnchorEvents, BridgeStatusHandle, ExExArgs, PrintConfig, SubmissionHistory,
+    T1ApiServer, T1Cli, T1Rpc, L1_HISTORY_FILE,
+};
 
+/// Upper bound of [`EngineArgs::persistence_threshold`].
+const MAX_PERSISTENCE_THRESHOLD: u64 = 1_024;
+/// Upper bound of [`EngineArgs::memory_block_buffer_target`].
+const MAX_MEMORY_BLOCK_BUFFER_TARGET: u64 = 1_024;
 
 /// Parameters for configuring the engine
 #[derive(Debug, Clone, Args, PartialEq, Eq)]
@@ -177,13 +34,51 @@ pub struct EngineArgs {
     #[arg(long = "engine.experimental", default_value = "false")]
     pub experimental: bool,
 
-    /// Configure persistence threshold for engine experimental.
-    #[arg(long = "engine.persistence-threshold", requires = "experimental", default_value_t = DEFAULT_PERSISTENCE_THRESHOLD)]
+    /// Configure the persistence threshold of the engine.
+    #[arg(long = "engine.persistence-threshold", default_value_t = DEFAULT_PERSISTENCE_THRESHOLD)]
     pub persistence_threshold: u64,
 
-    /// Configure the target number of blocks to keep in memory.
-    #[arg(long = "engine.memory-block-buffer-target", requires = "experimental", default_value_t = DEFAULT_MEMORY_BLOCK_BUFFER_TARGET)]
+    /// Configure the target number of blocks to keep in memory, at least 1.
+    #[arg(
+        long = "engine.memory-block-buffer-target",
+        value_parser = clap::value_parser!(u64).range(1..),
+        default_value_t = DEFAULT_MEMORY_BLOCK_BUFFER_TARGET
+    )]
     pub memory_block_buffer_target: u64,
+
+    /// Configure the maximum number of blocks to execute sequentially in a batch.
+    #[arg(
+        long = "engine.max-execute-block-batch-size",
+        default_value_t = DEFAULT_MAX_EXECUTE_BLOCK_BATCH_SIZE
+    )]
+    pub max_execute_block_batch_size: usize,
+}
+
+impl EngineArgs {
+    /// Checks that the values are within sane bounds.
+    ///
+    /// The memory block buffer target has to be non-zero, and neither value may exceed
+    /// [`MAX_PERSISTENCE_THRESHOLD`] and [`MAX_MEMORY_BLOCK_BUFFER_TARGET`] respectively.
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.persistence_threshold > MAX_PERSISTENCE_THRESHOLD {
+            eyre::bail!(
+                "engine persistence threshold {} exceeds the maximum of \
+                 {MAX_PERSISTENCE_THRESHOLD}",
+                self.persistence_threshold
+            )
+        }
+        if self.memory_block_buffer_target == 0 {
+            eyre::bail!("engine memory block buffer target must be at least 1")
+        }
+        if self.memory_block_buffer_target > MAX_MEMORY_BLOCK_BUFFER_TARGET {
+            eyre::bail!(
+                "engine memory block buffer target {} exceeds the maximum of \
+                 {MAX_MEMORY_BLOCK_BUFFER_TARGET}",
+                self.memory_block_buffer_target
+            )
+        }
+        Ok(())
+    }
 }
 
 impl Default for EngineArgs {
@@ -192,10 +87,42 @@ impl Default for EngineArgs {
             experimental: false,
             persistence_threshold: DEFAULT_PERSISTENCE_THRESHOLD,
             memory_block_buffer_target: DEFAULT_MEMORY_BLOCK_BUFFER_TARGET,
+            max_execute_block_batch_size: DEFAULT_MAX_EXECUTE_BLOCK_BATCH_SIZE,
         }
     }
 }
 
+/// Checks that the engine tree config built from the [`EngineArgs`] is consistent as a whole.
+///
+/// The engine keeps at most `persistence_threshold` blocks in memory before persisting down to
+/// `memory_block_buffer_target` blocks, so the target can't exceed the threshold. Blocks are
+/// executed in batches of at least one block.
+fn validate_tree_config(config: &TreeConfig) -> eyre::Result<()> {
+    if config.memory_block_buffer_target() > config.persistence_threshold() {
+        eyre::bail!(
+            "engine memory block buffer target {} exceeds the persistence threshold {}",
+            config.memory_block_buffer_target(),
+            config.persistence_threshold()
+        )
+    }
+    if config.max_execute_block_batch_size() == 0 {
+        eyre::bail!("engine max execute block batch size must be at least 1")
+    }
+    Ok(())
+}
+
+/// Additional parameters of the reth binary
+#[derive(Debug, Clone, Default, Args, PartialEq, Eq)]
+pub struct RethArgs {
+    #[command(flatten)]
+    pub engine: EngineArgs,
+
+    // Parameters of the L1 bridge, only available with the `l1-bridge` feature
+    #[cfg(feature = "l1-bridge")]
+    #[command(flatten)]
+    pub exex: ExExArgs,
+}
+
 fn main() {
     reth_cli_util::sigsegv_handler::install();
 
@@ -204,16 +131,60 @@ fn main() {
         std::env::set_var("RUST_BACKTRACE", "1");
     }
 
+    // The commands of the L1 bridge are dispatched before the node CLI, which can't be extended
+    // with subcommands
+    #[cfg(feature = "l1-bridge")]
+    if std::env::args().nth(1).as_deref() == Some("t1") {
+        if let Err(err) = T1Cli::parse_from(std::env::args().skip(1)).run() {
+            eprintln!("Error: {err:?}");
+            std::process::exit(1);
+        }
+        return
+    }
+
     if let Err(err) =
-        Cli::<DefaultChainSpecParser, EngineArgs>::parse().run(|builder, engine_args| async move {
+        Cli::<DefaultChainSpecParser, RethArgs>::parse().run(|builder, args| async move {
+                    let engine_args = args.engine;
+                    engine_args.validate()?;
                     let engine_tree_config = TreeConfig::default()
                         .with_persistence_threshold(engine_args.persistence_threshold)
-                        .with_memory_block_buffer_target(engine_args.memory_block_buffer_target);
-                    let handle = builder
+                        .with_memory_block_buffer_target(engine_args.memory_block_buffer_target)
+                        .with_max_execute_block_batch_size(
+                            engine_args.max_execute_block_batch_size,
+                        );
+                    validate_tree_config(&engine_tree_config)?;
+                    // The configuration of the L1 bridge is resolved from the node's, so it can be
+                    // printed without launching the node
+                    #[cfg(feature = "l1-bridge")]
+                    if args.exex.print_config == Some(PrintConfig::Exit) {
+                        let config = args.exex.resolve_config(builder.config())?;
+                        println!("{config:#?}");
+                        return Ok(())
+                    }
+                    let builder = builder
                         .with_types_and_provider::<EthereumNode, BlockchainProvider2<_>>()
                         .with_components(EthereumNode::components())
-                        .with_add_ons::<EthereumAddOns>()
-                        .install_exex("t1", exex_init)
+                        .with_add_ons::<EthereumAddOns>();
+                    // Without the L1 bridge, the binary is a plain Ethereum node
+                    #[cfg(feature = "l1-bridge")]
+                    let builder = {
+                        let exex_args = args.exex;
+                        let status = BridgeStatusHandle::default();
+                        let anchor_events = AnchorEvents::default();
+                        let rpc = T1Rpc::new(status.clone());
+                        builder
+                            .extend_rpc_modules(move |ctx| {
+                                let data_dir = ctx.config().datadir();
+                                let history = data_dir.data_dir().join(L1_HISTORY_FILE);
+                                let rpc = rpc.with_history(SubmissionHistory::new(history));
+                                ctx.modules.merge_configured(rpc.into_rpc())?;
+                                Ok(())
+                            })
+                            .install_exex(exex_args.name.clone(), move |ctx| {
+                                exex_init(ctx, exex_args, status, anchor_events)
+                            })
+                    };
+                    let handle = builder
                         .launch_with_fn(|builder| {
                             let launcher = EngineNodeLauncher::new(
                                 builder.task_executor().clone(),
@@ -234,7 +205,6 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use clap::Parser;
 
     /// A helper type to parse Args more easily
     #[derive(Parser)]
@@ -248,5 +218,228 @@ mod tests {
         let default_args = EngineArgs::default();
         let args = CommandParser::<EngineArgs>::parse_from(["reth"]).args;
         assert_eq!(args, default_args);
+        assert_eq!(args.max_execute_block_batch_size, DEFAULT_MAX_EXECUTE_BLOCK_BATCH_SIZE);
+
+        let args = CommandParser::<EngineArgs>::parse_from([
+            "reth",
+            "--engine.max-execute-block-batch-size",
+            "16",
+        ])
+        .args;
+        assert_eq!(args, EngineArgs { max_execute_block_batch_size: 16, ..default_args.clone() });
+
+        // Both values can be tuned without the experimental engine
+        let args =
+            CommandParser::<EngineArgs>::parse_from(["reth", "--engine.persistence-threshold", "5"])
+                .args;
+        assert_eq!(args, EngineArgs { persistence_threshold: 5, ..default_args.clone() });
+
+        let args = CommandParser::<EngineArgs>::parse_from([
+            "reth",
+            "--engine.memory-block-buffer-target",
+            "3",
+        ])
+        .args;
+        assert_eq!(args, EngineArgs { memory_block_buffer_target: 3, ..default_args.clone() });
+
+        let args = CommandParser::<EngineArgs>::parse_from([
+            "reth",
+            "--engine.experimental",
+            "--engine.persistence-threshold",
+            "5",
+            "--engine.memory-block-buffer-target",
+            "3",
+        ])
+        .args;
+        let expected = EngineArgs {
+            experimental: true,
+            persistence_threshold: 5,
+            memory_block_buffer_target: 3,
+            ..default_args.clone()
+        };
+        assert_eq!(args, expected);
+
+        // The memory block buffer target can't be zero
+        let result = CommandParser::<EngineArgs>::try_parse_from([
+            "reth",
+            "--engine.memory-block-buffer-target",
+            "0",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_validate_engine_args() {
+        assert!(EngineArgs::default().validate().is_ok());
+
+        let args = |persistence_threshold, memory_block_buffer_target| EngineArgs {
+            persistence_threshold,
+            memory_block_buffer_target,
+            ..Default::default()
+        };
+        assert!(args(0, 1).validate().is_ok());
+        assert!(args(MAX_PERSISTENCE_THRESHOLD, MAX_MEMORY_BLOCK_BUFFER_TARGET).validate().is_ok());
+
+        let err = args(0, 0).validate().unwrap_err();
+        assert!(err.to_string().contains("at least 1"), "{err}");
+        let err = args(MAX_PERSISTENCE_THRESHOLD + 1, 1).validate().unwrap_err();
+        assert!(err.to_string().contains("persistence threshold"), "{err}");
+        let err = args(0, MAX_MEMORY_BLOCK_BUFFER_TARGET + 1).validate().unwrap_err();
+        assert!(err.to_string().contains("memory block buffer target"), "{err}");
+        assert!(args(u64::MAX, u64::MAX).validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_tree_config() {
+        assert!(validate_tree_config(&TreeConfig::default()).is_ok());
+
+        let config = |persistence_threshold, memory_block_buffer_target| {
+            TreeConfig::default()
+                .with_persistence_threshold(persistence_threshold)
+                .with_memory_block_buffer_target(memory_block_buffer_target)
+        };
+        assert!(validate_tree_config(&config(5, 5)).is_ok());
+
+        // Each value is valid on its own, but the target can't exceed the threshold
+        let args = EngineArgs {
+            persistence_threshold: 0,
+            memory_block_buffer_target: 1,
+            ..Default::default()
+        };
+        assert!(args.validate().is_ok());
+        let err = validate_tree_config(&config(0, 1)).unwrap_err();
+        assert!(err.to_string().contains("exceeds the persistence threshold"), "{err}");
+
+        let err = validate_tree_config(&TreeConfig::default().with_max_execute_block_batch_size(0))
+            .unwrap_err();
+        assert!(err.to_string().contains("batch size must be at least 1"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_reth_args() {
+        let args = CommandParser::<RethArgs>::parse_from(["reth"]).args;
+        assert_eq!(args, RethArgs::default());
+
+        // The arguments of the L1 bridge only exist if it's compiled in
+        let result = CommandParser::<RethArgs>::try_parse_from(["reth", "--exex.dry-run"]);
+        assert_eq!(result.is_ok(), cfg!(feature = "l1-bridge"));
+    }
+
+    #[test]
+    #[cfg(feature = "l1-bridge")]
+    fn test_parse_exex_args() {
+        let args = CommandParser::<ExExArgs>::parse_from(["reth"]).args;
+        assert_eq!(args, ExExArgs::default());
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth", "--exex.dry-run"]).args;
+        assert!(args.dry_run);
+
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.submit-interval-ms", "500"])
+                .args;
+        assert_eq!(args.submit_interval_ms, 500);
+
+        let args = CommandParser::<ExExArgs>::parse_from([
+            "reth",
+            "--exex.confirm-poll-interval-ms",
+            "100",
+            "--exex.confirm-timeout-ms",
+            "60000",
+        ])
+        .args;
+        assert_eq!((args.confirm_poll_interval_ms, args.confirm_timeout_ms), (100, 60_000));
+        assert!(CommandParser::<ExExArgs>::try_parse_from([
+            "reth",
+            "--exex.confirm-poll-interval-ms",
+            "0"
+        ])
+        .is_err());
+
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.submit-timeout-blocks", "5"])
+                .args;
+        assert_eq!(args.submit_timeout_blocks, Some(5));
+        assert!(CommandParser::<ExExArgs>::try_parse_from([
+            "reth",
+            "--exex.submit-timeout-blocks",
+            "5",
+            "--exex.confirm-timeout-ms",
+            "60000"
+        ])
+        .is_err());
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.l1_rpc_timeout_ms, 30_000);
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.l1-rpc-timeout-ms", "0"]).args;
+        assert_eq!(args.l1_rpc_timeout_ms, 0);
+
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.start-height", "1000"]).args;
+        assert_eq!(args.start_height, 1000);
+
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.anchor-every", "3"]).args;
+        assert_eq!(args.anchor_every, 3);
+        assert!(
+            CommandParser::<ExExArgs>::try_parse_from(["reth", "--exex.anchor-every", "0"])
+                .is_err()
+        );
+
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.submit-jitter-ms", "500"]).args;
+        assert_eq!(args.submit_jitter_ms, 500);
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth", "--exex.log-events"]).args;
+        assert!(args.log_events);
+
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.reanchor-on-mismatch"]).args;
+        assert!(args.reanchor_on_mismatch);
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth", "--exex.allow-same-chain"]).args;
+        assert!(args.allow_same_chain);
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth", "--exex.anchor-finalized"]).args;
+        assert!(args.anchor_finalized);
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth", "--exex.reorg-only"]).args;
+        assert!(args.reorg_only);
+        assert!(CommandParser::<ExExArgs>::try_parse_from([
+            "reth",
+            "--exex.reorg-only",
+            "--exex.anchor-finalized"
+        ])
+        .is_err());
+
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.verify-root-locally"]).args;
+        assert!(args.verify_root_locally);
+
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.contract-abi-dir", "/etc/t1"])
+                .args;
+        assert_eq!(args.contract_abi_dir, Some(std::path::PathBuf::from("/etc/t1")));
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.name, reth::bridge::EXEX_NAME);
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.name", "t1-replica"]).args;
+        assert_eq!(args.name, "t1-replica");
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth", "--exex.disable"]).args;
+        assert!(args.disable);
+
+        let args = CommandParser::<ExExArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.print_config, None);
+        let args = CommandParser::<ExExArgs>::parse_from(["reth", "--exex.print-config"]).args;
+        assert_eq!(args.print_config, Some(PrintConfig::Continue));
+        let args =
+            CommandParser::<ExExArgs>::parse_from(["reth", "--exex.print-config", "exit"]).args;
+        assert_eq!(args.print_config, Some(PrintConfig::Exit));
+        assert!(
+            CommandParser::<ExExArgs>::try_parse_from(["reth", "--exex.print-config", "maybe"])
+                .is_err()
+        );
     }
 }
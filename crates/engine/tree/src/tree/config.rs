@@ -9,7 +9,8 @@ pub const DEFAULT_MEMORY_BLOCK_BUFFER_TARGET: u64 = 2;
 const DEFAULT_BLOCK_BUFFER_LIMIT: u32 = 256;
 const DEFAULT_MAX_INVALID_HEADER_CACHE_LENGTH: u32 = 256;
 
-const DEFAULT_MAX_EXECUTE_BLOCK_BATCH_SIZE: usize = 4;
+/// Maximum number of blocks to execute sequentially in a batch.
+pub const DEFAULT_MAX_EXECUTE_BLOCK_BATCH_SIZE: usize = 4;
 
 /// The configuration of the engine tree.
 #[derive(Debug)]